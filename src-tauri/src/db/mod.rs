@@ -1,15 +1,20 @@
+use crate::fs::io_error_kind;
 use directories::BaseDirs;
 use serde::{Deserialize, Serialize};
 use sqlite::{Connection, State as SqliteState};
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::fs;
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use tauri::State;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tauri::{AppHandle, Emitter, State};
 
 // Helper function to get the app data directory
-fn get_app_data_dir() -> Result<PathBuf, DbError> {
+pub(crate) fn get_app_data_dir() -> Result<PathBuf, DbError> {
     let base_dirs = BaseDirs::new().ok_or_else(|| DbError {
         message: "Failed to resolve local app data directory".to_string(),
+        kind: "NotFound".to_string(),
     })?;
 
     let data_dir = base_dirs.data_local_dir().join("skybox");
@@ -17,6 +22,7 @@ fn get_app_data_dir() -> Result<PathBuf, DbError> {
     // Create the directory if it doesn't exist
     fs::create_dir_all(&data_dir).map_err(|e| DbError {
         message: format!("Failed to create app data directory: {}", e),
+        kind: io_error_kind(&e),
     })?;
 
     Ok(data_dir)
@@ -25,6 +31,7 @@ fn get_app_data_dir() -> Result<PathBuf, DbError> {
 fn get_legacy_database_path() -> Result<PathBuf, DbError> {
     let base_dirs = BaseDirs::new().ok_or_else(|| DbError {
         message: "Failed to resolve local app data directory".to_string(),
+        kind: "NotFound".to_string(),
     })?;
 
     Ok(base_dirs
@@ -52,6 +59,7 @@ fn migrate_legacy_database_if_needed(new_db_path: &Path) -> Result<(), DbError>
                 parent_dir.display(),
                 e
             ),
+            kind: io_error_kind(&e),
         })?;
     }
 
@@ -62,6 +70,7 @@ fn migrate_legacy_database_if_needed(new_db_path: &Path) -> Result<(), DbError>
             new_db_path.display(),
             e
         ),
+        kind: io_error_kind(&e),
     })?;
 
     for sidecar_suffix in ["-wal", "-shm"] {
@@ -90,23 +99,335 @@ fn migrate_legacy_database_if_needed(new_db_path: &Path) -> Result<(), DbError>
                 new_sidecar.display(),
                 e
             ),
+            kind: io_error_kind(&e),
         })?;
     }
 
     Ok(())
 }
 
+fn sidecar_path(db_path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", db_path.to_string_lossy(), suffix))
+}
+
+// Imported databases are staged next to the live file and swapped in on the
+// next launch, since the open connection cannot be replaced in place.
+fn get_pending_import_path(db_path: &Path) -> PathBuf {
+    sidecar_path(db_path, ".import")
+}
+
+fn apply_pending_import_if_needed(db_path: &Path) -> Result<(), DbError> {
+    let pending_path = get_pending_import_path(db_path);
+    if !pending_path.exists() {
+        return Ok(());
+    }
+
+    if db_path.exists() {
+        let backup_path = sidecar_path(db_path, ".bak");
+        fs::copy(db_path, &backup_path).map_err(|e| DbError {
+            message: format!(
+                "Failed to back up database to {} before import: {}",
+                backup_path.display(),
+                e
+            ),
+            kind: io_error_kind(&e),
+        })?;
+    }
+
+    for sidecar_suffix in ["-wal", "-shm"] {
+        let sidecar = sidecar_path(db_path, sidecar_suffix);
+        if sidecar.exists() {
+            fs::remove_file(&sidecar).map_err(|e| DbError {
+                message: format!(
+                    "Failed to remove database sidecar {}: {}",
+                    sidecar.display(),
+                    e
+                ),
+                kind: io_error_kind(&e),
+            })?;
+        }
+    }
+
+    fs::rename(&pending_path, db_path).map_err(|e| DbError {
+        message: format!(
+            "Failed to apply imported database from {}: {}",
+            pending_path.display(),
+            e
+        ),
+        kind: io_error_kind(&e),
+    })?;
+
+    log::info!("Applied imported database from {}", pending_path.display());
+    Ok(())
+}
+
+// Tables every SkyBox database has; used to reject foreign SQLite files on import
+const REQUIRED_TABLES: [&str; 6] = [
+    "settings",
+    "recent_paths",
+    "favorites",
+    "session",
+    "telegram_messages",
+    "telegram_saved_items",
+];
+
+fn validate_skybox_database(path: &Path) -> Result<(), DbError> {
+    let conn = sqlite::Connection::open_with_flags(path, sqlite::OpenFlags::new().with_read_only())
+        .map_err(|e| DbError {
+            message: format!("Failed to open {} as a database: {}", path.display(), e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+    let mut statement = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")
+        .map_err(|e| DbError {
+            message: format!("{} is not a valid SQLite database: {}", path.display(), e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+    let mut tables = Vec::new();
+    while let Ok(SqliteState::Row) = statement.next() {
+        tables.push(statement.read::<String, usize>(0).unwrap_or_default());
+    }
+
+    let missing: Vec<&str> = REQUIRED_TABLES
+        .iter()
+        .copied()
+        .filter(|table| !tables.iter().any(|name| name == table))
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(DbError {
+            message: format!(
+                "{} is not a SkyBox database (missing tables: {})",
+                path.display(),
+                missing.join(", ")
+            ),
+            kind: "NotADatabase".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
 // Helper function to get the full database path
 fn get_database_path() -> Result<PathBuf, DbError> {
     let app_data_dir = get_app_data_dir()?;
     let db_path = app_data_dir.join("Skybox.db");
     migrate_legacy_database_if_needed(&db_path)?;
+    apply_pending_import_if_needed(&db_path)?;
     Ok(db_path)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DbError {
     pub message: String,
+    /// SQLite result category (e.g. `Busy`, `Constraint`) or `io::ErrorKind` name for file operations.
+    #[serde(default)]
+    pub kind: String,
+}
+
+const SAVED_FOLDERS_LOCALE_SETTING_KEY: &str = "tg_saved_folders_locale";
+pub(crate) const DOWNLOAD_DIRECTORY_SETTING_KEY: &str = "download_directory";
+pub(crate) const SEARCH_ROOT_SETTING_KEY: &str = "search_root";
+pub(crate) const SETTING_CHANGED_EVENT: &str = "setting-changed";
+
+// Payload of `setting-changed`, emitted after `db_set_setting` stores a value
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SettingChanged {
+    pub key: String,
+    pub value: String,
+}
+pub(crate) const DEFAULT_SAVED_FOLDERS: [&str; 6] = [
+    "Images",
+    "Videos",
+    "Audios",
+    "Documents",
+    "Notes",
+    "Recycle Bin",
+];
+
+// Display names for the default folders; unknown locales fall back to English
+fn saved_folder_display_name(folder_name: &'static str, locale: &str) -> &'static str {
+    let language = locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match (language.as_str(), folder_name) {
+        ("de", "Images") => "Bilder",
+        ("de", "Audios") => "Audio",
+        ("de", "Documents") => "Dokumente",
+        ("de", "Notes") => "Notizen",
+        ("de", "Recycle Bin") => "Papierkorb",
+        ("es", "Images") => "Imágenes",
+        ("es", "Videos") => "Vídeos",
+        ("es", "Documents") => "Documentos",
+        ("es", "Notes") => "Notas",
+        ("es", "Recycle Bin") => "Papelera",
+        ("fr", "Videos") => "Vidéos",
+        ("fr", "Recycle Bin") => "Corbeille",
+        ("ru", "Images") => "Изображения",
+        ("ru", "Videos") => "Видео",
+        ("ru", "Audios") => "Аудио",
+        ("ru", "Documents") => "Документы",
+        ("ru", "Notes") => "Заметки",
+        ("ru", "Recycle Bin") => "Корзина",
+        _ => folder_name,
+    }
+}
+
+fn sqlite_error_kind(error: &sqlite::Error) -> String {
+    // Extended result codes carry the primary code in the low byte
+    let kind = match error.code.map(|code| code & 0xff) {
+        Some(5) => "Busy",
+        Some(6) => "Locked",
+        Some(7) => "OutOfMemory",
+        Some(8) => "ReadOnly",
+        Some(10) => "Io",
+        Some(11) => "Corrupt",
+        Some(13) => "StorageFull",
+        Some(14) => "CantOpen",
+        Some(19) => "Constraint",
+        Some(20) => "TypeMismatch",
+        Some(21) => "Misuse",
+        Some(26) => "NotADatabase",
+        _ => "Sqlite",
+    };
+
+    kind.to_string()
+}
+
+// Reads a row selected with the column order used by the telegram_saved_items listings
+fn read_telegram_saved_item_row(statement: &sqlite::Statement) -> TelegramSavedItem {
+    TelegramSavedItem {
+        chat_id: statement.read::<i64, usize>(0).unwrap_or(0),
+        message_id: statement.read::<i64, usize>(1).unwrap_or(0) as i32,
+        thumbnail: statement.read::<Option<String>, usize>(2).unwrap_or(None),
+        file_type: statement
+            .read::<String, usize>(3)
+            .unwrap_or_else(|_| "file".to_string()),
+        file_unique_id: statement.read::<String, usize>(4).unwrap_or_default(),
+        file_size: statement.read::<i64, usize>(5).unwrap_or(0),
+        file_name: statement.read::<String, usize>(6).unwrap_or_default(),
+        file_caption: statement.read::<Option<String>, usize>(7).unwrap_or(None),
+        file_path: statement.read::<String, usize>(8).unwrap_or_default(),
+        recycle_origin_path: statement.read::<Option<String>, usize>(9).unwrap_or(None),
+        modified_date: statement.read::<String, usize>(10).unwrap_or_default(),
+        owner_id: statement.read::<String, usize>(11).unwrap_or_default(),
+        pinned: statement.read::<i64, usize>(12).unwrap_or(0) != 0,
+        image_width: statement.read::<Option<i64>, usize>(13).unwrap_or(None),
+        image_height: statement.read::<Option<i64>, usize>(14).unwrap_or(None),
+        indexed_at: statement.read::<Option<String>, usize>(15).unwrap_or(None),
+        audio_duration_secs: statement.read::<Option<i64>, usize>(16).unwrap_or(None),
+        audio_title: statement.read::<Option<String>, usize>(17).unwrap_or(None),
+        audio_performer: statement.read::<Option<String>, usize>(18).unwrap_or(None),
+    }
+}
+
+// Run `f` inside BEGIN/COMMIT, rolling back if it fails
+fn with_transaction<T>(
+    conn: &PooledConnection<'_>,
+    f: impl FnOnce(&Connection) -> Result<T, DbError>,
+) -> Result<T, DbError> {
+    // IMMEDIATE takes the write lock up front, where the busy timeout applies; a
+    // deferred transaction upgrading to a write fails at once if another connection wrote
+    conn.execute("BEGIN IMMEDIATE").map_err(|e| DbError {
+        message: format!("Failed to begin transaction: {}", e),
+        kind: sqlite_error_kind(&e),
+    })?;
+
+    match f(conn) {
+        Ok(value) => {
+            conn.execute("COMMIT").map_err(|e| DbError {
+                message: format!("Failed to commit transaction: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            Ok(value)
+        }
+        Err(error) => {
+            if let Err(e) = conn.execute("ROLLBACK") {
+                log::error!("Failed to roll back transaction: {}", e);
+                // It may still be inside the transaction; the next checkout must not inherit it
+                conn.discard();
+            }
+            Err(error)
+        }
+    }
+}
+
+// Whether `table` already has `column`, for migrations that add columns
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool, DbError> {
+    let mut statement = conn
+        .prepare(format!("PRAGMA table_info({})", table))
+        .map_err(|e| DbError {
+            message: format!("Failed to inspect {} schema: {}", table, e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+    while let Ok(SqliteState::Row) = statement.next() {
+        let name: String = statement.read(1).unwrap_or_default();
+        if name == column {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+// Insert one favorite and return its id; the caller holds the transaction so the
+// rowid read always belongs to this insert
+fn insert_favorite(conn: &Connection, path: &str, label: &str) -> Result<i32, DbError> {
+    let mut statement = conn
+        .prepare("INSERT INTO favorites (path, label) VALUES (?, ?)")
+        .map_err(|e| DbError {
+            message: format!("Failed to prepare statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+    statement.bind((1, path)).map_err(|e| DbError {
+        message: format!("Failed to bind path parameter: {}", e),
+        kind: sqlite_error_kind(&e),
+    })?;
+    statement.bind((2, label)).map_err(|e| DbError {
+        message: format!("Failed to bind label parameter: {}", e),
+        kind: sqlite_error_kind(&e),
+    })?;
+
+    statement.next().map_err(|e| DbError {
+        message: format!("Failed to execute statement: {}", e),
+        kind: sqlite_error_kind(&e),
+    })?;
+
+    // Get the last inserted ID using a separate query since sqlite crate doesn't expose last_insert_rowid
+    let mut id_statement = conn
+        .prepare("SELECT last_insert_rowid()")
+        .map_err(|e| DbError {
+            message: format!("Failed to prepare id query: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+    id_statement.next().map_err(|e| DbError {
+        message: format!("Failed to execute id query: {}", e),
+        kind: sqlite_error_kind(&e),
+    })?;
+
+    let id: i64 = id_statement.read::<i64, usize>(0).map_err(|e| DbError {
+        message: format!("Failed to read id: {}", e),
+        kind: sqlite_error_kind(&e),
+    })?;
+
+    Ok(id as i32)
+}
+
+// Main database file plus its WAL, which holds pages not yet checkpointed
+fn database_files_size(db_path: &Path) -> u64 {
+    let wal_path = db_path.with_extension("db-wal");
+    [db_path, wal_path.as_path()]
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -118,6 +439,8 @@ pub struct Session {
     pub first_name: Option<String>,   // User's first name
     pub last_name: Option<String>,    // User's last name
     pub username: Option<String>,     // User's username
+    pub user_id: Option<i64>,         // Telegram user id owning this session
+    pub is_active: bool,              // Session restored on startup
     pub created_at: String,
 }
 
@@ -142,7 +465,15 @@ pub struct Favorite {
     pub label: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
+pub struct FavoritesAdded {
+    /// Ids of the inserted favorites, in input order.
+    pub ids: Vec<i32>,
+    /// Paths left out because they were already favorites or repeated in the batch.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TelegramMessage {
     pub message_id: i32,
     pub chat_id: i64,
@@ -155,6 +486,35 @@ pub struct TelegramMessage {
     pub text: Option<String>,
     pub thumbnail: Option<String>,
     pub file_reference: String,
+    // Set when an upload matched an existing file and was not sent again
+    #[serde(default)]
+    pub deduped: bool,
+    // Set when the device was offline and the upload went to pending_uploads
+    #[serde(default)]
+    pub queued: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PendingUpload {
+    pub id: i64,
+    pub owner_id: String,
+    pub file_name: String,
+    // Copy of the file bytes kept under the app data directory until sent
+    pub local_path: String,
+    pub target_path: Option<String>,
+    /// `pending`, `uploading` or `failed`
+    pub status: String,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    // How a name clash in the target folder is handled, as passed to the upload command
+    #[serde(default)]
+    pub on_conflict: Option<String>,
+    // Failed send attempts so far; reset by a manual retry
+    #[serde(default)]
+    pub attempts: i64,
+    // Unix time after which a failed upload is tried again, None when it won't be
+    #[serde(default)]
+    pub next_retry_at: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -171,17 +531,141 @@ pub struct TelegramSavedItem {
     pub recycle_origin_path: Option<String>,
     pub modified_date: String,
     pub owner_id: String,
+    #[serde(default)]
+    pub pinned: bool,
+    // Pixel size of uploaded images, when it could be read from the file header
+    #[serde(default)]
+    pub image_width: Option<i64>,
+    #[serde(default)]
+    pub image_height: Option<i64>,
+    // When the item first entered the index; kept across moves, renames and re-indexing
+    #[serde(default)]
+    pub indexed_at: Option<String>,
+    // Track info recorded when an audio file is first previewed
+    #[serde(default)]
+    pub audio_duration_secs: Option<i64>,
+    #[serde(default)]
+    pub audio_title: Option<String>,
+    #[serde(default)]
+    pub audio_performer: Option<String>,
+}
+
+// Idle connections kept open for reuse; more are opened while commands overlap
+const MAX_IDLE_CONNECTIONS: usize = 4;
+// How long a connection waits for another one's write lock before failing with Busy
+const BUSY_TIMEOUT_MS: usize = 5000;
+
+// Connections to the database file in WAL mode, so reads run while another
+// connection writes. SQLite serializes the writes themselves.
+struct ConnectionPool {
+    db_path: PathBuf,
+    idle: Mutex<Vec<Connection>>,
+    // Held shared by every checkout and exclusively by `exclusive`
+    gate: RwLock<()>,
+}
+
+// A checked-out connection, returned to the pool when dropped
+struct PooledConnection<'a> {
+    conn: Option<Connection>,
+    pool: &'a ConnectionPool,
+    _shared: Option<RwLockReadGuard<'a, ()>>,
+    _exclusive: Option<RwLockWriteGuard<'a, ()>>,
+    discarded: Cell<bool>,
+}
+
+impl PooledConnection<'_> {
+    // Closes the connection on drop instead of returning it to the pool
+    fn discard(&self) {
+        self.discarded.set(true);
+    }
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn
+            .as_ref()
+            .expect("pooled connection is present until dropped")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take().filter(|_| !self.discarded.get()) {
+            let mut idle = self.pool.idle.lock().unwrap();
+            if idle.len() < MAX_IDLE_CONNECTIONS {
+                idle.push(conn);
+            }
+        }
+    }
+}
+
+fn open_pool_connection(db_path: &Path) -> Result<Connection, DbError> {
+    let mut conn = Connection::open(db_path).map_err(|e| DbError {
+        message: format!("Failed to open database at {}: {}", db_path.display(), e),
+        kind: sqlite_error_kind(&e),
+    })?;
+    conn.set_busy_timeout(BUSY_TIMEOUT_MS)
+        .map_err(|e| DbError {
+            message: format!("Failed to set busy timeout: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+    Ok(conn)
+}
+
+impl ConnectionPool {
+    fn take_connection(&self) -> Result<Connection, DbError> {
+        let idle_conn = self.idle.lock().unwrap().pop();
+        match idle_conn {
+            Some(conn) => Ok(conn),
+            None => open_pool_connection(&self.db_path),
+        }
+    }
+
+    fn get(&self) -> Result<PooledConnection<'_>, DbError> {
+        let shared = self.gate.read().unwrap();
+        Ok(PooledConnection {
+            conn: Some(self.take_connection()?),
+            pool: self,
+            _shared: Some(shared),
+            _exclusive: None,
+            discarded: Cell::new(false),
+        })
+    }
+
+    // Waits for every other checkout to finish and blocks new ones until dropped,
+    // for work that must not see concurrent writes (export, maintenance)
+    fn exclusive(&self) -> Result<PooledConnection<'_>, DbError> {
+        let exclusive = self.gate.write().unwrap();
+        Ok(PooledConnection {
+            conn: Some(self.take_connection()?),
+            pool: self,
+            _shared: None,
+            _exclusive: Some(exclusive),
+            discarded: Cell::new(false),
+        })
+    }
 }
 
 #[derive(Clone)]
-pub struct Database(Arc<Mutex<Connection>>);
+pub struct Database(Arc<ConnectionPool>);
 
 impl Database {
     pub fn new() -> Result<Self, DbError> {
-        let db_path = get_database_path()?;
-        let conn = Connection::open(&db_path).map_err(|e| DbError {
-            message: format!("Failed to open database at {}: {}", db_path.display(), e),
-        })?;
+        Self::open(get_database_path()?)
+    }
+
+    // Opens (and migrates) the database at `db_path`
+    fn open(db_path: PathBuf) -> Result<Self, DbError> {
+        let conn = open_pool_connection(&db_path)?;
+
+        // Persistent for the file; lets pooled connections read while one writes
+        conn.execute("PRAGMA journal_mode = WAL")
+            .map_err(|e| DbError {
+                message: format!("Failed to enable WAL journal mode: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
         // Create tables
         conn.execute(
@@ -192,6 +676,7 @@ impl Database {
         )
         .map_err(|e| DbError {
             message: format!("Failed to create settings table: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         conn.execute(
@@ -203,6 +688,7 @@ impl Database {
         )
         .map_err(|e| DbError {
             message: format!("Failed to create recent_paths table: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         conn.execute(
@@ -214,6 +700,7 @@ impl Database {
         )
         .map_err(|e| DbError {
             message: format!("Failed to create favorites table: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         conn.execute(
@@ -226,6 +713,7 @@ impl Database {
         )
         .map_err(|e| DbError {
             message: format!("Failed to create session table: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         conn.execute(
@@ -246,6 +734,7 @@ impl Database {
         )
         .map_err(|e| DbError {
             message: format!("Failed to create telegram_messages table: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         conn.execute(
@@ -266,36 +755,67 @@ impl Database {
         )
         .map_err(|e| DbError {
             message: format!("Failed to create telegram_saved_items table: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_telegram_saved_items_owner_path ON telegram_saved_items (owner_id, file_path)",
         ).map_err(|e| DbError {
             message: format!("Failed to create telegram_saved_items index: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS pending_uploads (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                owner_id TEXT NOT NULL,
+                file_name TEXT NOT NULL,
+                local_path TEXT NOT NULL,
+                target_path TEXT,
+                status TEXT NOT NULL DEFAULT 'pending',
+                last_error TEXT,
+                created_at TEXT NOT NULL,
+                on_conflict TEXT,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                next_retry_at INTEGER
+            )",
+        )
+        .map_err(|e| DbError {
+            message: format!("Failed to create pending_uploads table: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
+        for (column_name, column_type) in [
+            ("on_conflict", "TEXT"),
+            ("attempts", "INTEGER NOT NULL DEFAULT 0"),
+            ("next_retry_at", "INTEGER"),
+        ] {
+            if column_exists(&conn, "pending_uploads", column_name)? {
+                continue;
+            }
+
+            conn.execute(format!(
+                "ALTER TABLE pending_uploads ADD COLUMN {} {}",
+                column_name, column_type
+            ))
+            .map_err(|e| DbError {
+                message: format!("Failed to add {} column: {}", column_name, e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        }
+
         // Migration: Add missing columns if they don't exist
         let columns_to_add = [
             ("profile_photo", "TEXT"),
             ("first_name", "TEXT"),
             ("last_name", "TEXT"),
             ("username", "TEXT"),
+            ("user_id", "INTEGER"),
+            ("is_active", "INTEGER NOT NULL DEFAULT 0"),
         ];
 
         for (col_name, col_type) in columns_to_add {
-            let check_query = format!("PRAGMA table_info(session)");
-            let mut statement = conn.prepare(&check_query).map_err(|e| DbError {
-                message: format!("Failed to prepare pragma check: {}", e),
-            })?;
-
-            let mut exists = false;
-            while let Ok(SqliteState::Row) = statement.next() {
-                let name: String = statement.read(1).unwrap_or_default();
-                if name == col_name {
-                    exists = true;
-                    break;
-                }
-            }
+            let exists = column_exists(&conn, "session", col_name)?;
 
             if !exists {
                 println!(
@@ -309,87 +829,300 @@ impl Database {
                         "Failed to migrate session table (adding {}): {}",
                         col_name, e
                     ),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            }
+        }
+
+        // Sessions created before multi-account support have no active flag;
+        // promote the most recent one so it is still restored on startup.
+        conn.execute(
+            "UPDATE session SET is_active = 1
+             WHERE id = (SELECT id FROM session ORDER BY created_at DESC, id DESC LIMIT 1)
+               AND NOT EXISTS (SELECT 1 FROM session WHERE is_active = 1)",
+        )
+        .map_err(|e| DbError {
+            message: format!("Failed to migrate active session flag: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        for (col_name, col_type) in [
+            ("recycle_origin_path", "TEXT"),
+            ("content_hash", "TEXT"),
+            ("pinned", "INTEGER DEFAULT 0"),
+            ("image_width", "INTEGER"),
+            ("image_height", "INTEGER"),
+            ("user_renamed", "INTEGER"),
+            ("audio_duration_secs", "INTEGER"),
+            ("audio_title", "TEXT"),
+            ("audio_performer", "TEXT"),
+        ] {
+            if !column_exists(&conn, "telegram_saved_items", col_name)? {
+                let alter_query = format!(
+                    "ALTER TABLE telegram_saved_items ADD COLUMN {} {}",
+                    col_name, col_type
+                );
+                conn.execute(&alter_query).map_err(|e| DbError {
+                    message: format!("Failed to add {} column: {}", col_name, e),
+                    kind: sqlite_error_kind(&e),
                 })?;
             }
         }
 
-        let mut saved_items_table_info = conn
-            .prepare("PRAGMA table_info(telegram_saved_items)")
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_telegram_saved_items_owner_hash ON telegram_saved_items (owner_id, content_hash)",
+        ).map_err(|e| DbError {
+            message: format!("Failed to create telegram_saved_items hash index: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        if !column_exists(&conn, "telegram_saved_items", "indexed_at")? {
+            conn.execute("ALTER TABLE telegram_saved_items ADD COLUMN indexed_at TEXT")
+                .map_err(|e| DbError {
+                    message: format!("Failed to add indexed_at column: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            // Existing rows were indexed at some unknown point; their modified date is the best guess
+            conn.execute(
+                "UPDATE telegram_saved_items SET indexed_at = modified_date WHERE indexed_at IS NULL",
+            )
             .map_err(|e| DbError {
-                message: format!("Failed to inspect telegram_saved_items schema: {}", e),
+                message: format!("Failed to backfill indexed_at: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
+        }
 
-        let mut recycle_origin_exists = false;
-        while let Ok(SqliteState::Row) = saved_items_table_info.next() {
-            let name: String = saved_items_table_info.read(1).unwrap_or_default();
-            if name == "recycle_origin_path" {
-                recycle_origin_exists = true;
-                break;
-            }
+        // User folders used to get an id derived from their original path, which a
+        // later folder could reproduce after a rename or move. Give each one a random
+        // id like new folders get; the default folders keep their fixed ids.
+        conn.execute(
+            "UPDATE telegram_saved_items
+             SET file_unique_id = 'folder-' || lower(hex(randomblob(16)))
+             WHERE file_type = 'folder'
+               AND file_unique_id LIKE 'folder\\_%' ESCAPE '\\'
+               AND NOT (file_path = '/Home' AND file_unique_id = 'folder_' || owner_id || '_' || lower(file_name))",
+        )
+        .map_err(|e| DbError {
+            message: format!("Failed to backfill folder ids: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        // Folder creation relies on this index to stay idempotent under concurrent
+        // callers; drop duplicates left by older builds first so it can be created
+        conn.execute(
+            "DELETE FROM telegram_saved_items
+             WHERE file_type = 'folder'
+               AND rowid NOT IN (
+                   SELECT MIN(rowid) FROM telegram_saved_items
+                   WHERE file_type = 'folder'
+                   GROUP BY owner_id, file_path, file_name
+               )",
+        )
+        .map_err(|e| DbError {
+            message: format!("Failed to remove duplicate folders: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_telegram_saved_items_unique_folder
+             ON telegram_saved_items (owner_id, file_path, file_name)
+             WHERE file_type = 'folder'",
+        )
+        .map_err(|e| DbError {
+            message: format!("Failed to create telegram_saved_items folder index: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(Database(Arc::new(ConnectionPool {
+            db_path,
+            idle: Mutex::new(vec![conn]),
+            gate: RwLock::new(()),
+        })))
+    }
+
+    pub fn export_database(&self, destination_path: &Path) -> Result<(), DbError> {
+        // Hold the pool for the whole copy so no write lands halfway through
+        let conn = self.0.exclusive()?;
+
+        conn.execute("PRAGMA wal_checkpoint(TRUNCATE)")
+            .map_err(|e| DbError {
+                message: format!("Failed to checkpoint database before export: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        if let Some(parent_dir) = destination_path.parent() {
+            fs::create_dir_all(parent_dir).map_err(|e| DbError {
+                message: format!(
+                    "Failed to create export directory {}: {}",
+                    parent_dir.display(),
+                    e
+                ),
+                kind: io_error_kind(&e),
+            })?;
         }
 
-        if !recycle_origin_exists {
-            conn.execute("ALTER TABLE telegram_saved_items ADD COLUMN recycle_origin_path TEXT")
+        fs::copy(&self.0.db_path, destination_path).map_err(|e| DbError {
+            message: format!(
+                "Failed to export database to {}: {}",
+                destination_path.display(),
+                e
+            ),
+            kind: io_error_kind(&e),
+        })?;
+
+        Ok(())
+    }
+
+    pub fn stage_database_import(&self, source_path: &Path) -> Result<(), DbError> {
+        validate_skybox_database(source_path)?;
+
+        let pending_path = get_pending_import_path(&self.0.db_path);
+        fs::copy(source_path, &pending_path).map_err(|e| DbError {
+            message: format!(
+                "Failed to stage database import from {}: {}",
+                source_path.display(),
+                e
+            ),
+            kind: io_error_kind(&e),
+        })?;
+
+        log::info!(
+            "Staged database import from {}; restart to apply",
+            source_path.display()
+        );
+        Ok(())
+    }
+
+    pub fn run_maintenance(&self) -> Result<serde_json::Value, DbError> {
+        let conn = self.0.exclusive()?;
+
+        let mut integrity_statement =
+            conn.prepare("PRAGMA integrity_check")
                 .map_err(|e| DbError {
-                    message: format!("Failed to add recycle_origin_path column: {}", e),
+                    message: format!("Failed to prepare integrity check: {}", e),
+                    kind: sqlite_error_kind(&e),
                 })?;
+
+        let mut integrity_messages = Vec::new();
+        loop {
+            match integrity_statement.next() {
+                Ok(SqliteState::Row) => {
+                    integrity_messages.push(
+                        integrity_statement
+                            .read::<String, usize>(0)
+                            .unwrap_or_default(),
+                    );
+                }
+                Ok(SqliteState::Done) => break,
+                Err(e) => {
+                    return Err(DbError {
+                        message: format!("Failed to run integrity check: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })
+                }
+            }
         }
+        drop(integrity_statement);
 
-        drop(saved_items_table_info);
+        let integrity_ok = integrity_messages.len() == 1 && integrity_messages[0] == "ok";
 
-        Ok(Database(Mutex::new(conn).into()))
+        let size_before = database_files_size(&self.0.db_path);
+
+        conn.execute("PRAGMA wal_checkpoint(TRUNCATE)")
+            .map_err(|e| DbError {
+                message: format!("Failed to checkpoint database: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        conn.execute("VACUUM").map_err(|e| DbError {
+            message: format!("Failed to vacuum database: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        // VACUUM goes through the WAL, so fold it back into the main file
+        conn.execute("PRAGMA wal_checkpoint(TRUNCATE)")
+            .map_err(|e| DbError {
+                message: format!("Failed to checkpoint database after vacuum: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        let size_after = database_files_size(&self.0.db_path);
+
+        Ok(serde_json::json!({
+            "integrity_ok": integrity_ok,
+            "integrity_messages": integrity_messages,
+            "size_bytes": size_after,
+            "reclaimed_bytes": size_before.saturating_sub(size_after),
+        }))
     }
 
     pub fn get_setting(&self, key: &str) -> Result<Option<String>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("SELECT value FROM settings WHERE key = ?")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((1, key)).map_err(|e| DbError {
             message: format!("Failed to bind parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         match statement.next() {
             Ok(SqliteState::Row) => {
                 let value: String = statement.read::<String, usize>(0).map_err(|e| DbError {
                     message: format!("Failed to read value: {}", e),
+                    kind: sqlite_error_kind(&e),
                 })?;
                 Ok(Some(value))
             }
             Ok(SqliteState::Done) => Ok(None),
             Err(e) => Err(DbError {
                 message: format!("Failed to get setting: {}", e),
+                kind: sqlite_error_kind(&e),
             }),
         }
     }
 
+    pub fn get_setting_i64(&self, key: &str) -> Result<Option<i64>, DbError> {
+        let Some(value) = self.get_setting(key)? else {
+            return Ok(None);
+        };
+
+        value.trim().parse::<i64>().map(Some).map_err(|e| DbError {
+            message: format!("Setting {} is not an integer: {}", key, e),
+            kind: "InvalidData".to_string(),
+        })
+    }
+
     pub fn set_setting(&self, key: &str, value: &str) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("INSERT OR REPLACE INTO settings (key, value) VALUES (?, ?)")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((1, key)).map_err(|e| DbError {
             message: format!("Failed to bind key parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, value)).map_err(|e| DbError {
             message: format!("Failed to bind value parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
     }
 
     pub fn get_recent_paths(&self, limit: i32) -> Result<Vec<RecentPath>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -397,26 +1130,32 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((1, limit as i64)).map_err(|e| DbError {
             message: format!("Failed to bind limit parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         let mut paths = Vec::new();
         while let SqliteState::Row = statement.next().map_err(|e| DbError {
             message: format!("Failed to query recent paths: {}", e),
+            kind: sqlite_error_kind(&e),
         })? {
             let id: i32 = statement
                 .read::<i64, usize>(0)
                 .map(|v| v as i32)
                 .map_err(|e| DbError {
                     message: format!("Failed to read id: {}", e),
+                    kind: sqlite_error_kind(&e),
                 })?;
             let path: String = statement.read::<String, usize>(1).map_err(|e| DbError {
                 message: format!("Failed to read path: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
             let last_opened: String = statement.read::<String, usize>(2).map_err(|e| DbError {
                 message: format!("Failed to read last_opened: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
             paths.push(RecentPath {
@@ -430,48 +1169,56 @@ impl Database {
     }
 
     pub fn add_recent_path(&self, path: &str) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("INSERT INTO recent_paths (path, last_opened) VALUES (?, datetime('now'))")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((1, path)).map_err(|e| DbError {
             message: format!("Failed to bind path parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
     }
 
     pub fn get_favorites(&self) -> Result<Vec<Favorite>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("SELECT id, path, label FROM favorites")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         let mut favorites = Vec::new();
         while let SqliteState::Row = statement.next().map_err(|e| DbError {
             message: format!("Failed to query favorites: {}", e),
+            kind: sqlite_error_kind(&e),
         })? {
             let id: i32 = statement
                 .read::<i64, usize>(0)
                 .map(|v| v as i32)
                 .map_err(|e| DbError {
                     message: format!("Failed to read id: {}", e),
+                    kind: sqlite_error_kind(&e),
                 })?;
             let path: String = statement.read::<String, usize>(1).map_err(|e| DbError {
                 message: format!("Failed to read path: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
             let label: String = statement.read::<String, usize>(2).map_err(|e| DbError {
                 message: format!("Failed to read label: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
             favorites.push(Favorite { id, path, label });
@@ -480,72 +1227,382 @@ impl Database {
         Ok(favorites)
     }
 
-    pub fn add_favorite(&self, path: &str, label: &str) -> Result<i32, DbError> {
-        let conn = self.0.lock().unwrap();
+    pub fn get_favorite_paths(&self) -> Result<Vec<String>, DbError> {
+        let conn = self.0.get()?;
 
         let mut statement = conn
-            .prepare("INSERT INTO favorites (path, label) VALUES (?, ?)")
+            .prepare("SELECT path FROM favorites")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
-        statement.bind((1, path)).map_err(|e| DbError {
-            message: format!("Failed to bind path parameter: {}", e),
-        })?;
-        statement.bind((2, label)).map_err(|e| DbError {
-            message: format!("Failed to bind label parameter: {}", e),
-        })?;
 
-        statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute statement: {}", e),
-        })?;
-
-        // Get the last inserted ID using a separate query since sqlite crate doesn't expose last_insert_rowid
-        let mut id_statement = conn
-            .prepare("SELECT last_insert_rowid()")
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare id query: {}", e),
-            })?;
+        let mut paths = Vec::new();
+        while let SqliteState::Row = statement.next().map_err(|e| DbError {
+            message: format!("Failed to query favorites: {}", e),
+            kind: sqlite_error_kind(&e),
+        })? {
+            paths.push(statement.read::<String, usize>(0).map_err(|e| DbError {
+                message: format!("Failed to read path: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?);
+        }
 
-        id_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute id query: {}", e),
-        })?;
+        Ok(paths)
+    }
 
-        let id: i64 = id_statement.read::<i64, usize>(0).map_err(|e| DbError {
-            message: format!("Failed to read id: {}", e),
-        })?;
+    pub fn is_favorite(&self, path: &str) -> Result<bool, DbError> {
+        let conn = self.0.get()?;
 
-        Ok(id as i32)
+        let mut statement = conn
+            .prepare("SELECT 1 FROM favorites WHERE path = ? LIMIT 1")
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((1, path)).map_err(|e| DbError {
+            message: format!("Failed to bind path parameter: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        match statement.next().map_err(|e| DbError {
+            message: format!("Failed to query favorites: {}", e),
+            kind: sqlite_error_kind(&e),
+        })? {
+            SqliteState::Row => Ok(true),
+            SqliteState::Done => Ok(false),
+        }
+    }
+
+    pub fn add_favorite(&self, path: &str, label: &str) -> Result<i32, DbError> {
+        let conn = self.0.get()?;
+
+        with_transaction(&conn, |conn| insert_favorite(conn, path, label))
+    }
+
+    /// Add many favorites in one transaction. Paths that are already favorites,
+    /// or repeat earlier in the batch, are skipped and listed in `skipped`.
+    pub fn add_favorites(&self, entries: Vec<(String, String)>) -> Result<FavoritesAdded, DbError> {
+        let conn = self.0.get()?;
+
+        with_transaction(&conn, |conn| {
+            let mut statement =
+                conn.prepare("SELECT path FROM favorites")
+                    .map_err(|e| DbError {
+                        message: format!("Failed to prepare statement: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+
+            let mut known_paths = HashSet::new();
+            while let SqliteState::Row = statement.next().map_err(|e| DbError {
+                message: format!("Failed to query favorites: {}", e),
+                kind: sqlite_error_kind(&e),
+            })? {
+                known_paths.insert(statement.read::<String, usize>(0).map_err(|e| DbError {
+                    message: format!("Failed to read path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?);
+            }
+
+            let mut added = FavoritesAdded {
+                ids: Vec::new(),
+                skipped: Vec::new(),
+            };
+            for (path, label) in entries {
+                if known_paths.contains(&path) {
+                    added.skipped.push(path);
+                    continue;
+                }
+
+                added.ids.push(insert_favorite(conn, &path, &label)?);
+                known_paths.insert(path);
+            }
+
+            Ok(added)
+        })
     }
 
     pub fn remove_favorite(&self, id: i32) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("DELETE FROM favorites WHERE id = ?")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((1, id as i64)).map_err(|e| DbError {
             message: format!("Failed to bind id parameter: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(())
+    }
+
+    pub fn add_pending_upload(
+        &self,
+        owner_id: &str,
+        file_name: &str,
+        local_path: &str,
+        target_path: Option<&str>,
+        on_conflict: Option<&str>,
+    ) -> Result<i64, DbError> {
+        let conn = self.0.get()?;
+
+        with_transaction(&conn, |conn| {
+            let mut statement = conn
+                .prepare(
+                    "INSERT INTO pending_uploads (owner_id, file_name, local_path, target_path, status, created_at, on_conflict)
+                     VALUES (?, ?, ?, ?, 'pending', ?, ?)",
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            statement.bind((1, owner_id)).map_err(|e| DbError {
+                message: format!("Failed to bind owner_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((2, file_name)).map_err(|e| DbError {
+                message: format!("Failed to bind file_name: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((3, local_path)).map_err(|e| DbError {
+                message: format!("Failed to bind local_path: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((4, target_path)).map_err(|e| DbError {
+                message: format!("Failed to bind target_path: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement
+                .bind((5, chrono::Utc::now().to_rfc3339().as_str()))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind created_at: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            statement.bind((6, on_conflict)).map_err(|e| DbError {
+                message: format!("Failed to bind on_conflict: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+            statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+            let mut id_statement =
+                conn.prepare("SELECT last_insert_rowid()")
+                    .map_err(|e| DbError {
+                        message: format!("Failed to prepare id query: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+
+            id_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute id query: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+            id_statement.read::<i64, usize>(0).map_err(|e| DbError {
+                message: format!("Failed to read id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })
+        })
+    }
+
+    pub fn get_pending_uploads(&self, owner_id: &str) -> Result<Vec<PendingUpload>, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT id, owner_id, file_name, local_path, target_path, status, last_error, created_at, on_conflict,
+                        attempts, next_retry_at
+                 FROM pending_uploads
+                 WHERE owner_id = ?
+                 ORDER BY id ASC",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        let mut uploads = Vec::new();
+        while let Ok(SqliteState::Row) = statement.next() {
+            uploads.push(PendingUpload {
+                id: statement.read::<i64, usize>(0).unwrap_or(0),
+                owner_id: statement.read::<String, usize>(1).unwrap_or_default(),
+                file_name: statement.read::<String, usize>(2).unwrap_or_default(),
+                local_path: statement.read::<String, usize>(3).unwrap_or_default(),
+                target_path: statement.read::<Option<String>, usize>(4).unwrap_or(None),
+                status: statement
+                    .read::<String, usize>(5)
+                    .unwrap_or_else(|_| "pending".to_string()),
+                last_error: statement.read::<Option<String>, usize>(6).unwrap_or(None),
+                created_at: statement.read::<String, usize>(7).unwrap_or_default(),
+                on_conflict: statement.read::<Option<String>, usize>(8).unwrap_or(None),
+                attempts: statement.read::<i64, usize>(9).unwrap_or(0),
+                next_retry_at: statement.read::<Option<i64>, usize>(10).unwrap_or(None),
+            });
+        }
+
+        Ok(uploads)
+    }
+
+    pub fn update_pending_upload_status(
+        &self,
+        id: i64,
+        status: &str,
+        last_error: Option<&str>,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare("UPDATE pending_uploads SET status = ?, last_error = ? WHERE id = ?")
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((1, status)).map_err(|e| DbError {
+            message: format!("Failed to bind status: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, last_error)).map_err(|e| DbError {
+            message: format!("Failed to bind last_error: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((3, id)).map_err(|e| DbError {
+            message: format!("Failed to bind id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(())
+    }
+
+    // Marks an upload failed and counts the attempt; `next_retry_at` None stops automatic retries
+    pub fn record_pending_upload_failure(
+        &self,
+        id: i64,
+        last_error: &str,
+        next_retry_at: Option<i64>,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "UPDATE pending_uploads
+                 SET status = 'failed', last_error = ?, attempts = attempts + 1, next_retry_at = ?
+                 WHERE id = ?",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((1, last_error)).map_err(|e| DbError {
+            message: format!("Failed to bind last_error: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, next_retry_at)).map_err(|e| DbError {
+            message: format!("Failed to bind next_retry_at: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((3, id)).map_err(|e| DbError {
+            message: format!("Failed to bind id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(())
+    }
+
+    // Puts a failed upload back in the queue with a fresh attempt budget
+    pub fn retry_pending_upload(&self, id: i64) -> Result<bool, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "UPDATE pending_uploads
+                 SET status = 'pending', attempts = 0, next_retry_at = NULL
+                 WHERE id = ? AND status = 'failed'",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((1, id)).map_err(|e| DbError {
+            message: format!("Failed to bind id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(conn.change_count() > 0)
+    }
+
+    // Uploads interrupted by a restart are retried instead of staying stuck
+    pub fn reset_uploading_pending_uploads(&self) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        conn.execute("UPDATE pending_uploads SET status = 'pending' WHERE status = 'uploading'")
+            .map_err(|e| DbError {
+                message: format!("Failed to reset pending uploads: {}", e),
+                kind: sqlite_error_kind(&e),
+            })
+    }
+
+    pub fn remove_pending_upload(&self, id: i64) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare("DELETE FROM pending_uploads WHERE id = ?")
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((1, id)).map_err(|e| DbError {
+            message: format!("Failed to bind id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
     }
 
     pub fn get_session(&self) -> Result<Option<Session>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
-        let mut statement = conn.prepare("SELECT id, phone, session_data, profile_photo, first_name, last_name, username, created_at
+        let mut statement = conn.prepare("SELECT id, phone, session_data, profile_photo, first_name, last_name, username, user_id, is_active, created_at
          FROM session
-         WHERE session_data IS NOT NULL AND session_data <> ''
+         WHERE is_active = 1 AND session_data IS NOT NULL AND session_data <> ''
          ORDER BY created_at DESC
          LIMIT 1")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         match statement.next() {
@@ -555,39 +1612,62 @@ impl Database {
                     .map(|v| v as i32)
                     .map_err(|e| DbError {
                         message: format!("Failed to read id: {}", e),
+                        kind: sqlite_error_kind(&e),
                     })?;
                 let phone: String = statement.read::<String, usize>(1).map_err(|e| DbError {
                     message: format!("Failed to read phone: {}", e),
+                    kind: sqlite_error_kind(&e),
                 })?;
                 let session_data: Option<String> = statement
                     .read::<Option<String>, usize>(2)
                     .map_err(|e| DbError {
                         message: format!("Failed to read session_data: {}", e),
+                        kind: sqlite_error_kind(&e),
                     })?;
                 let profile_photo: Option<String> = statement
                     .read::<Option<String>, usize>(3)
                     .map_err(|e| DbError {
                         message: format!("Failed to read profile_photo: {}", e),
+                        kind: sqlite_error_kind(&e),
                     })?;
                 let first_name: Option<String> = statement
                     .read::<Option<String>, usize>(4)
                     .map_err(|e| DbError {
                         message: format!("Failed to read first_name: {}", e),
+                        kind: sqlite_error_kind(&e),
                     })?;
                 let last_name: Option<String> = statement
                     .read::<Option<String>, usize>(5)
                     .map_err(|e| DbError {
                         message: format!("Failed to read last_name: {}", e),
+                        kind: sqlite_error_kind(&e),
                     })?;
                 let username: Option<String> =
                     statement
                         .read::<Option<String>, usize>(6)
                         .map_err(|e| DbError {
                             message: format!("Failed to read username: {}", e),
+                            kind: sqlite_error_kind(&e),
+                        })?;
+                let user_id: Option<i64> =
+                    statement
+                        .read::<Option<i64>, usize>(7)
+                        .map_err(|e| DbError {
+                            message: format!("Failed to read user_id: {}", e),
+                            kind: sqlite_error_kind(&e),
+                        })?;
+                let is_active: bool =
+                    statement
+                        .read::<i64, usize>(8)
+                        .map(|v| v != 0)
+                        .map_err(|e| DbError {
+                            message: format!("Failed to read is_active: {}", e),
+                            kind: sqlite_error_kind(&e),
                         })?;
                 let created_at: String =
-                    statement.read::<String, usize>(7).map_err(|e| DbError {
+                    statement.read::<String, usize>(9).map_err(|e| DbError {
                         message: format!("Failed to read created_at: {}", e),
+                        kind: sqlite_error_kind(&e),
                     })?;
 
                 Ok(Some(Session {
@@ -598,6 +1678,8 @@ impl Database {
                     first_name,
                     last_name,
                     username,
+                    user_id,
+                    is_active,
                     created_at,
                 }))
             }
@@ -609,11 +1691,13 @@ impl Database {
                 println!("[DB DEBUG] Error querying session: {}", e);
                 Err(DbError {
                     message: format!("Failed to get session: {}", e),
+                    kind: sqlite_error_kind(&e),
                 })
             }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_session(
         &self,
         phone: &str,
@@ -622,102 +1706,171 @@ impl Database {
         first_name: Option<&str>,
         last_name: Option<&str>,
         username: Option<&str>,
+        user_id: Option<i64>,
     ) -> Result<i32, DbError> {
-        let conn = self.0.lock().unwrap();
-        conn.execute("DELETE FROM session").map_err(|e| DbError {
-            message: format!("Failed to clear session: {}", e),
-        })?;
-        println!(
-            "[DB DEBUG] Creating session - phone: {}, has_session_data: {}, has_profile_photo: {}",
-            phone,
-            session_data.is_some(),
-            profile_photo.is_some()
-        );
-
-        let mut statement = conn.prepare("INSERT INTO session (phone, session_data, profile_photo, first_name, last_name, username, created_at) VALUES (?, ?, ?, ?, ?, ?, datetime('now'))")
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare statement: {}", e),
+        let conn = self.0.get()?;
+
+        // Keep the insert and the rowid read in one transaction so the id
+        // always belongs to this insert
+        with_transaction(&conn, |conn| {
+            // Replace any previous session for the same account and keep the others
+            let mut delete_statement = match user_id {
+                Some(user_id) => {
+                    let mut statement = conn
+                        .prepare(
+                            "DELETE FROM session WHERE user_id = ? OR (user_id IS NULL AND phone = ?)",
+                        )
+                        .map_err(|e| DbError {
+                            message: format!("Failed to prepare session cleanup: {}", e),
+                            kind: sqlite_error_kind(&e),
+                        })?;
+                    statement.bind((1, user_id)).map_err(|e| DbError {
+                        message: format!("Failed to bind user_id parameter: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+                    statement.bind((2, phone)).map_err(|e| DbError {
+                        message: format!("Failed to bind phone parameter: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+                    statement
+                }
+                None => {
+                    let mut statement = conn
+                        .prepare("DELETE FROM session WHERE phone = ?")
+                        .map_err(|e| DbError {
+                            message: format!("Failed to prepare session cleanup: {}", e),
+                            kind: sqlite_error_kind(&e),
+                        })?;
+                    statement.bind((1, phone)).map_err(|e| DbError {
+                        message: format!("Failed to bind phone parameter: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+                    statement
+                }
+            };
+            delete_statement.next().map_err(|e| DbError {
+                message: format!("Failed to clear previous session: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
-        statement.bind((1, phone)).map_err(|e| DbError {
-            message: format!("Failed to bind phone parameter: {}", e),
-        })?;
+            drop(delete_statement);
 
-        match session_data {
-            Some(data) => {
-                println!("[DB DEBUG] Binding session data (length: {})", data.len());
-                statement.bind((2, data)).map_err(|e| DbError {
-                    message: format!("Failed to bind session_data parameter: {}", e),
+            conn.execute("UPDATE session SET is_active = 0")
+                .map_err(|e| DbError {
+                    message: format!("Failed to deactivate sessions: {}", e),
+                    kind: sqlite_error_kind(&e),
                 })?;
-            }
-            None => {
-                println!("[DB DEBUG] Binding NULL session data");
-                statement.bind((2, ())).map_err(|e| DbError {
-                    message: format!("Failed to bind null session_data parameter: {}", e),
+            println!(
+                "[DB DEBUG] Creating session - phone: {}, has_session_data: {}, has_profile_photo: {}",
+                phone,
+                session_data.is_some(),
+                profile_photo.is_some()
+            );
+
+            let mut statement = conn.prepare("INSERT INTO session (phone, session_data, profile_photo, first_name, last_name, username, user_id, is_active, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, 1, datetime('now'))")
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare statement: {}", e),
+                    kind: sqlite_error_kind(&e),
                 })?;
-            }
-        }
+            statement.bind((1, phone)).map_err(|e| DbError {
+                message: format!("Failed to bind phone parameter: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        match profile_photo {
-            Some(photo) => {
-                println!("[DB DEBUG] Binding profile photo (length: {})", photo.len());
-                statement.bind((3, photo)).map_err(|e| DbError {
-                    message: format!("Failed to bind profile_photo parameter: {}", e),
-                })?;
-            }
-            None => {
-                statement.bind((3, ())).map_err(|e| DbError {
-                    message: format!("Failed to bind null profile_photo parameter: {}", e),
-                })?;
+            match session_data {
+                Some(data) => {
+                    println!("[DB DEBUG] Binding session data (length: {})", data.len());
+                    statement.bind((2, data)).map_err(|e| DbError {
+                        message: format!("Failed to bind session_data parameter: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+                }
+                None => {
+                    println!("[DB DEBUG] Binding NULL session data");
+                    statement.bind((2, ())).map_err(|e| DbError {
+                        message: format!("Failed to bind null session_data parameter: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+                }
             }
-        }
 
-        statement.bind((4, first_name)).map_err(|e| DbError {
-            message: format!("Failed to bind first_name parameter: {}", e),
-        })?;
-        statement.bind((5, last_name)).map_err(|e| DbError {
-            message: format!("Failed to bind last_name parameter: {}", e),
-        })?;
-        statement.bind((6, username)).map_err(|e| DbError {
-            message: format!("Failed to bind username parameter: {}", e),
-        })?;
+            match profile_photo {
+                Some(photo) => {
+                    println!("[DB DEBUG] Binding profile photo (length: {})", photo.len());
+                    statement.bind((3, photo)).map_err(|e| DbError {
+                        message: format!("Failed to bind profile_photo parameter: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+                }
+                None => {
+                    statement.bind((3, ())).map_err(|e| DbError {
+                        message: format!("Failed to bind null profile_photo parameter: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+                }
+            }
 
-        statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute statement: {}", e),
-        })?;
+            statement.bind((4, first_name)).map_err(|e| DbError {
+                message: format!("Failed to bind first_name parameter: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((5, last_name)).map_err(|e| DbError {
+                message: format!("Failed to bind last_name parameter: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((6, username)).map_err(|e| DbError {
+                message: format!("Failed to bind username parameter: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((7, user_id)).map_err(|e| DbError {
+                message: format!("Failed to bind user_id parameter: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        // Get the last inserted ID using a separate query since sqlite crate doesn't expose last_insert_rowid
-        let mut id_statement = conn
-            .prepare("SELECT last_insert_rowid()")
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare id query: {}", e),
+            statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        id_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute id query: {}", e),
-        })?;
+            // Get the last inserted ID using a separate query since sqlite crate doesn't expose last_insert_rowid
+            let mut id_statement =
+                conn.prepare("SELECT last_insert_rowid()")
+                    .map_err(|e| DbError {
+                        message: format!("Failed to prepare id query: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
 
-        let id: i64 = id_statement.read::<i64, usize>(0).map_err(|e| DbError {
-            message: format!("Failed to read id: {}", e),
-        })?;
+            id_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute id query: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+            let id: i64 = id_statement.read::<i64, usize>(0).map_err(|e| DbError {
+                message: format!("Failed to read id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        println!("[DB DEBUG] Session created with ID: {}", id);
-        Ok(id as i32)
+            println!("[DB DEBUG] Session created with ID: {}", id);
+            Ok(id as i32)
+        })
     }
 
     pub fn update_session_profile_photo(&self, profile_photo: &str) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
-            .prepare("UPDATE session SET profile_photo = ?")
+            .prepare("UPDATE session SET profile_photo = ? WHERE is_active = 1")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((1, profile_photo)).map_err(|e| DbError {
             message: format!("Failed to bind profile_photo parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         println!(
@@ -733,25 +1886,32 @@ impl Database {
         last_name: Option<&str>,
         username: Option<&str>,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
-            .prepare("UPDATE session SET first_name = ?, last_name = ?, username = ?")
+            .prepare(
+                "UPDATE session SET first_name = ?, last_name = ?, username = ? WHERE is_active = 1",
+            )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((1, first_name)).map_err(|e| DbError {
             message: format!("Failed to bind first_name parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, last_name)).map_err(|e| DbError {
             message: format!("Failed to bind last_name parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((3, username)).map_err(|e| DbError {
             message: format!("Failed to bind username parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         println!("[DB DEBUG] Updated session user info - first_name: {:?}, last_name: {:?}, username: {:?}", 
@@ -760,80 +1920,202 @@ impl Database {
     }
 
     pub fn clear_session(&self) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
-        let mut statement = conn.prepare("DELETE FROM session").map_err(|e| DbError {
-            message: format!("Failed to prepare statement: {}", e),
-        })?;
+        // Only the active account is logged out; other stored accounts stay available
+        let mut statement = conn
+            .prepare("DELETE FROM session WHERE is_active = 1")
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
     }
-    pub fn save_telegram_message(&self, msg: &TelegramMessage) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
 
-        let mut statement = conn.prepare("INSERT OR REPLACE INTO telegram_messages (message_id, chat_id, category, filename, extension, mime_type, timestamp, size, text, thumbnail, file_reference) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+    pub fn list_sessions(&self) -> Result<Vec<Session>, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT id, phone, session_data, profile_photo, first_name, last_name, username, user_id, is_active, created_at
+                 FROM session
+                 WHERE session_data IS NOT NULL AND session_data <> ''
+                 ORDER BY is_active DESC, created_at DESC",
+            )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        statement
-            .bind((1, msg.message_id as i64))
+        let mut sessions = Vec::new();
+        while let Ok(SqliteState::Row) = statement.next() {
+            sessions.push(Session {
+                id: statement.read::<i64, usize>(0).unwrap_or(0) as i32,
+                phone: statement.read::<String, usize>(1).unwrap_or_default(),
+                session_data: statement.read::<Option<String>, usize>(2).unwrap_or(None),
+                profile_photo: statement.read::<Option<String>, usize>(3).unwrap_or(None),
+                first_name: statement.read::<Option<String>, usize>(4).unwrap_or(None),
+                last_name: statement.read::<Option<String>, usize>(5).unwrap_or(None),
+                username: statement.read::<Option<String>, usize>(6).unwrap_or(None),
+                user_id: statement.read::<Option<i64>, usize>(7).unwrap_or(None),
+                is_active: statement.read::<i64, usize>(8).unwrap_or(0) != 0,
+                created_at: statement.read::<String, usize>(9).unwrap_or_default(),
+            });
+        }
+
+        Ok(sessions)
+    }
+
+    pub fn switch_active_session(&self, user_id: i64) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut exists_statement = conn
+            .prepare("SELECT COUNT(*) FROM session WHERE user_id = ?")
             .map_err(|e| DbError {
-                message: format!("Failed to bind message_id: {}", e),
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
-        statement.bind((2, msg.chat_id)).map_err(|e| DbError {
-            message: format!("Failed to bind chat_id: {}", e),
+        exists_statement.bind((1, user_id)).map_err(|e| DbError {
+            message: format!("Failed to bind user_id parameter: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
-        statement
-            .bind((3, msg.category.as_str()))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind category: {}", e),
-            })?;
-        statement
-            .bind((4, msg.filename.as_deref()))
+        exists_statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        let count = exists_statement.read::<i64, usize>(0).unwrap_or(0);
+        drop(exists_statement);
+
+        if count == 0 {
+            return Err(DbError {
+                message: format!("No stored session for user {}", user_id),
+                kind: "NotFound".to_string(),
+            });
+        }
+
+        let mut statement = conn
+            .prepare("UPDATE session SET is_active = CASE WHEN user_id = ? THEN 1 ELSE 0 END")
             .map_err(|e| DbError {
-                message: format!("Failed to bind filename: {}", e),
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
-        statement
-            .bind((5, msg.extension.as_deref()))
+        statement.bind((1, user_id)).map_err(|e| DbError {
+            message: format!("Failed to bind user_id parameter: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to switch active session: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        log::info!("Switched active session to user {}", user_id);
+        Ok(())
+    }
+
+    // Sessions saved before multi-account support were not keyed by user id
+    pub fn set_active_session_user_id(&self, user_id: i64) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare("UPDATE session SET user_id = ? WHERE is_active = 1 AND user_id IS NULL")
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((1, user_id)).map_err(|e| DbError {
+            message: format!("Failed to bind user_id parameter: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(())
+    }
+    pub fn save_telegram_message(&self, msg: &TelegramMessage) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn.prepare("INSERT OR REPLACE INTO telegram_messages (message_id, chat_id, category, filename, extension, mime_type, timestamp, size, text, thumbnail, file_reference) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement
+            .bind((1, msg.message_id as i64))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((2, msg.chat_id)).map_err(|e| DbError {
+            message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((3, msg.category.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind category: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((4, msg.filename.as_deref()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind filename: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((5, msg.extension.as_deref()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind extension: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
             .bind((6, msg.mime_type.as_deref()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind mime_type: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
             .bind((7, msg.timestamp.as_str()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind timestamp: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((8, msg.size)).map_err(|e| DbError {
             message: format!("Failed to bind size: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((9, msg.text.as_deref()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind text: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
             .bind((10, msg.thumbnail.as_deref()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind thumbnail: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
             .bind((11, msg.file_reference.as_str()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind file_reference: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
@@ -844,20 +2126,23 @@ impl Database {
         chat_id: i64,
         message_id: i32,
     ) -> Result<Option<TelegramMessage>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn.prepare("SELECT message_id, chat_id, category, filename, extension, mime_type, timestamp, size, text, thumbnail, file_reference FROM telegram_messages WHERE chat_id = ? AND message_id = ?")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((2, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         if let Ok(SqliteState::Row) = statement.next() {
@@ -873,6 +2158,8 @@ impl Database {
                 text: statement.read::<Option<String>, usize>(8).unwrap(),
                 thumbnail: statement.read::<Option<String>, usize>(9).unwrap(),
                 file_reference: statement.read::<String, usize>(10).unwrap(),
+                deduped: false,
+                queued: false,
             }))
         } else {
             Ok(None)
@@ -885,7 +2172,7 @@ impl Database {
         message_id: i32,
         thumbnail: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -893,22 +2180,120 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, thumbnail)).map_err(|e| DbError {
             message: format!("Failed to bind thumbnail: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, chat_id)).map_err(|e| DbError {
+            message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((3, message_id as i64))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(())
+    }
+
+    /// Point cached thumbnail paths that start with `old_prefix` at `new_prefix`
+    /// instead, in both tables at once. Returns the number of rows changed.
+    pub fn rewrite_telegram_thumbnail_path_prefix(
+        &self,
+        old_prefix: &str,
+        new_prefix: &str,
+    ) -> Result<usize, DbError> {
+        let conn = self.0.get()?;
+        let old_prefix_len = old_prefix.chars().count() as i64;
+
+        with_transaction(&conn, |conn| {
+            let mut changed = 0usize;
+            for table in ["telegram_messages", "telegram_saved_items"] {
+                let mut statement = conn
+                    .prepare(format!(
+                        "UPDATE {} SET thumbnail = ? || substr(thumbnail, ?)
+                         WHERE substr(thumbnail, 1, ?) = ?",
+                        table
+                    ))
+                    .map_err(|e| DbError {
+                        message: format!("Failed to prepare statement: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+                statement.bind((1, new_prefix)).map_err(|e| DbError {
+                    message: format!("Failed to bind new_prefix: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+                statement
+                    .bind((2, old_prefix_len + 1))
+                    .map_err(|e| DbError {
+                        message: format!("Failed to bind prefix length: {}", e),
+                        kind: sqlite_error_kind(&e),
+                    })?;
+                statement.bind((3, old_prefix_len)).map_err(|e| DbError {
+                    message: format!("Failed to bind prefix length: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+                statement.bind((4, old_prefix)).map_err(|e| DbError {
+                    message: format!("Failed to bind old_prefix: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+
+                statement.next().map_err(|e| DbError {
+                    message: format!("Failed to rewrite thumbnail paths in {}: {}", table, e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+                changed += conn.change_count();
+            }
+
+            Ok(changed)
+        })
+    }
+
+    pub fn update_telegram_message_file_reference(
+        &self,
+        chat_id: i64,
+        message_id: i32,
+        file_reference: &str,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "UPDATE telegram_messages SET file_reference = ? WHERE chat_id = ? AND message_id = ?",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, file_reference)).map_err(|e| DbError {
+            message: format!("Failed to bind file_reference: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((3, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
@@ -920,28 +2305,33 @@ impl Database {
         message_id: i32,
         size: i64,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("UPDATE telegram_messages SET size = ? WHERE chat_id = ? AND message_id = ?")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, size.max(0))).map_err(|e| DbError {
             message: format!("Failed to bind size: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((3, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
@@ -954,31 +2344,77 @@ impl Database {
         text: &str,
         timestamp: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("UPDATE telegram_messages SET text = ?, timestamp = ? WHERE chat_id = ? AND message_id = ?")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, text)).map_err(|e| DbError {
             message: format!("Failed to bind text: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, timestamp)).map_err(|e| DbError {
             message: format!("Failed to bind timestamp: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((3, chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((4, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(())
+    }
+
+    pub fn update_telegram_message_filename(
+        &self,
+        chat_id: i64,
+        message_id: i32,
+        filename: &str,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "UPDATE telegram_messages SET filename = ? WHERE chat_id = ? AND message_id = ?",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, filename)).map_err(|e| DbError {
+            message: format!("Failed to bind filename: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, chat_id)).map_err(|e| DbError {
+            message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((3, message_id as i64))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
@@ -989,18 +2425,21 @@ impl Database {
         chat_id: i64,
         category: &str,
     ) -> Result<Vec<TelegramMessage>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn.prepare("SELECT message_id, chat_id, category, filename, extension, mime_type, timestamp, size, text, thumbnail, file_reference FROM telegram_messages WHERE chat_id = ? AND category = ? ORDER BY timestamp DESC")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, category)).map_err(|e| DbError {
             message: format!("Failed to bind category: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         let mut messages = Vec::new();
@@ -1017,6 +2456,8 @@ impl Database {
                 text: statement.read::<Option<String>, usize>(8).unwrap(),
                 thumbnail: statement.read::<Option<String>, usize>(9).unwrap(),
                 file_reference: statement.read::<String, usize>(10).unwrap(),
+                deduped: false,
+                queued: false,
             });
         }
 
@@ -1024,15 +2465,17 @@ impl Database {
     }
 
     pub fn get_all_indexed_messages(&self, chat_id: i64) -> Result<Vec<TelegramMessage>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn.prepare("SELECT message_id, chat_id, category, filename, extension, mime_type, timestamp, size, text, thumbnail, file_reference FROM telegram_messages WHERE chat_id = ? ORDER BY message_id DESC")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         let mut messages = Vec::new();
@@ -1051,6 +2494,8 @@ impl Database {
                 text: statement.read::<Option<String>, usize>(8).unwrap_or(None),
                 thumbnail: statement.read::<Option<String>, usize>(9).unwrap_or(None),
                 file_reference: statement.read::<String, usize>(10).unwrap_or_default(),
+                deduped: false,
+                queued: false,
             });
         }
 
@@ -1058,16 +2503,18 @@ impl Database {
     }
 
     pub fn count_all_indexed_messages(&self, chat_id: i64) -> Result<i64, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("SELECT COUNT(*) FROM telegram_messages WHERE chat_id = ?")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         match statement.next() {
@@ -1078,21 +2525,24 @@ impl Database {
             Ok(SqliteState::Done) => Ok(0),
             Err(e) => Err(DbError {
                 message: format!("Failed to count indexed messages: {}", e),
+                kind: sqlite_error_kind(&e),
             }),
         }
     }
 
     pub fn get_last_indexed_message_id(&self, chat_id: i64) -> Result<i32, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("SELECT MAX(message_id) FROM telegram_messages WHERE chat_id = ?")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         match statement.next() {
@@ -1108,16 +2558,18 @@ impl Database {
     }
 
     pub fn get_oldest_indexed_message_id(&self, chat_id: i64) -> Result<i32, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare("SELECT MIN(message_id) FROM telegram_messages WHERE chat_id = ?")
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         match statement.next() {
@@ -1132,8 +2584,123 @@ impl Database {
         }
     }
 
+    /// Insert a folder row unless the owner already has a folder with that name
+    /// in `item.file_path`. Returns whether a row was inserted.
+    pub fn insert_telegram_saved_folder(&self, item: &TelegramSavedItem) -> Result<bool, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "INSERT INTO telegram_saved_items (
+                    file_unique_id,
+                    chat_id,
+                    message_id,
+                    file_type,
+                    file_size,
+                    file_name,
+                    file_caption,
+                    file_path,
+                    modified_date,
+                    owner_id,
+                    pinned,
+                    user_renamed,
+                    indexed_at
+                ) VALUES (?, 0, 0, 'folder', 0, ?, ?, ?, ?, ?, 0, 0, ?)
+                ON CONFLICT DO NOTHING",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((1, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((2, item.file_name.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_name: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((3, item.file_caption.as_deref()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_caption: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((4, item.file_path.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_path: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((5, item.modified_date.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind modified_date: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((6, item.owner_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind owner_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((7, item.modified_date.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind indexed_at: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to insert folder: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(conn.change_count() > 0)
+    }
+
     pub fn upsert_telegram_saved_item(&self, item: &TelegramSavedItem) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
+
+        // A name the user picked wins over one re-derived from the cached message
+        let mut renamed_statement = conn
+            .prepare(
+                "SELECT file_name, file_caption, file_type FROM telegram_saved_items
+                 WHERE file_unique_id = ? AND user_renamed = 1",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        renamed_statement
+            .bind((1, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        let (file_name, file_caption, file_type) = match renamed_statement.next() {
+            Ok(SqliteState::Row) => (
+                renamed_statement
+                    .read::<String, usize>(0)
+                    .unwrap_or_else(|_| item.file_name.clone()),
+                renamed_statement
+                    .read::<Option<String>, usize>(1)
+                    .unwrap_or(None),
+                renamed_statement
+                    .read::<String, usize>(2)
+                    .unwrap_or_else(|_| item.file_type.clone()),
+            ),
+            _ => (
+                item.file_name.clone(),
+                item.file_caption.clone(),
+                item.file_type.clone(),
+            ),
+        };
+        drop(renamed_statement);
 
         let mut statement = conn
             .prepare(
@@ -1149,527 +2716,1330 @@ impl Database {
                 file_path,
                 recycle_origin_path,
                 modified_date,
-                owner_id
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                owner_id,
+                content_hash,
+                pinned,
+                image_width,
+                image_height,
+                user_renamed,
+                indexed_at,
+                audio_duration_secs,
+                audio_title,
+                audio_performer
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                (SELECT content_hash FROM telegram_saved_items WHERE file_unique_id = ?),
+                COALESCE((SELECT pinned FROM telegram_saved_items WHERE file_unique_id = ?), 0),
+                COALESCE(?, (SELECT image_width FROM telegram_saved_items WHERE file_unique_id = ?)),
+                COALESCE(?, (SELECT image_height FROM telegram_saved_items WHERE file_unique_id = ?)),
+                COALESCE((SELECT user_renamed FROM telegram_saved_items WHERE file_unique_id = ?), 0),
+                COALESCE((SELECT indexed_at FROM telegram_saved_items WHERE file_unique_id = ?), ?),
+                COALESCE(?, (SELECT audio_duration_secs FROM telegram_saved_items WHERE file_unique_id = ?)),
+                COALESCE(?, (SELECT audio_title FROM telegram_saved_items WHERE file_unique_id = ?)),
+                COALESCE(?, (SELECT audio_performer FROM telegram_saved_items WHERE file_unique_id = ?)))",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement
             .bind((1, item.file_unique_id.as_str()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((2, item.chat_id)).map_err(|e| DbError {
             message: format!("Failed to bind chat_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((3, item.message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
             .bind((4, item.thumbnail.as_deref()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind thumbnail: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
-            .bind((5, item.file_type.as_str()))
+            .bind((5, file_type.as_str()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind file_type: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement.bind((6, item.file_size)).map_err(|e| DbError {
             message: format!("Failed to bind file_size: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
-            .bind((7, item.file_name.as_str()))
+            .bind((7, file_name.as_str()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind file_name: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
-            .bind((8, item.file_caption.as_deref()))
+            .bind((8, file_caption.as_deref()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind file_caption: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
             .bind((9, item.file_path.as_str()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind file_path: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
             .bind((10, item.recycle_origin_path.as_deref()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind recycle_origin_path: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
             .bind((11, item.modified_date.as_str()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind modified_date: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
         statement
             .bind((12, item.owner_id.as_str()))
             .map_err(|e| DbError {
                 message: format!("Failed to bind owner_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        // REPLACE rewrites the row, so carry over the hash recorded at upload time
+        statement
+            .bind((13, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((14, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((15, item.image_width))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind image_width: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((16, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((17, item.image_height))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind image_height: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((18, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((19, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((20, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        let indexed_at = item
+            .indexed_at
+            .clone()
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+        statement
+            .bind((21, indexed_at.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind indexed_at: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((22, item.audio_duration_secs))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind audio_duration_secs: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((23, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((24, item.audio_title.as_deref()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind audio_title: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((25, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((26, item.audio_performer.as_deref()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind audio_performer: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement
+            .bind((27, item.file_unique_id.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind file_unique_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
     }
 
-    pub fn update_telegram_saved_item_thumbnail(
+    pub fn set_telegram_saved_item_pinned(
         &self,
         owner_id: &str,
         message_id: i32,
-        thumbnail: &str,
+        pinned: bool,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
-                "UPDATE telegram_saved_items
-                 SET thumbnail = ?
-                 WHERE owner_id = ? AND message_id = ? AND file_type != 'folder'",
+                "UPDATE telegram_saved_items SET pinned = ? WHERE owner_id = ? AND message_id = ? AND file_type <> 'folder'",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        statement.bind((1, thumbnail)).map_err(|e| DbError {
-            message: format!("Failed to bind thumbnail: {}", e),
-        })?;
+        statement
+            .bind((1, if pinned { 1_i64 } else { 0_i64 }))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind pinned: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
         statement.bind((2, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((3, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
     }
 
-    pub fn update_telegram_saved_item_size(
+    pub fn update_telegram_saved_item_content_hash(
         &self,
         owner_id: &str,
         message_id: i32,
-        file_size: i64,
+        content_hash: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
-                "UPDATE telegram_saved_items
-                 SET file_size = ?
-                 WHERE owner_id = ? AND message_id = ? AND file_type = 'image'",
+                "UPDATE telegram_saved_items SET content_hash = ? WHERE owner_id = ? AND message_id = ? AND file_type <> 'folder'",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        statement.bind((1, file_size.max(0))).map_err(|e| DbError {
-            message: format!("Failed to bind file_size: {}", e),
+        statement.bind((1, content_hash)).map_err(|e| DbError {
+            message: format!("Failed to bind content_hash: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((3, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
     }
 
-    pub fn get_telegram_saved_zero_sized_image_message_ids(
+    pub fn update_telegram_saved_item_dimensions(
         &self,
         owner_id: &str,
-        limit: i64,
-    ) -> Result<Vec<i32>, DbError> {
-        let conn = self.0.lock().unwrap();
+        message_id: i32,
+        width: i64,
+        height: i64,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
 
-        let safe_limit = limit.max(1);
         let mut statement = conn
             .prepare(
-                "SELECT DISTINCT message_id
-                 FROM telegram_saved_items
-                 WHERE owner_id = ?
-                   AND file_type = 'image'
-                   AND file_size <= 0
-                   AND message_id > 0
-                 ORDER BY message_id DESC
-                 LIMIT ?",
+                "UPDATE telegram_saved_items SET image_width = ?, image_height = ? WHERE owner_id = ? AND message_id = ? AND file_type <> 'folder'",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        statement.bind((1, owner_id)).map_err(|e| DbError {
-            message: format!("Failed to bind owner_id: {}", e),
+        statement.bind((1, width)).map_err(|e| DbError {
+            message: format!("Failed to bind image_width: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
-        statement.bind((2, safe_limit)).map_err(|e| DbError {
-            message: format!("Failed to bind limit: {}", e),
+        statement.bind((2, height)).map_err(|e| DbError {
+            message: format!("Failed to bind image_height: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((3, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
+        statement
+            .bind((4, message_id as i64))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        let mut message_ids = Vec::new();
-        while let Ok(SqliteState::Row) = statement.next() {
-            let message_id = statement.read::<i64, usize>(0).unwrap_or(0) as i32;
-            if message_id > 0 {
-                message_ids.push(message_id);
-            }
-        }
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
 
-        Ok(message_ids)
+        Ok(())
     }
 
-    pub fn get_telegram_saved_items_by_path(
+    pub fn update_telegram_saved_item_audio_metadata(
         &self,
         owner_id: &str,
-        file_path: &str,
-    ) -> Result<Vec<TelegramSavedItem>, DbError> {
-        let conn = self.0.lock().unwrap();
+        message_id: i32,
+        duration_secs: Option<i64>,
+        title: Option<&str>,
+        performer: Option<&str>,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
-                "SELECT
-                chat_id,
-                message_id,
-                thumbnail,
-                file_type,
-                file_unique_id,
-                file_size,
-                file_name,
-                file_caption,
-                file_path,
-                recycle_origin_path,
-                modified_date,
-                owner_id
-             FROM telegram_saved_items
-             WHERE owner_id = ? AND file_path = ?
-             ORDER BY
-                CASE WHEN file_type = 'folder' THEN 0 ELSE 1 END,
-                CASE WHEN file_type = 'folder' THEN LOWER(file_name) ELSE '' END,
-                CASE WHEN file_type = 'folder' THEN 0 ELSE message_id END DESC,
-                LOWER(file_name) ASC",
+                "UPDATE telegram_saved_items SET audio_duration_secs = ?, audio_title = ?, audio_performer = ? WHERE owner_id = ? AND message_id = ? AND file_type <> 'folder'",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        statement.bind((1, owner_id)).map_err(|e| DbError {
-            message: format!("Failed to bind owner_id: {}", e),
+        statement.bind((1, duration_secs)).map_err(|e| DbError {
+            message: format!("Failed to bind audio_duration_secs: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
-        statement.bind((2, file_path)).map_err(|e| DbError {
-            message: format!("Failed to bind file_path: {}", e),
+        statement.bind((2, title)).map_err(|e| DbError {
+            message: format!("Failed to bind audio_title: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
-
-        let mut items = Vec::new();
-        while let Ok(SqliteState::Row) = statement.next() {
-            items.push(TelegramSavedItem {
-                chat_id: statement.read::<i64, usize>(0).unwrap_or(0),
-                message_id: statement.read::<i64, usize>(1).unwrap_or(0) as i32,
-                thumbnail: statement.read::<Option<String>, usize>(2).unwrap_or(None),
-                file_type: statement
-                    .read::<String, usize>(3)
-                    .unwrap_or_else(|_| "file".to_string()),
-                file_unique_id: statement.read::<String, usize>(4).unwrap_or_default(),
-                file_size: statement.read::<i64, usize>(5).unwrap_or(0),
-                file_name: statement.read::<String, usize>(6).unwrap_or_default(),
-                file_caption: statement.read::<Option<String>, usize>(7).unwrap_or(None),
-                file_path: statement.read::<String, usize>(8).unwrap_or_default(),
-                recycle_origin_path: statement.read::<Option<String>, usize>(9).unwrap_or(None),
-                modified_date: statement.read::<String, usize>(10).unwrap_or_default(),
-                owner_id: statement.read::<String, usize>(11).unwrap_or_default(),
-            });
-        }
-
-        Ok(items)
-    }
-
-    pub fn count_telegram_saved_non_folder_items(&self, owner_id: &str) -> Result<i64, DbError> {
-        let conn = self.0.lock().unwrap();
-
-        let mut statement = conn
-            .prepare("SELECT COUNT(*) FROM telegram_saved_items WHERE owner_id = ? AND file_type != 'folder'")
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare statement: {}", e),
-            })?;
-
-        statement.bind((1, owner_id)).map_err(|e| DbError {
+        statement.bind((3, performer)).map_err(|e| DbError {
+            message: format!("Failed to bind audio_performer: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((4, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
-
-        match statement.next() {
-            Ok(SqliteState::Row) => {
-                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
-                Ok(count)
-            }
-            Ok(SqliteState::Done) => Ok(0),
-            Err(e) => Err(DbError {
-                message: format!("Failed to count saved items: {}", e),
-            }),
-        }
-    }
-
-    pub fn count_telegram_saved_items_with_empty_name(
-        &self,
-        owner_id: &str,
-    ) -> Result<i64, DbError> {
-        let conn = self.0.lock().unwrap();
-
-        let mut statement = conn
-            .prepare(
-                "SELECT COUNT(*)
-                 FROM telegram_saved_items
-                 WHERE owner_id = ?
-                   AND file_type != 'folder'
-                   AND (file_name IS NULL OR TRIM(file_name) = '')",
-            )
+        statement
+            .bind((5, message_id as i64))
             .map_err(|e| DbError {
-                message: format!("Failed to prepare statement: {}", e),
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        statement.bind((1, owner_id)).map_err(|e| DbError {
-            message: format!("Failed to bind owner_id: {}", e),
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
-        match statement.next() {
-            Ok(SqliteState::Row) => {
-                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
-                Ok(count)
-            }
-            Ok(SqliteState::Done) => Ok(0),
-            Err(e) => Err(DbError {
-                message: format!("Failed to count unnamed saved items: {}", e),
-            }),
-        }
+        Ok(())
     }
 
-    pub fn count_telegram_generated_names_missing_extension(
+    pub fn find_telegram_saved_message_id_by_content_hash(
         &self,
         owner_id: &str,
-    ) -> Result<i64, DbError> {
-        let conn = self.0.lock().unwrap();
+        content_hash: &str,
+    ) -> Result<Option<i32>, DbError> {
+        let conn = self.0.get()?;
 
+        // Items waiting in Recycle Bin are not reused as upload targets
         let mut statement = conn
             .prepare(
-                "SELECT COUNT(*)
-                 FROM telegram_saved_items
-                 WHERE owner_id = ?
-                   AND file_type != 'folder'
-                   AND file_name IS NOT NULL
-                   AND TRIM(file_name) != ''
-                   AND file_name NOT LIKE '%.%'
-                   AND (
-                     (file_type = 'image' AND LOWER(file_name) LIKE 'image_%')
-                     OR (file_type = 'video' AND LOWER(file_name) LIKE 'video_%')
-                     OR (file_type = 'audio' AND LOWER(file_name) LIKE 'audio_%')
-                   )",
+                "SELECT message_id FROM telegram_saved_items
+                 WHERE owner_id = ? AND content_hash = ? AND file_type <> 'folder' AND recycle_origin_path IS NULL
+                 ORDER BY message_id DESC
+                 LIMIT 1",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, content_hash)).map_err(|e| DbError {
+            message: format!("Failed to bind content_hash: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         match statement.next() {
             Ok(SqliteState::Row) => {
-                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
-                Ok(count)
+                Ok(Some(statement.read::<i64, usize>(0).map_err(|e| DbError {
+                    message: format!("Failed to read message_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })? as i32))
             }
-            Ok(SqliteState::Done) => Ok(0),
+            Ok(SqliteState::Done) => Ok(None),
             Err(e) => Err(DbError {
-                message: format!("Failed to count generated names without extension: {}", e),
+                message: format!("Failed to look up content hash: {}", e),
+                kind: sqlite_error_kind(&e),
             }),
         }
     }
 
-    pub fn telegram_saved_file_exists_by_message_id(
+    pub fn update_telegram_saved_item_thumbnail(
         &self,
         owner_id: &str,
         message_id: i32,
-    ) -> Result<bool, DbError> {
-        let conn = self.0.lock().unwrap();
+        thumbnail: &str,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
-                "SELECT COUNT(*)
-                 FROM telegram_saved_items
+                "UPDATE telegram_saved_items
+                 SET thumbnail = ?
                  WHERE owner_id = ? AND message_id = ? AND file_type != 'folder'",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        statement.bind((1, owner_id)).map_err(|e| DbError {
+        statement.bind((1, thumbnail)).map_err(|e| DbError {
+            message: format!("Failed to bind thumbnail: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
-            .bind((2, message_id as i64))
+            .bind((3, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        match statement.next() {
-            Ok(SqliteState::Row) => {
-                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
-                Ok(count > 0)
-            }
-            Ok(SqliteState::Done) => Ok(false),
-            Err(e) => Err(DbError {
-                message: format!("Failed to read file existence: {}", e),
-            }),
-        }
-    }
-
-    pub fn telegram_saved_folder_exists(
-        &self,
-        owner_id: &str,
-        parent_path: &str,
-        folder_name: &str,
-    ) -> Result<bool, DbError> {
-        let conn = self.0.lock().unwrap();
-
-        let mut statement = conn
-            .prepare(
-                "SELECT COUNT(*)
-                 FROM telegram_saved_items
-                 WHERE owner_id = ? AND file_type = 'folder' AND file_path = ? AND file_name = ?",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare statement: {}", e),
-            })?;
-
-        statement.bind((1, owner_id)).map_err(|e| DbError {
-            message: format!("Failed to bind owner_id: {}", e),
-        })?;
-        statement.bind((2, parent_path)).map_err(|e| DbError {
-            message: format!("Failed to bind parent_path: {}", e),
-        })?;
-        statement.bind((3, folder_name)).map_err(|e| DbError {
-            message: format!("Failed to bind folder_name: {}", e),
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
-        match statement.next() {
-            Ok(SqliteState::Row) => {
-                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
-                Ok(count > 0)
-            }
-            Ok(SqliteState::Done) => Ok(false),
-            Err(e) => Err(DbError {
-                message: format!("Failed to read folder existence: {}", e),
-            }),
-        }
+        Ok(())
     }
 
-    pub fn move_telegram_saved_file_by_message_id(
+    pub fn update_telegram_saved_item_size(
         &self,
         owner_id: &str,
         message_id: i32,
-        destination_path: &str,
-        modified_date: &str,
+        file_size: i64,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
                 "UPDATE telegram_saved_items
-                 SET file_path = ?, modified_date = ?
-                 WHERE owner_id = ? AND message_id = ? AND file_type != 'folder'",
+                 SET file_size = ?
+                 WHERE owner_id = ? AND message_id = ? AND file_type = 'image'",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        statement.bind((1, destination_path)).map_err(|e| DbError {
-            message: format!("Failed to bind destination_path: {}", e),
-        })?;
-        statement.bind((2, modified_date)).map_err(|e| DbError {
-            message: format!("Failed to bind modified_date: {}", e),
+        statement.bind((1, file_size.max(0))).map_err(|e| DbError {
+            message: format!("Failed to bind file_size: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
-        statement.bind((3, owner_id)).map_err(|e| DbError {
+        statement.bind((2, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
-            .bind((4, message_id as i64))
+            .bind((3, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
     }
 
-    pub fn rename_telegram_saved_file_by_message_id(
+    /// Like `update_telegram_saved_item_size`, but for any non-folder item; used once
+    /// a download has revealed the real byte length.
+    pub fn set_telegram_saved_item_file_size(
         &self,
         owner_id: &str,
         message_id: i32,
-        new_file_name: &str,
-        modified_date: &str,
+        file_size: i64,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
                 "UPDATE telegram_saved_items
-                 SET file_name = ?, file_caption = ?, modified_date = ?
+                 SET file_size = ?
                  WHERE owner_id = ? AND message_id = ? AND file_type != 'folder'",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        statement.bind((1, new_file_name)).map_err(|e| DbError {
-            message: format!("Failed to bind new_file_name: {}", e),
-        })?;
-        statement.bind((2, new_file_name)).map_err(|e| DbError {
-            message: format!("Failed to bind file_caption: {}", e),
-        })?;
-        statement.bind((3, modified_date)).map_err(|e| DbError {
-            message: format!("Failed to bind modified_date: {}", e),
+        statement.bind((1, file_size.max(0))).map_err(|e| DbError {
+            message: format!("Failed to bind file_size: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
-        statement.bind((4, owner_id)).map_err(|e| DbError {
+        statement.bind((2, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
-            .bind((5, message_id as i64))
+            .bind((3, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
     }
 
-    pub fn update_telegram_saved_item_caption_by_message_id(
+    pub fn get_telegram_saved_zero_sized_image_message_ids(
         &self,
         owner_id: &str,
-        message_id: i32,
-        file_caption: &str,
-        modified_date: &str,
-    ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        limit: i64,
+    ) -> Result<Vec<i32>, DbError> {
+        let conn = self.0.get()?;
+
+        let safe_limit = limit.max(1);
+        let mut statement = conn
+            .prepare(
+                "SELECT DISTINCT message_id
+                 FROM telegram_saved_items
+                 WHERE owner_id = ?
+                   AND file_type = 'image'
+                   AND file_size <= 0
+                   AND message_id > 0
+                 ORDER BY message_id DESC
+                 LIMIT ?",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, safe_limit)).map_err(|e| DbError {
+            message: format!("Failed to bind limit: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        let mut message_ids = Vec::new();
+        while let Ok(SqliteState::Row) = statement.next() {
+            let message_id = statement.read::<i64, usize>(0).unwrap_or(0) as i32;
+            if message_id > 0 {
+                message_ids.push(message_id);
+            }
+        }
+
+        Ok(message_ids)
+    }
+
+    pub fn get_telegram_saved_items_by_path(
+        &self,
+        owner_id: &str,
+        file_path: &str,
+    ) -> Result<Vec<TelegramSavedItem>, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT
+                chat_id,
+                message_id,
+                thumbnail,
+                file_type,
+                file_unique_id,
+                file_size,
+                file_name,
+                file_caption,
+                file_path,
+                recycle_origin_path,
+                modified_date,
+                owner_id,
+                pinned,
+                image_width,
+                image_height,
+                indexed_at,
+                audio_duration_secs,
+                audio_title,
+                audio_performer
+             FROM telegram_saved_items
+             WHERE owner_id = ? AND file_path = ?
+             ORDER BY
+                COALESCE(pinned, 0) DESC,
+                CASE WHEN file_type = 'folder' THEN 0 ELSE 1 END,
+                CASE WHEN file_type = 'folder' THEN LOWER(file_name) ELSE '' END,
+                CASE WHEN file_type = 'folder' THEN 0 ELSE message_id END DESC,
+                LOWER(file_name) ASC",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, file_path)).map_err(|e| DbError {
+            message: format!("Failed to bind file_path: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        let mut items = Vec::new();
+        while let Ok(SqliteState::Row) = statement.next() {
+            items.push(read_telegram_saved_item_row(&statement));
+        }
+
+        Ok(items)
+    }
+
+    pub fn get_recently_modified_saved_items(
+        &self,
+        owner_id: &str,
+        limit: i64,
+    ) -> Result<Vec<TelegramSavedItem>, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT
+                chat_id,
+                message_id,
+                thumbnail,
+                file_type,
+                file_unique_id,
+                file_size,
+                file_name,
+                file_caption,
+                file_path,
+                recycle_origin_path,
+                modified_date,
+                owner_id,
+                pinned,
+                image_width,
+                image_height,
+                indexed_at,
+                audio_duration_secs,
+                audio_title,
+                audio_performer
+             FROM telegram_saved_items
+             WHERE owner_id = ?
+                AND file_type != 'folder'
+                AND recycle_origin_path IS NULL
+                AND file_path != '/Home/Recycle Bin'
+                AND file_path NOT LIKE '/Home/Recycle Bin/%'
+             ORDER BY modified_date DESC, message_id DESC
+             LIMIT ?",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, limit)).map_err(|e| DbError {
+            message: format!("Failed to bind limit: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        let mut items = Vec::new();
+        while let Ok(SqliteState::Row) = statement.next() {
+            items.push(read_telegram_saved_item_row(&statement));
+        }
+
+        Ok(items)
+    }
+
+    pub fn search_telegram_saved_items(
+        &self,
+        owner_id: &str,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<TelegramSavedItem>, DbError> {
+        let escaped_query = query
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let name_pattern = format!("%{}%", escaped_query);
+
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT
+                chat_id,
+                message_id,
+                thumbnail,
+                file_type,
+                file_unique_id,
+                file_size,
+                file_name,
+                file_caption,
+                file_path,
+                recycle_origin_path,
+                modified_date,
+                owner_id,
+                pinned,
+                image_width,
+                image_height,
+                indexed_at,
+                audio_duration_secs,
+                audio_title,
+                audio_performer
+             FROM telegram_saved_items
+             WHERE owner_id = ?
+                AND LOWER(file_name) LIKE ? ESCAPE '\\'
+                AND recycle_origin_path IS NULL
+                AND file_path != '/Home/Recycle Bin'
+                AND file_path NOT LIKE '/Home/Recycle Bin/%'
+             ORDER BY
+                CASE WHEN file_type = 'folder' THEN 0 ELSE 1 END,
+                modified_date DESC,
+                message_id DESC
+             LIMIT ?",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((2, name_pattern.as_str()))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind query: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((3, limit)).map_err(|e| DbError {
+            message: format!("Failed to bind limit: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        let mut items = Vec::new();
+        while let Ok(SqliteState::Row) = statement.next() {
+            items.push(read_telegram_saved_item_row(&statement));
+        }
+
+        Ok(items)
+    }
+
+    pub fn count_telegram_saved_non_folder_items(&self, owner_id: &str) -> Result<i64, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare("SELECT COUNT(*) FROM telegram_saved_items WHERE owner_id = ? AND file_type != 'folder'")
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => {
+                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
+                Ok(count)
+            }
+            Ok(SqliteState::Done) => Ok(0),
+            Err(e) => Err(DbError {
+                message: format!("Failed to count saved items: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    pub fn count_telegram_saved_items_by_path(
+        &self,
+        owner_id: &str,
+        file_path: &str,
+    ) -> Result<i64, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT COUNT(*) FROM telegram_saved_items WHERE owner_id = ? AND file_path = ?",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, file_path)).map_err(|e| DbError {
+            message: format!("Failed to bind file_path: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => {
+                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
+                Ok(count)
+            }
+            Ok(SqliteState::Done) => Ok(0),
+            Err(e) => Err(DbError {
+                message: format!("Failed to count saved items by path: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    pub fn count_telegram_saved_items_with_empty_name(
+        &self,
+        owner_id: &str,
+    ) -> Result<i64, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT COUNT(*)
+                 FROM telegram_saved_items
+                 WHERE owner_id = ?
+                   AND file_type != 'folder'
+                   AND (file_name IS NULL OR TRIM(file_name) = '')",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => {
+                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
+                Ok(count)
+            }
+            Ok(SqliteState::Done) => Ok(0),
+            Err(e) => Err(DbError {
+                message: format!("Failed to count unnamed saved items: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    pub fn count_telegram_generated_names_missing_extension(
+        &self,
+        owner_id: &str,
+    ) -> Result<i64, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT COUNT(*)
+                 FROM telegram_saved_items
+                 WHERE owner_id = ?
+                   AND file_type != 'folder'
+                   AND file_name IS NOT NULL
+                   AND TRIM(file_name) != ''
+                   AND file_name NOT LIKE '%.%'
+                   AND (
+                     (file_type = 'image' AND LOWER(file_name) LIKE 'image_%')
+                     OR (file_type = 'video' AND LOWER(file_name) LIKE 'video_%')
+                     OR (file_type = 'audio' AND LOWER(file_name) LIKE 'audio_%')
+                   )",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => {
+                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
+                Ok(count)
+            }
+            Ok(SqliteState::Done) => Ok(0),
+            Err(e) => Err(DbError {
+                message: format!("Failed to count generated names without extension: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    // (message_id, file_type, file_name) of the items counted by
+    // `count_telegram_generated_names_missing_extension`
+    pub fn get_telegram_generated_names_missing_extension(
+        &self,
+        owner_id: &str,
+    ) -> Result<Vec<(i32, String, String)>, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT message_id, file_type, file_name
+                 FROM telegram_saved_items
+                 WHERE owner_id = ?
+                   AND file_type != 'folder'
+                   AND file_name IS NOT NULL
+                   AND TRIM(file_name) != ''
+                   AND file_name NOT LIKE '%.%'
+                   AND (
+                     (file_type = 'image' AND LOWER(file_name) LIKE 'image_%')
+                     OR (file_type = 'video' AND LOWER(file_name) LIKE 'video_%')
+                     OR (file_type = 'audio' AND LOWER(file_name) LIKE 'audio_%')
+                   )",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        let mut items = Vec::new();
+        while let Ok(SqliteState::Row) = statement.next() {
+            let message_id = statement.read::<i64, usize>(0).unwrap_or(0) as i32;
+            let file_type = statement.read::<String, usize>(1).unwrap_or_default();
+            let file_name = statement.read::<String, usize>(2).unwrap_or_default();
+            items.push((message_id, file_type, file_name));
+        }
+
+        Ok(items)
+    }
+
+    pub fn telegram_saved_file_exists_by_message_id(
+        &self,
+        owner_id: &str,
+        message_id: i32,
+    ) -> Result<bool, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT COUNT(*)
+                 FROM telegram_saved_items
+                 WHERE owner_id = ? AND message_id = ? AND file_type != 'folder'",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((2, message_id as i64))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => {
+                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
+                Ok(count > 0)
+            }
+            Ok(SqliteState::Done) => Ok(false),
+            Err(e) => Err(DbError {
+                message: format!("Failed to read file existence: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    pub fn telegram_saved_folder_exists(
+        &self,
+        owner_id: &str,
+        parent_path: &str,
+        folder_name: &str,
+    ) -> Result<bool, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT COUNT(*)
+                 FROM telegram_saved_items
+                 WHERE owner_id = ? AND file_type = 'folder' AND file_path = ? AND file_name = ?",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, parent_path)).map_err(|e| DbError {
+            message: format!("Failed to bind parent_path: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((3, folder_name)).map_err(|e| DbError {
+            message: format!("Failed to bind folder_name: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => {
+                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
+                Ok(count > 0)
+            }
+            Ok(SqliteState::Done) => Ok(false),
+            Err(e) => Err(DbError {
+                message: format!("Failed to read folder existence: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    pub fn telegram_saved_item_name_exists(
+        &self,
+        owner_id: &str,
+        parent_path: &str,
+        file_name: &str,
+    ) -> Result<bool, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT COUNT(*)
+                 FROM telegram_saved_items
+                 WHERE owner_id = ? AND file_path = ? AND file_name = ?",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, parent_path)).map_err(|e| DbError {
+            message: format!("Failed to bind parent_path: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((3, file_name)).map_err(|e| DbError {
+            message: format!("Failed to bind file_name: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => {
+                let count: i64 = statement.read::<i64, usize>(0).unwrap_or(0);
+                Ok(count > 0)
+            }
+            Ok(SqliteState::Done) => Ok(false),
+            Err(e) => Err(DbError {
+                message: format!("Failed to read item name existence: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    pub fn move_telegram_saved_file_by_message_id(
+        &self,
+        owner_id: &str,
+        message_id: i32,
+        destination_path: &str,
+        modified_date: &str,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "UPDATE telegram_saved_items
+                 SET file_path = ?, modified_date = ?
+                 WHERE owner_id = ? AND message_id = ? AND file_type != 'folder'",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, destination_path)).map_err(|e| DbError {
+            message: format!("Failed to bind destination_path: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, modified_date)).map_err(|e| DbError {
+            message: format!("Failed to bind modified_date: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((3, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((4, message_id as i64))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(())
+    }
+
+    pub fn rename_telegram_saved_file_by_message_id(
+        &self,
+        owner_id: &str,
+        message_id: i32,
+        new_file_name: &str,
+        modified_date: &str,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "UPDATE telegram_saved_items
+                 SET file_name = ?, file_caption = ?, modified_date = ?, user_renamed = 1
+                 WHERE owner_id = ? AND message_id = ? AND file_type != 'folder'",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, new_file_name)).map_err(|e| DbError {
+            message: format!("Failed to bind new_file_name: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, new_file_name)).map_err(|e| DbError {
+            message: format!("Failed to bind file_caption: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((3, modified_date)).map_err(|e| DbError {
+            message: format!("Failed to bind modified_date: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((4, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((5, message_id as i64))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.next().map_err(|e| DbError {
+            message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        Ok(())
+    }
+
+    // Renames a file whose extension changed, moving both the saved item and its
+    // cached message to the new type in one transaction
+    #[allow(clippy::too_many_arguments)]
+    pub fn rename_telegram_saved_file_with_type_by_message_id(
+        &self,
+        owner_id: &str,
+        chat_id: i64,
+        message_id: i32,
+        new_file_name: &str,
+        extension: &str,
+        file_type: &str,
+        category: &str,
+        modified_date: &str,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
+
+        with_transaction(&conn, |conn| {
+            let mut statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
+                     SET file_name = ?, file_caption = ?, file_type = ?, modified_date = ?, user_renamed = 1
+                     WHERE owner_id = ? AND message_id = ? AND file_type != 'folder'",
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+
+            statement.bind((1, new_file_name)).map_err(|e| DbError {
+                message: format!("Failed to bind new_file_name: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((2, new_file_name)).map_err(|e| DbError {
+                message: format!("Failed to bind file_caption: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((3, file_type)).map_err(|e| DbError {
+                message: format!("Failed to bind file_type: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((4, modified_date)).map_err(|e| DbError {
+                message: format!("Failed to bind modified_date: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement.bind((5, owner_id)).map_err(|e| DbError {
+                message: format!("Failed to bind owner_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            statement
+                .bind((6, message_id as i64))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind message_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+
+            statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+            // Index rebuilds read the cached message, so it has to agree on the category
+            let mut message_statement = conn
+                .prepare(
+                    "UPDATE telegram_messages SET filename = ?, extension = ?, category = ?
+                     WHERE chat_id = ? AND message_id = ?",
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+
+            message_statement
+                .bind((1, new_file_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind filename: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            message_statement
+                .bind((2, extension))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind extension: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            message_statement.bind((3, category)).map_err(|e| DbError {
+                message: format!("Failed to bind category: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            message_statement.bind((4, chat_id)).map_err(|e| DbError {
+                message: format!("Failed to bind chat_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+            message_statement
+                .bind((5, message_id as i64))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind message_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+
+            message_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+            Ok(())
+        })
+    }
+
+    pub fn update_telegram_saved_item_caption_by_message_id(
+        &self,
+        owner_id: &str,
+        message_id: i32,
+        file_caption: &str,
+        modified_date: &str,
+    ) -> Result<(), DbError> {
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -1679,25 +4049,31 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, file_caption)).map_err(|e| DbError {
             message: format!("Failed to bind file_caption: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, modified_date)).map_err(|e| DbError {
             message: format!("Failed to bind modified_date: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((3, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((4, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
@@ -1713,62 +4089,71 @@ impl Database {
         destination_folder_path: &str,
         modified_date: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
-        let mut rename_folder_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+        with_transaction(&conn, |conn| {
+            let mut rename_folder_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET file_name = ?, file_caption = ?, modified_date = ?
                  WHERE owner_id = ?
                    AND file_type = 'folder'
                    AND file_path = ?
                    AND file_name = ?",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare folder rename statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare folder rename statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+
+            rename_folder_statement
+                .bind((1, new_folder_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind new_folder_name: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_folder_statement
+                .bind((2, new_folder_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind file_caption: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_folder_statement
+                .bind((3, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_folder_statement
+                .bind((4, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_folder_statement
+                .bind((5, parent_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind parent_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_folder_statement
+                .bind((6, current_folder_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind current_folder_name: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        rename_folder_statement
-            .bind((1, new_folder_name))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind new_folder_name: {}", e),
-            })?;
-        rename_folder_statement
-            .bind((2, new_folder_name))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind file_caption: {}", e),
-            })?;
-        rename_folder_statement
-            .bind((3, modified_date))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
-            })?;
-        rename_folder_statement
-            .bind((4, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        rename_folder_statement
-            .bind((5, parent_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind parent_path: {}", e),
-            })?;
-        rename_folder_statement
-            .bind((6, current_folder_name))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind current_folder_name: {}", e),
+            rename_folder_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute folder rename statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        rename_folder_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute folder rename statement: {}", e),
-        })?;
-
-        let prefix_like_pattern = format!("{}/%", source_folder_path);
-        let source_prefix_length = source_folder_path.len() as i64 + 1;
+            let prefix_like_pattern = format!("{}/%", source_folder_path);
+            let source_prefix_length = source_folder_path.len() as i64 + 1;
 
-        let mut rename_children_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+            let mut rename_children_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET file_path = CASE
                      WHEN file_path = ? THEN ?
                      ELSE ? || substr(file_path, ?)
@@ -1776,57 +4161,68 @@ impl Database {
                  modified_date = ?
                  WHERE owner_id = ?
                    AND (file_path = ? OR file_path LIKE ?)",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare child rename statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare child rename statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        rename_children_statement
-            .bind((1, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path (eq): {}", e),
-            })?;
-        rename_children_statement
-            .bind((2, destination_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_folder_path (eq): {}", e),
-            })?;
-        rename_children_statement
-            .bind((3, destination_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_folder_path (prefix): {}", e),
-            })?;
-        rename_children_statement
-            .bind((4, source_prefix_length))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_prefix_length: {}", e),
-            })?;
-        rename_children_statement
-            .bind((5, modified_date))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
-            })?;
-        rename_children_statement
-            .bind((6, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        rename_children_statement
-            .bind((7, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path (where): {}", e),
-            })?;
-        rename_children_statement
-            .bind((8, prefix_like_pattern.as_str()))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind prefix_like_pattern: {}", e),
-            })?;
+            rename_children_statement
+                .bind((1, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path (eq): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_children_statement
+                .bind((2, destination_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_folder_path (eq): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_children_statement
+                .bind((3, destination_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_folder_path (prefix): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_children_statement
+                .bind((4, source_prefix_length))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_prefix_length: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_children_statement
+                .bind((5, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_children_statement
+                .bind((6, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_children_statement
+                .bind((7, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path (where): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            rename_children_statement
+                .bind((8, prefix_like_pattern.as_str()))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind prefix_like_pattern: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        rename_children_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute child rename statement: {}", e),
-        })?;
+            rename_children_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute child rename statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
     pub fn move_telegram_saved_folder_tree(
@@ -1839,57 +4235,66 @@ impl Database {
         destination_folder_path: &str,
         modified_date: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
-        let mut move_folder_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+        // Either the whole tree moves or none of it does
+        with_transaction(&conn, |conn| {
+            let mut move_folder_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET file_path = ?, modified_date = ?
                  WHERE owner_id = ?
                    AND file_type = 'folder'
                    AND file_path = ?
                    AND file_name = ?",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare folder move statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare folder move statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        move_folder_statement
-            .bind((1, destination_parent_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_parent_path: {}", e),
-            })?;
-        move_folder_statement
-            .bind((2, modified_date))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
-            })?;
-        move_folder_statement
-            .bind((3, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        move_folder_statement
-            .bind((4, source_parent_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_parent_path: {}", e),
-            })?;
-        move_folder_statement
-            .bind((5, folder_name))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind folder_name: {}", e),
-            })?;
+            move_folder_statement
+                .bind((1, destination_parent_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_parent_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_folder_statement
+                .bind((2, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_folder_statement
+                .bind((3, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_folder_statement
+                .bind((4, source_parent_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_parent_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_folder_statement
+                .bind((5, folder_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind folder_name: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        move_folder_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute folder move statement: {}", e),
-        })?;
+            move_folder_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute folder move statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        let prefix_like_pattern = format!("{}/%", source_folder_path);
-        let source_prefix_length = source_folder_path.len() as i64 + 1;
+            let prefix_like_pattern = format!("{}/%", source_folder_path);
+            let source_prefix_length = source_folder_path.len() as i64 + 1;
 
-        let mut move_children_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+            let mut move_children_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET file_path = CASE
                      WHEN file_path = ? THEN ?
                      ELSE ? || substr(file_path, ?)
@@ -1897,57 +4302,68 @@ impl Database {
                  modified_date = ?
                  WHERE owner_id = ?
                    AND (file_path = ? OR file_path LIKE ?)",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare child move statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare child move statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        move_children_statement
-            .bind((1, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path (eq): {}", e),
-            })?;
-        move_children_statement
-            .bind((2, destination_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_folder_path (eq): {}", e),
-            })?;
-        move_children_statement
-            .bind((3, destination_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_folder_path (prefix): {}", e),
-            })?;
-        move_children_statement
-            .bind((4, source_prefix_length))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_prefix_length: {}", e),
-            })?;
-        move_children_statement
-            .bind((5, modified_date))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
-            })?;
-        move_children_statement
-            .bind((6, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        move_children_statement
-            .bind((7, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path (where): {}", e),
-            })?;
-        move_children_statement
-            .bind((8, prefix_like_pattern.as_str()))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind prefix_like_pattern: {}", e),
-            })?;
+            move_children_statement
+                .bind((1, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path (eq): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((2, destination_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_folder_path (eq): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((3, destination_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_folder_path (prefix): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((4, source_prefix_length))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_prefix_length: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((5, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((6, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((7, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path (where): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((8, prefix_like_pattern.as_str()))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind prefix_like_pattern: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        move_children_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute child move statement: {}", e),
-        })?;
+            move_children_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute child move statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
     pub fn get_telegram_saved_file_path_and_recycle_origin_by_message_id(
@@ -1955,7 +4371,7 @@ impl Database {
         owner_id: &str,
         message_id: i32,
     ) -> Result<Option<(String, Option<String>)>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -1966,15 +4382,18 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((2, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         match statement.next() {
@@ -1987,6 +4406,128 @@ impl Database {
             Ok(SqliteState::Done) => Ok(None),
             Err(e) => Err(DbError {
                 message: format!("Failed to read file metadata: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    pub fn get_telegram_saved_file_by_message_id(
+        &self,
+        owner_id: &str,
+        message_id: i32,
+    ) -> Result<Option<TelegramSavedItem>, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT
+                chat_id,
+                message_id,
+                thumbnail,
+                file_type,
+                file_unique_id,
+                file_size,
+                file_name,
+                file_caption,
+                file_path,
+                recycle_origin_path,
+                modified_date,
+                owner_id,
+                pinned,
+                image_width,
+                image_height,
+                indexed_at,
+                audio_duration_secs,
+                audio_title,
+                audio_performer
+                 FROM telegram_saved_items
+                 WHERE owner_id = ? AND message_id = ? AND file_type != 'folder'
+                 LIMIT 1",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((2, message_id as i64))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => Ok(Some(read_telegram_saved_item_row(&statement))),
+            Ok(SqliteState::Done) => Ok(None),
+            Err(e) => Err(DbError {
+                message: format!("Failed to read saved item: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    pub fn get_telegram_saved_folder(
+        &self,
+        owner_id: &str,
+        parent_path: &str,
+        folder_name: &str,
+    ) -> Result<Option<TelegramSavedItem>, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT
+                chat_id,
+                message_id,
+                thumbnail,
+                file_type,
+                file_unique_id,
+                file_size,
+                file_name,
+                file_caption,
+                file_path,
+                recycle_origin_path,
+                modified_date,
+                owner_id,
+                pinned,
+                image_width,
+                image_height,
+                indexed_at,
+                audio_duration_secs,
+                audio_title,
+                audio_performer
+                 FROM telegram_saved_items
+                 WHERE owner_id = ? AND file_path = ? AND file_name = ? AND file_type = 'folder'
+                 LIMIT 1",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, parent_path)).map_err(|e| DbError {
+            message: format!("Failed to bind parent_path: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((3, folder_name)).map_err(|e| DbError {
+            message: format!("Failed to bind folder_name: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => Ok(Some(read_telegram_saved_item_row(&statement))),
+            Ok(SqliteState::Done) => Ok(None),
+            Err(e) => Err(DbError {
+                message: format!("Failed to read saved folder: {}", e),
+                kind: sqlite_error_kind(&e),
             }),
         }
     }
@@ -1996,7 +4537,7 @@ impl Database {
         owner_id: &str,
         message_id: i32,
     ) -> Result<Option<String>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -2007,15 +4548,18 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((2, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         match statement.next() {
@@ -2029,7 +4573,48 @@ impl Database {
             }
             Ok(SqliteState::Done) => Ok(None),
             Err(e) => Err(DbError {
-                message: format!("Failed to read file name metadata: {}", e),
+                message: format!("Failed to read file name metadata: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    pub fn is_telegram_saved_item_user_renamed(
+        &self,
+        owner_id: &str,
+        message_id: i32,
+    ) -> Result<bool, DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT 1
+                 FROM telegram_saved_items
+                 WHERE owner_id = ? AND message_id = ? AND file_type != 'folder' AND user_renamed = 1
+                 LIMIT 1",
+            )
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((2, message_id as i64))
+            .map_err(|e| DbError {
+                message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => Ok(true),
+            Ok(SqliteState::Done) => Ok(false),
+            Err(e) => Err(DbError {
+                message: format!("Failed to read rename state: {}", e),
+                kind: sqlite_error_kind(&e),
             }),
         }
     }
@@ -2041,7 +4626,7 @@ impl Database {
         recycle_path: &str,
         modified_date: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -2053,25 +4638,31 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, recycle_path)).map_err(|e| DbError {
             message: format!("Failed to bind recycle_path: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, modified_date)).map_err(|e| DbError {
             message: format!("Failed to bind modified_date: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((3, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((4, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute recycle statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
@@ -2084,7 +4675,7 @@ impl Database {
         destination_path: &str,
         modified_date: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -2096,25 +4687,31 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, destination_path)).map_err(|e| DbError {
             message: format!("Failed to bind destination_path: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, modified_date)).map_err(|e| DbError {
             message: format!("Failed to bind modified_date: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((3, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((4, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute restore statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
@@ -2126,7 +4723,7 @@ impl Database {
         parent_path: &str,
         folder_name: &str,
     ) -> Result<Option<String>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -2140,16 +4737,20 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, parent_path)).map_err(|e| DbError {
             message: format!("Failed to bind parent_path: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((3, folder_name)).map_err(|e| DbError {
             message: format!("Failed to bind folder_name: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         match statement.next() {
@@ -2161,6 +4762,7 @@ impl Database {
             Ok(SqliteState::Done) => Ok(None),
             Err(e) => Err(DbError {
                 message: format!("Failed to read folder recycle origin: {}", e),
+                kind: sqlite_error_kind(&e),
             }),
         }
     }
@@ -2175,133 +4777,153 @@ impl Database {
         destination_folder_path: &str,
         modified_date: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
-        let mut mark_root_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+        with_transaction(&conn, |conn| {
+            let mut mark_root_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET recycle_origin_path = COALESCE(recycle_origin_path, file_path),
                      modified_date = ?
                  WHERE owner_id = ?
                    AND file_type = 'folder'
                    AND file_path = ?
                    AND file_name = ?",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare recycle root mark statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare recycle root mark statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        mark_root_statement
-            .bind((1, modified_date))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
-            })?;
-        mark_root_statement
-            .bind((2, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        mark_root_statement
-            .bind((3, source_parent_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_parent_path: {}", e),
-            })?;
-        mark_root_statement
-            .bind((4, folder_name))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind folder_name: {}", e),
-            })?;
+            mark_root_statement
+                .bind((1, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            mark_root_statement
+                .bind((2, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            mark_root_statement
+                .bind((3, source_parent_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_parent_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            mark_root_statement
+                .bind((4, folder_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind folder_name: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        mark_root_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute recycle root mark statement: {}", e),
-        })?;
+            mark_root_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute recycle root mark statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        let prefix_like_pattern = format!("{}/%", source_folder_path);
+            let prefix_like_pattern = format!("{}/%", source_folder_path);
 
-        let mut mark_children_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+            let mut mark_children_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET recycle_origin_path = COALESCE(recycle_origin_path, file_path),
                      modified_date = ?
                  WHERE owner_id = ?
                    AND (file_path = ? OR file_path LIKE ?)",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare recycle children mark statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare recycle children mark statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        mark_children_statement
-            .bind((1, modified_date))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
-            })?;
-        mark_children_statement
-            .bind((2, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        mark_children_statement
-            .bind((3, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path: {}", e),
-            })?;
-        mark_children_statement
-            .bind((4, prefix_like_pattern.as_str()))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind prefix_like_pattern: {}", e),
-            })?;
+            mark_children_statement
+                .bind((1, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            mark_children_statement
+                .bind((2, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            mark_children_statement
+                .bind((3, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            mark_children_statement
+                .bind((4, prefix_like_pattern.as_str()))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind prefix_like_pattern: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        mark_children_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute recycle children mark statement: {}", e),
-        })?;
+            mark_children_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute recycle children mark statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        let mut move_root_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+            let mut move_root_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET file_path = ?, modified_date = ?
                  WHERE owner_id = ?
                    AND file_type = 'folder'
                    AND file_path = ?
                    AND file_name = ?",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare recycle root move statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare recycle root move statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        move_root_statement
-            .bind((1, recycle_parent_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind recycle_parent_path: {}", e),
-            })?;
-        move_root_statement
-            .bind((2, modified_date))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
-            })?;
-        move_root_statement
-            .bind((3, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        move_root_statement
-            .bind((4, source_parent_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_parent_path: {}", e),
-            })?;
-        move_root_statement
-            .bind((5, folder_name))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind folder_name: {}", e),
-            })?;
+            move_root_statement
+                .bind((1, recycle_parent_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind recycle_parent_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_root_statement
+                .bind((2, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_root_statement
+                .bind((3, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_root_statement
+                .bind((4, source_parent_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_parent_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_root_statement
+                .bind((5, folder_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind folder_name: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        move_root_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute recycle root move statement: {}", e),
-        })?;
+            move_root_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute recycle root move statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        let source_prefix_length = source_folder_path.len() as i64 + 1;
-        let mut move_children_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+            let source_prefix_length = source_folder_path.len() as i64 + 1;
+            let mut move_children_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET file_path = CASE
                      WHEN file_path = ? THEN ?
                      ELSE ? || substr(file_path, ?)
@@ -2309,57 +4931,68 @@ impl Database {
                  modified_date = ?
                  WHERE owner_id = ?
                    AND (file_path = ? OR file_path LIKE ?)",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare recycle children move statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare recycle children move statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        move_children_statement
-            .bind((1, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path (eq): {}", e),
-            })?;
-        move_children_statement
-            .bind((2, destination_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_folder_path (eq): {}", e),
-            })?;
-        move_children_statement
-            .bind((3, destination_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_folder_path (prefix): {}", e),
-            })?;
-        move_children_statement
-            .bind((4, source_prefix_length))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_prefix_length: {}", e),
-            })?;
-        move_children_statement
-            .bind((5, modified_date))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
-            })?;
-        move_children_statement
-            .bind((6, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        move_children_statement
-            .bind((7, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path (where): {}", e),
-            })?;
-        move_children_statement
-            .bind((8, prefix_like_pattern.as_str()))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind prefix_like_pattern: {}", e),
-            })?;
+            move_children_statement
+                .bind((1, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path (eq): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((2, destination_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_folder_path (eq): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((3, destination_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_folder_path (prefix): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((4, source_prefix_length))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_prefix_length: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((5, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((6, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((7, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path (where): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            move_children_statement
+                .bind((8, prefix_like_pattern.as_str()))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind prefix_like_pattern: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        move_children_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute recycle children move statement: {}", e),
-        })?;
+            move_children_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute recycle children move statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
     pub fn restore_telegram_saved_folder_tree(
@@ -2372,11 +5005,12 @@ impl Database {
         destination_folder_path: &str,
         modified_date: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
-        let mut restore_root_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+        with_transaction(&conn, |conn| {
+            let mut restore_root_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET file_path = ?,
                      modified_date = ?,
                      recycle_origin_path = NULL
@@ -2384,47 +5018,54 @@ impl Database {
                    AND file_type = 'folder'
                    AND file_path = ?
                    AND file_name = ?",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare restore root statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare restore root statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        restore_root_statement
-            .bind((1, destination_parent_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_parent_path: {}", e),
-            })?;
-        restore_root_statement
-            .bind((2, modified_date))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
-            })?;
-        restore_root_statement
-            .bind((3, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        restore_root_statement
-            .bind((4, source_parent_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_parent_path: {}", e),
-            })?;
-        restore_root_statement
-            .bind((5, folder_name))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind folder_name: {}", e),
-            })?;
+            restore_root_statement
+                .bind((1, destination_parent_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_parent_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_root_statement
+                .bind((2, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_root_statement
+                .bind((3, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_root_statement
+                .bind((4, source_parent_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_parent_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_root_statement
+                .bind((5, folder_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind folder_name: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        restore_root_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute restore root statement: {}", e),
-        })?;
+            restore_root_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute restore root statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        let prefix_like_pattern = format!("{}/%", source_folder_path);
-        let source_prefix_length = source_folder_path.len() as i64 + 1;
+            let prefix_like_pattern = format!("{}/%", source_folder_path);
+            let source_prefix_length = source_folder_path.len() as i64 + 1;
 
-        let mut restore_children_statement = conn
-            .prepare(
-                "UPDATE telegram_saved_items
+            let mut restore_children_statement = conn
+                .prepare(
+                    "UPDATE telegram_saved_items
                  SET file_path = CASE
                      WHEN file_path = ? THEN ?
                      ELSE ? || substr(file_path, ?)
@@ -2433,104 +5074,267 @@ impl Database {
                  recycle_origin_path = NULL
                  WHERE owner_id = ?
                    AND (file_path = ? OR file_path LIKE ?)",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare restore children statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare restore children statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        restore_children_statement
-            .bind((1, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path (eq): {}", e),
-            })?;
-        restore_children_statement
-            .bind((2, destination_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_folder_path (eq): {}", e),
-            })?;
-        restore_children_statement
-            .bind((3, destination_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind destination_folder_path (prefix): {}", e),
-            })?;
-        restore_children_statement
-            .bind((4, source_prefix_length))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_prefix_length: {}", e),
+            restore_children_statement
+                .bind((1, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path (eq): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_children_statement
+                .bind((2, destination_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_folder_path (eq): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_children_statement
+                .bind((3, destination_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind destination_folder_path (prefix): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_children_statement
+                .bind((4, source_prefix_length))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_prefix_length: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_children_statement
+                .bind((5, modified_date))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind modified_date: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_children_statement
+                .bind((6, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_children_statement
+                .bind((7, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path (where): {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            restore_children_statement
+                .bind((8, prefix_like_pattern.as_str()))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind prefix_like_pattern: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+
+            restore_children_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute restore children statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
-        restore_children_statement
-            .bind((5, modified_date))
+
+            Ok(())
+        })
+    }
+
+    pub fn get_telegram_saved_message_ids_by_folder_tree(
+        &self,
+        owner_id: &str,
+        source_folder_path: &str,
+    ) -> Result<Vec<i32>, DbError> {
+        let conn = self.0.get()?;
+
+        let prefix_like_pattern = format!("{}/%", source_folder_path);
+        let mut statement = conn
+            .prepare(
+                "SELECT message_id
+                 FROM telegram_saved_items
+                 WHERE owner_id = ?
+                   AND file_type != 'folder'
+                   AND (file_path = ? OR file_path LIKE ?)",
+            )
             .map_err(|e| DbError {
-                message: format!("Failed to bind modified_date: {}", e),
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
-        restore_children_statement
-            .bind((6, owner_id))
+
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement
+            .bind((2, source_folder_path))
             .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
+                message: format!("Failed to bind source_folder_path: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
-        restore_children_statement
-            .bind((7, source_folder_path))
+        statement
+            .bind((3, prefix_like_pattern.as_str()))
             .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path (where): {}", e),
+                message: format!("Failed to bind prefix_like_pattern: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
-        restore_children_statement
-            .bind((8, prefix_like_pattern.as_str()))
+
+        let mut message_ids = Vec::new();
+        while let Ok(SqliteState::Row) = statement.next() {
+            let message_id = statement.read::<i64, usize>(0).unwrap_or(0) as i32;
+            if message_id > 0 {
+                message_ids.push(message_id);
+            }
+        }
+
+        message_ids.sort_unstable();
+        message_ids.dedup();
+        Ok(message_ids)
+    }
+
+    /// Message ids of the files directly inside `folder_path`, plus the number of
+    /// subfolders found there.
+    pub fn get_telegram_saved_message_ids_in_folder(
+        &self,
+        owner_id: &str,
+        folder_path: &str,
+    ) -> Result<(Vec<i32>, usize), DbError> {
+        let conn = self.0.get()?;
+
+        let mut statement = conn
+            .prepare(
+                "SELECT message_id, file_type
+                 FROM telegram_saved_items
+                 WHERE owner_id = ? AND file_path = ?",
+            )
             .map_err(|e| DbError {
-                message: format!("Failed to bind prefix_like_pattern: {}", e),
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        restore_children_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute restore children statement: {}", e),
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, folder_path)).map_err(|e| DbError {
+            message: format!("Failed to bind folder_path: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
-        Ok(())
+        let mut message_ids = Vec::new();
+        let mut folder_count = 0usize;
+        while let Ok(SqliteState::Row) = statement.next() {
+            let file_type = statement.read::<String, usize>(1).unwrap_or_default();
+            if file_type == "folder" {
+                folder_count += 1;
+                continue;
+            }
+
+            let message_id = statement.read::<i64, usize>(0).unwrap_or(0) as i32;
+            if message_id > 0 {
+                message_ids.push(message_id);
+            }
+        }
+
+        message_ids.sort_unstable();
+        message_ids.dedup();
+        Ok((message_ids, folder_count))
     }
 
-    pub fn get_telegram_saved_message_ids_by_folder_tree(
+    // Thumbnail of the newest file directly inside `folder_path` that has one
+    pub fn get_first_thumbnail_in_folder(
         &self,
         owner_id: &str,
-        source_folder_path: &str,
-    ) -> Result<Vec<i32>, DbError> {
-        let conn = self.0.lock().unwrap();
+        folder_path: &str,
+    ) -> Result<Option<String>, DbError> {
+        let conn = self.0.get()?;
 
-        let prefix_like_pattern = format!("{}/%", source_folder_path);
         let mut statement = conn
             .prepare(
-                "SELECT message_id
+                "SELECT thumbnail
                  FROM telegram_saved_items
-                 WHERE owner_id = ?
-                   AND file_type != 'folder'
-                   AND (file_path = ? OR file_path LIKE ?)",
+                 WHERE owner_id = ? AND file_path = ? AND file_type != 'folder'
+                   AND thumbnail IS NOT NULL AND thumbnail != ''
+                 ORDER BY message_id DESC
+                 LIMIT 1",
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, folder_path)).map_err(|e| DbError {
+            message: format!("Failed to bind folder_path: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        match statement.next() {
+            Ok(SqliteState::Row) => Ok(statement.read::<Option<String>, usize>(0).unwrap_or(None)),
+            Ok(SqliteState::Done) => Ok(None),
+            Err(e) => Err(DbError {
+                message: format!("Failed to read folder thumbnail: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    // Files anywhere below `folder_path`. Counts for folders outside Recycle Bin
+    // leave out recycled files, which still sit under `/Home`.
+    pub fn count_items_in_folder_tree(
+        &self,
+        owner_id: &str,
+        folder_path: &str,
+    ) -> Result<i64, DbError> {
+        let conn = self.0.get()?;
+
+        let in_recycle_bin =
+            folder_path == "/Home/Recycle Bin" || folder_path.starts_with("/Home/Recycle Bin/");
+        let query = if in_recycle_bin {
+            "SELECT COUNT(*)
+             FROM telegram_saved_items
+             WHERE owner_id = ?
+               AND file_type != 'folder'
+               AND (file_path = ? OR file_path LIKE ?)"
+        } else {
+            "SELECT COUNT(*)
+             FROM telegram_saved_items
+             WHERE owner_id = ?
+               AND file_type != 'folder'
+               AND (file_path = ? OR file_path LIKE ?)
+               AND file_path != '/Home/Recycle Bin'
+               AND file_path NOT LIKE '/Home/Recycle Bin/%'"
+        };
+
+        let mut statement = conn.prepare(query).map_err(|e| DbError {
+            message: format!("Failed to prepare statement: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+
+        let prefix_like_pattern = format!("{}/%", folder_path.trim_end_matches('/'));
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        statement.bind((2, folder_path)).map_err(|e| DbError {
+            message: format!("Failed to bind folder_path: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
-        statement
-            .bind((2, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path: {}", e),
-            })?;
         statement
             .bind((3, prefix_like_pattern.as_str()))
             .map_err(|e| DbError {
-                message: format!("Failed to bind prefix_like_pattern: {}", e),
+                message: format!("Failed to bind folder_path prefix: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
-        let mut message_ids = Vec::new();
-        while let Ok(SqliteState::Row) = statement.next() {
-            let message_id = statement.read::<i64, usize>(0).unwrap_or(0) as i32;
-            if message_id > 0 {
-                message_ids.push(message_id);
-            }
+        match statement.next() {
+            Ok(SqliteState::Row) => Ok(statement.read::<i64, usize>(0).unwrap_or(0)),
+            Ok(SqliteState::Done) => Ok(0),
+            Err(e) => Err(DbError {
+                message: format!("Failed to count folder items: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
         }
-
-        message_ids.sort_unstable();
-        message_ids.dedup();
-        Ok(message_ids)
     }
 
     pub fn delete_telegram_saved_file_by_message_id(
@@ -2538,7 +5342,7 @@ impl Database {
         owner_id: &str,
         message_id: i32,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -2547,19 +5351,23 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement
             .bind((2, message_id as i64))
             .map_err(|e| DbError {
                 message: format!("Failed to bind message_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.next().map_err(|e| DbError {
             message: format!("Failed to execute delete statement: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         Ok(())
@@ -2572,72 +5380,84 @@ impl Database {
         folder_name: &str,
         source_folder_path: &str,
     ) -> Result<(), DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
-        let mut delete_root_statement = conn
-            .prepare(
-                "DELETE FROM telegram_saved_items
+        with_transaction(&conn, |conn| {
+            let mut delete_root_statement = conn
+                .prepare(
+                    "DELETE FROM telegram_saved_items
                  WHERE owner_id = ?
                    AND file_type = 'folder'
                    AND file_path = ?
                    AND file_name = ?",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare root delete statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare root delete statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        delete_root_statement
-            .bind((1, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        delete_root_statement
-            .bind((2, source_parent_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_parent_path: {}", e),
-            })?;
-        delete_root_statement
-            .bind((3, folder_name))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind folder_name: {}", e),
-            })?;
+            delete_root_statement
+                .bind((1, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            delete_root_statement
+                .bind((2, source_parent_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_parent_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            delete_root_statement
+                .bind((3, folder_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind folder_name: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        delete_root_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute root delete statement: {}", e),
-        })?;
+            delete_root_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute root delete statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        let prefix_like_pattern = format!("{}/%", source_folder_path);
-        let mut delete_children_statement = conn
-            .prepare(
-                "DELETE FROM telegram_saved_items
+            let prefix_like_pattern = format!("{}/%", source_folder_path);
+            let mut delete_children_statement = conn
+                .prepare(
+                    "DELETE FROM telegram_saved_items
                  WHERE owner_id = ?
                    AND (file_path = ? OR file_path LIKE ?)",
-            )
-            .map_err(|e| DbError {
-                message: format!("Failed to prepare tree delete statement: {}", e),
-            })?;
+                )
+                .map_err(|e| DbError {
+                    message: format!("Failed to prepare tree delete statement: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        delete_children_statement
-            .bind((1, owner_id))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind owner_id: {}", e),
-            })?;
-        delete_children_statement
-            .bind((2, source_folder_path))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind source_folder_path: {}", e),
-            })?;
-        delete_children_statement
-            .bind((3, prefix_like_pattern.as_str()))
-            .map_err(|e| DbError {
-                message: format!("Failed to bind prefix_like_pattern: {}", e),
-            })?;
+            delete_children_statement
+                .bind((1, owner_id))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind owner_id: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            delete_children_statement
+                .bind((2, source_folder_path))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind source_folder_path: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+            delete_children_statement
+                .bind((3, prefix_like_pattern.as_str()))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind prefix_like_pattern: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
 
-        delete_children_statement.next().map_err(|e| DbError {
-            message: format!("Failed to execute tree delete statement: {}", e),
-        })?;
+            delete_children_statement.next().map_err(|e| DbError {
+                message: format!("Failed to execute tree delete statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
     pub fn delete_telegram_messages_by_ids(
@@ -2649,7 +5469,7 @@ impl Database {
             return Ok(());
         }
 
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
         for message_id in message_ids.iter().copied().filter(|value| *value > 0) {
             let mut statement = conn
                 .prepare("DELETE FROM telegram_messages WHERE chat_id = ? AND message_id = ?")
@@ -2658,15 +5478,18 @@ impl Database {
                         "Failed to prepare telegram_messages delete statement: {}",
                         e
                     ),
+                    kind: sqlite_error_kind(&e),
                 })?;
 
             statement.bind((1, chat_id)).map_err(|e| DbError {
                 message: format!("Failed to bind chat_id: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
             statement
                 .bind((2, message_id as i64))
                 .map_err(|e| DbError {
                     message: format!("Failed to bind message_id: {}", e),
+                    kind: sqlite_error_kind(&e),
                 })?;
 
             statement.next().map_err(|e| DbError {
@@ -2674,6 +5497,7 @@ impl Database {
                     "Failed to execute telegram_messages delete statement: {}",
                     e
                 ),
+                kind: sqlite_error_kind(&e),
             })?;
         }
 
@@ -2687,7 +5511,7 @@ impl Database {
         offset: i64,
         limit: i64,
     ) -> Result<Vec<TelegramSavedItem>, DbError> {
-        let conn = self.0.lock().unwrap();
+        let conn = self.0.get()?;
 
         let mut statement = conn
             .prepare(
@@ -2703,10 +5527,18 @@ impl Database {
                 file_path,
                 recycle_origin_path,
                 modified_date,
-                owner_id
+                owner_id,
+                pinned,
+                image_width,
+                image_height,
+                indexed_at,
+                audio_duration_secs,
+                audio_title,
+                audio_performer
              FROM telegram_saved_items
              WHERE owner_id = ? AND file_path = ?
              ORDER BY
+                COALESCE(pinned, 0) DESC,
                 CASE WHEN file_type = 'folder' THEN 0 ELSE 1 END,
                 CASE WHEN file_type = 'folder' THEN LOWER(file_name) ELSE '' END,
                 CASE WHEN file_type = 'folder' THEN 0 ELSE message_id END DESC,
@@ -2715,57 +5547,101 @@ impl Database {
             )
             .map_err(|e| DbError {
                 message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
             })?;
 
         statement.bind((1, owner_id)).map_err(|e| DbError {
             message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((2, file_path)).map_err(|e| DbError {
             message: format!("Failed to bind file_path: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((3, limit)).map_err(|e| DbError {
             message: format!("Failed to bind limit: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
         statement.bind((4, offset)).map_err(|e| DbError {
             message: format!("Failed to bind offset: {}", e),
+            kind: sqlite_error_kind(&e),
         })?;
 
         let mut items = Vec::new();
         while let Ok(SqliteState::Row) = statement.next() {
-            items.push(TelegramSavedItem {
-                chat_id: statement.read::<i64, usize>(0).unwrap_or(0),
-                message_id: statement.read::<i64, usize>(1).unwrap_or(0) as i32,
-                thumbnail: statement.read::<Option<String>, usize>(2).unwrap_or(None),
-                file_type: statement
-                    .read::<String, usize>(3)
-                    .unwrap_or_else(|_| "file".to_string()),
-                file_unique_id: statement.read::<String, usize>(4).unwrap_or_default(),
-                file_size: statement.read::<i64, usize>(5).unwrap_or(0),
-                file_name: statement.read::<String, usize>(6).unwrap_or_default(),
-                file_caption: statement.read::<Option<String>, usize>(7).unwrap_or(None),
-                file_path: statement.read::<String, usize>(8).unwrap_or_default(),
-                recycle_origin_path: statement.read::<Option<String>, usize>(9).unwrap_or(None),
-                modified_date: statement.read::<String, usize>(10).unwrap_or_default(),
-                owner_id: statement.read::<String, usize>(11).unwrap_or_default(),
-            });
+            items.push(read_telegram_saved_item_row(&statement));
         }
 
         Ok(items)
     }
 
     pub fn ensure_telegram_saved_folders(&self, owner_id: &str) -> Result<(), DbError> {
+        let locale = self
+            .get_setting(SAVED_FOLDERS_LOCALE_SETTING_KEY)?
+            .unwrap_or_else(|| "en".to_string());
+
+        self.ensure_telegram_saved_folders_for_locale(owner_id, &locale)
+    }
+
+    fn count_default_saved_folders(&self, owner_id: &str) -> Result<usize, DbError> {
+        let conn = self.0.get()?;
+
+        let placeholders = vec!["?"; DEFAULT_SAVED_FOLDERS.len()].join(", ");
+        let mut statement = conn
+            .prepare(format!(
+                "SELECT COUNT(DISTINCT file_name) FROM telegram_saved_items
+                 WHERE owner_id = ? AND file_type = 'folder' AND file_path = '/Home'
+                   AND file_name IN ({})",
+                placeholders
+            ))
+            .map_err(|e| DbError {
+                message: format!("Failed to prepare statement: {}", e),
+                kind: sqlite_error_kind(&e),
+            })?;
+        statement.bind((1, owner_id)).map_err(|e| DbError {
+            message: format!("Failed to bind owner_id: {}", e),
+            kind: sqlite_error_kind(&e),
+        })?;
+        for (index, folder_name) in DEFAULT_SAVED_FOLDERS.iter().enumerate() {
+            statement
+                .bind((index + 2, *folder_name))
+                .map_err(|e| DbError {
+                    message: format!("Failed to bind folder name: {}", e),
+                    kind: sqlite_error_kind(&e),
+                })?;
+        }
+
+        match statement.next() {
+            Ok(SqliteState::Row) => Ok(statement.read::<i64, usize>(0).unwrap_or(0) as usize),
+            Ok(SqliteState::Done) => Ok(0),
+            Err(e) => Err(DbError {
+                message: format!("Failed to count default folders: {}", e),
+                kind: sqlite_error_kind(&e),
+            }),
+        }
+    }
+
+    fn ensure_telegram_saved_folders_for_locale(
+        &self,
+        owner_id: &str,
+        locale: &str,
+    ) -> Result<(), DbError> {
+        // The flag records the locale the folders were last written with, so a
+        // locale change re-labels them. It only counts while every default folder
+        // row is still there; a lost one is written again below.
+        let ready_key = format!("tg_saved_folders_ready_{}", owner_id);
+        if self.get_setting(&ready_key)?.as_deref() == Some(locale)
+            && self.count_default_saved_folders(owner_id)? == DEFAULT_SAVED_FOLDERS.len()
+        {
+            return Ok(());
+        }
+
         let now = chrono::Utc::now().to_rfc3339();
         let root = "/Home";
-        let folders = [
-            "Images",
-            "Videos",
-            "Audios",
-            "Documents",
-            "Notes",
-            "Recycle Bin",
-        ];
 
-        for folder_name in folders {
+        for folder_name in DEFAULT_SAVED_FOLDERS {
+            // file_name stays English because it is part of every child's path;
+            // only the caption shown to the user is localized
             let item = TelegramSavedItem {
                 chat_id: 0,
                 message_id: 0,
@@ -2774,16 +5650,25 @@ impl Database {
                 file_unique_id: format!("folder_{}_{}", owner_id, folder_name.to_lowercase()),
                 file_size: 0,
                 file_name: folder_name.to_string(),
-                file_caption: Some(folder_name.to_string()),
+                file_caption: Some(saved_folder_display_name(folder_name, locale).to_string()),
                 file_path: root.to_string(),
                 recycle_origin_path: None,
                 modified_date: now.clone(),
                 owner_id: owner_id.to_string(),
+                pinned: false,
+                image_width: None,
+                image_height: None,
+                indexed_at: None,
+                audio_duration_secs: None,
+                audio_title: None,
+                audio_performer: None,
             };
 
             self.upsert_telegram_saved_item(&item)?;
         }
 
+        self.set_setting(&ready_key, locale)?;
+
         Ok(())
     }
 }
@@ -2798,11 +5683,46 @@ pub async fn db_get_setting(
 
 #[tauri::command]
 pub async fn db_set_setting(
+    app: AppHandle,
     state: State<'_, Database>,
     key: String,
     value: String,
 ) -> Result<(), DbError> {
-    state.set_setting(&key, &value)
+    // An empty value clears the download directory back to the OS default
+    if key == DOWNLOAD_DIRECTORY_SETTING_KEY && !value.trim().is_empty() {
+        validate_download_directory(Path::new(value.trim()))?;
+    }
+
+    state.set_setting(&key, &value)?;
+
+    if let Err(error) = app.emit(SETTING_CHANGED_EVENT, SettingChanged { key, value }) {
+        log::warn!("Failed to emit setting changed event: {}", error);
+    }
+
+    Ok(())
+}
+
+fn validate_download_directory(dir: &Path) -> Result<(), DbError> {
+    if !dir.is_dir() {
+        return Err(DbError {
+            message: format!("Download directory does not exist: {}", dir.display()),
+            kind: "NotFound".to_string(),
+        });
+    }
+
+    // Directory permissions are not portable to inspect, so try writing a file
+    let probe_path = dir.join(format!(".skybox-write-test-{}", std::process::id()));
+    fs::write(&probe_path, b"").map_err(|e| DbError {
+        message: format!(
+            "Download directory is not writable {}: {}",
+            dir.display(),
+            e
+        ),
+        kind: io_error_kind(&e),
+    })?;
+    let _ = fs::remove_file(&probe_path);
+
+    Ok(())
 }
 
 #[tauri::command]
@@ -2823,6 +5743,24 @@ pub async fn db_get_favorites(state: State<'_, Database>) -> Result<Vec<Favorite
     state.get_favorites()
 }
 
+#[tauri::command]
+pub async fn db_is_favorite(state: State<'_, Database>, path: String) -> Result<bool, DbError> {
+    state.is_favorite(&path)
+}
+
+#[tauri::command]
+pub async fn db_are_favorites(
+    state: State<'_, Database>,
+    paths: Vec<String>,
+) -> Result<Vec<bool>, DbError> {
+    let favorite_paths: HashSet<String> = state.get_favorite_paths()?.into_iter().collect();
+
+    Ok(paths
+        .iter()
+        .map(|path| favorite_paths.contains(path))
+        .collect())
+}
+
 #[tauri::command]
 pub async fn db_add_favorite(
     state: State<'_, Database>,
@@ -2832,6 +5770,14 @@ pub async fn db_add_favorite(
     state.add_favorite(&path, &label)
 }
 
+#[tauri::command]
+pub async fn db_add_favorites(
+    state: State<'_, Database>,
+    entries: Vec<(String, String)>,
+) -> Result<FavoritesAdded, DbError> {
+    state.add_favorites(entries)
+}
+
 #[tauri::command]
 pub async fn db_remove_favorite(state: State<'_, Database>, id: i32) -> Result<(), DbError> {
     state.remove_favorite(id)
@@ -2843,6 +5789,7 @@ pub async fn db_get_session(state: State<'_, Database>) -> Result<Option<Session
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn db_create_session(
     state: State<'_, Database>,
     phone: String,
@@ -2851,6 +5798,7 @@ pub async fn db_create_session(
     first_name: Option<String>,
     last_name: Option<String>,
     username: Option<String>,
+    user_id: Option<i64>,
 ) -> Result<i32, DbError> {
     state.create_session(
         &phone,
@@ -2859,9 +5807,52 @@ pub async fn db_create_session(
         first_name.as_deref(),
         last_name.as_deref(),
         username.as_deref(),
+        user_id,
     )
 }
 
+#[tauri::command]
+pub async fn db_export_database(
+    state: State<'_, Database>,
+    destination_path: String,
+) -> Result<(), DbError> {
+    state.export_database(Path::new(&destination_path))
+}
+
+#[tauri::command]
+pub async fn db_maintenance(state: State<'_, Database>) -> Result<serde_json::Value, DbError> {
+    if crate::telegram::messages::is_indexing_in_progress() {
+        return Err(DbError {
+            message: "Wait for Saved Messages indexing to finish before running maintenance"
+                .to_string(),
+            kind: "Busy".to_string(),
+        });
+    }
+
+    state.run_maintenance()
+}
+
+#[tauri::command]
+pub async fn db_import_database(
+    state: State<'_, Database>,
+    source_path: String,
+) -> Result<(), DbError> {
+    state.stage_database_import(Path::new(&source_path))
+}
+
+#[tauri::command]
+pub async fn db_list_sessions(state: State<'_, Database>) -> Result<Vec<Session>, DbError> {
+    state.list_sessions()
+}
+
+#[tauri::command]
+pub async fn db_switch_active_session(
+    state: State<'_, Database>,
+    user_id: i64,
+) -> Result<(), DbError> {
+    state.switch_active_session(user_id)
+}
+
 #[tauri::command]
 pub async fn db_update_session_profile_photo(
     state: State<'_, Database>,
@@ -2888,3 +5879,301 @@ pub async fn db_update_session_user_info(
 pub async fn db_clear_session(state: State<'_, Database>) -> Result<(), DbError> {
     state.clear_session()
 }
+
+// A migrated database in the temp directory, deleted again when dropped
+#[cfg(test)]
+pub(crate) struct TempDatabase {
+    db: Database,
+    path: PathBuf,
+}
+
+#[cfg(test)]
+impl TempDatabase {
+    pub(crate) fn new(label: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "skybox-test-{}-{}-{}.db",
+            label,
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let db = Database::open(path.clone()).expect("temp database opens");
+        TempDatabase { db, path }
+    }
+}
+
+#[cfg(test)]
+impl Deref for TempDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm", ".import"] {
+            let _ = fs::remove_file(format!("{}{}", self.path.display(), suffix));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn saved_file(file_path: &str, message_id: i32) -> TelegramSavedItem {
+        TelegramSavedItem {
+            chat_id: 42,
+            message_id,
+            thumbnail: None,
+            file_type: "document".to_string(),
+            file_unique_id: format!("msg_42_{}", message_id),
+            file_size: 1,
+            file_name: format!("file-{}.pdf", message_id),
+            file_caption: None,
+            file_path: file_path.to_string(),
+            recycle_origin_path: None,
+            modified_date: "2026-01-01T00:00:00+00:00".to_string(),
+            owner_id: "42".to_string(),
+            pinned: false,
+            image_width: None,
+            image_height: None,
+            indexed_at: None,
+            audio_duration_secs: None,
+            audio_title: None,
+            audio_performer: None,
+        }
+    }
+
+    #[test]
+    fn export_and_import_use_the_open_database_file() {
+        let db = TempDatabase::new("export");
+        db.set_setting("export_marker", "temp").unwrap();
+
+        let export_path = sidecar_path(&db.path, ".export");
+        db.export_database(&export_path).unwrap();
+        let exported = sqlite::Connection::open(&export_path).unwrap();
+        let mut statement = exported
+            .prepare("SELECT value FROM settings WHERE key = 'export_marker'")
+            .unwrap();
+        assert_eq!(statement.next().unwrap(), SqliteState::Row);
+        assert_eq!(statement.read::<String, usize>(0).unwrap(), "temp");
+        drop(statement);
+        drop(exported);
+
+        db.stage_database_import(&export_path).unwrap();
+        assert!(get_pending_import_path(&db.path).exists());
+        fs::remove_file(&export_path).unwrap();
+    }
+
+    #[test]
+    fn maintenance_measures_the_open_database_file() {
+        let db = TempDatabase::new("maintenance");
+        for message_id in 1..=200 {
+            db.upsert_telegram_saved_item(&saved_file("/Home/Documents", message_id))
+                .unwrap();
+        }
+        for message_id in 1..=200 {
+            db.delete_telegram_saved_file_by_message_id("42", message_id)
+                .unwrap();
+        }
+
+        let report = db.run_maintenance().unwrap();
+        assert_eq!(report["integrity_ok"], true);
+        assert_eq!(
+            report["size_bytes"].as_u64(),
+            Some(fs::metadata(&db.path).unwrap().len())
+        );
+        assert!(report["reclaimed_bytes"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn reindexing_keeps_recorded_audio_metadata() {
+        let db = TempDatabase::new("audio-metadata");
+        db.upsert_telegram_saved_item(&saved_file("/Home/Audios", 7))
+            .unwrap();
+        db.update_telegram_saved_item_audio_metadata("42", 7, Some(181), Some("Song"), None)
+            .unwrap();
+
+        // A re-index rebuilds the item without the track info
+        db.upsert_telegram_saved_item(&saved_file("/Home/Audios", 7))
+            .unwrap();
+
+        let items = db
+            .get_telegram_saved_items_by_path("42", "/Home/Audios")
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].audio_duration_secs, Some(181));
+        assert_eq!(items[0].audio_title.as_deref(), Some("Song"));
+        assert_eq!(items[0].audio_performer, None);
+    }
+
+    #[test]
+    fn listing_is_not_blocked_by_an_open_write_transaction() {
+        let db = TempDatabase::new("listing-latency");
+        for message_id in 1..=50 {
+            db.upsert_telegram_saved_item(&saved_file("/Home/Documents", message_id))
+                .unwrap();
+        }
+
+        // A backfill holding its write transaction for a while
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let writer = {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                let conn = db.0.get().unwrap();
+                with_transaction(&conn, |conn| {
+                    conn.execute(
+                        "UPDATE telegram_saved_items SET file_size = 2 WHERE owner_id = '42'",
+                    )
+                    .unwrap();
+                    started_tx.send(()).unwrap();
+                    std::thread::sleep(Duration::from_millis(500));
+                    Ok(())
+                })
+                .unwrap();
+            })
+        };
+        started_rx.recv().unwrap();
+
+        let started = Instant::now();
+        let items = db
+            .get_telegram_saved_items_by_path("42", "/Home/Documents")
+            .unwrap();
+        let latency = started.elapsed();
+
+        assert_eq!(items.len(), 50);
+        // Readers see the last committed state, not the open transaction
+        assert!(items.iter().all(|item| item.file_size == 1));
+        assert!(
+            latency < Duration::from_millis(250),
+            "listing took {:?} while a write transaction was open",
+            latency
+        );
+
+        writer.join().unwrap();
+        let items = db
+            .get_telegram_saved_items_by_path("42", "/Home/Documents")
+            .unwrap();
+        assert!(items.iter().all(|item| item.file_size == 2));
+    }
+
+    #[test]
+    fn favorite_ids_are_unique_and_increasing() {
+        let db = TempDatabase::new("favorite-ids");
+
+        let ids: Vec<i32> = (0..200)
+            .map(|i| {
+                db.add_favorite(&format!("/tmp/favorite-{}", i), "label")
+                    .unwrap()
+            })
+            .collect();
+
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let favorites = db.get_favorites().unwrap();
+        for (i, id) in ids.iter().enumerate() {
+            let favorite = favorites.iter().find(|f| f.id == *id).unwrap();
+            assert_eq!(favorite.path, format!("/tmp/favorite-{}", i));
+        }
+    }
+
+    #[test]
+    fn concurrent_favorite_inserts_get_their_own_ids() {
+        let db = TempDatabase::new("favorite-ids-concurrent");
+
+        let handles: Vec<_> = (0..4)
+            .map(|thread| {
+                let db = db.clone();
+                std::thread::spawn(move || {
+                    (0..50)
+                        .map(|i| {
+                            let path = format!("/tmp/thread-{}-{}", thread, i);
+                            (db.add_favorite(&path, "label").unwrap(), path)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let inserted: Vec<(i32, String)> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+
+        let unique_ids: HashSet<i32> = inserted.iter().map(|(id, _)| *id).collect();
+        assert_eq!(unique_ids.len(), inserted.len());
+
+        let favorites = db.get_favorites().unwrap();
+        for (id, path) in &inserted {
+            assert_eq!(&favorites.iter().find(|f| f.id == *id).unwrap().path, path);
+        }
+    }
+
+    #[test]
+    fn connection_left_in_a_transaction_is_not_pooled() {
+        let db = TempDatabase::new("rollback-failure");
+        let idle_before = db.0.idle.lock().unwrap().len();
+
+        {
+            let conn = db.0.get().unwrap();
+            // Ending the transaction early makes the final ROLLBACK fail
+            let result: Result<(), DbError> = with_transaction(&conn, |conn| {
+                conn.execute("ROLLBACK").unwrap();
+                Err(DbError {
+                    message: "fail".to_string(),
+                    kind: "Sqlite".to_string(),
+                })
+            });
+            assert!(result.is_err());
+        }
+
+        assert_eq!(db.0.idle.lock().unwrap().len(), idle_before - 1);
+    }
+
+    #[test]
+    fn failed_transaction_writes_nothing() {
+        let db = TempDatabase::new("rollback");
+        let conn = db.0.get().unwrap();
+
+        let result: Result<(), DbError> = with_transaction(&conn, |conn| {
+            insert_favorite(conn, "/tmp/first", "first")?;
+            insert_favorite(conn, "/tmp/second", "second")?;
+            Err(DbError {
+                message: "simulated failure".to_string(),
+                kind: "Other".to_string(),
+            })
+        });
+        drop(conn);
+
+        assert_eq!(result.unwrap_err().message, "simulated failure");
+        assert!(db.get_favorites().unwrap().is_empty());
+
+        // The connection is usable again and a successful closure commits
+        let conn = db.0.get().unwrap();
+        with_transaction(&conn, |conn| insert_favorite(conn, "/tmp/third", "third")).unwrap();
+        drop(conn);
+        let favorites = db.get_favorites().unwrap();
+        assert_eq!(favorites.len(), 1);
+        assert_eq!(favorites[0].path, "/tmp/third");
+    }
+
+    #[test]
+    fn create_session_returns_the_new_row_id() {
+        let db = TempDatabase::new("session-ids");
+
+        let first = db
+            .create_session("+100", Some("a"), None, None, None, None, Some(1))
+            .unwrap();
+        let second = db
+            .create_session("+200", Some("b"), None, None, None, None, Some(2))
+            .unwrap();
+
+        assert!(second > first);
+        let active = db.get_session().unwrap().unwrap();
+        assert_eq!(active.id, second);
+        assert_eq!(active.user_id, Some(2));
+    }
+}
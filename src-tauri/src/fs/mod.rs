@@ -1,10 +1,116 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 
+// Upper bound for a single read_file_range call
+const MAX_FILE_RANGE_BYTES: u64 = 16 * 1024 * 1024;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FsError {
     pub message: String,
+    /// `io::ErrorKind` name (e.g. `NotFound`, `PermissionDenied`, `AlreadyExists`).
+    #[serde(default)]
+    pub kind: String,
+}
+
+pub(crate) fn io_error_kind(error: &std::io::Error) -> String {
+    format!("{:?}", error.kind())
+}
+
+/// Unify separators and collapse repeated ones in a path from the frontend.
+///
+/// On Windows `/` becomes `\`, and a leading `\\` (UNC share or `\\?\`
+/// prefix) is kept as is. Elsewhere `\` is a valid name character and stays.
+pub(crate) fn normalize_local_path(path: &str) -> String {
+    let separator = std::path::MAIN_SEPARATOR;
+    let unified = if cfg!(windows) {
+        path.replace('/', "\\")
+    } else {
+        path.to_string()
+    };
+
+    let prefix_len = if cfg!(windows) && unified.starts_with("\\\\") {
+        2
+    } else {
+        0
+    };
+    let (prefix, rest) = unified.split_at(prefix_len);
+
+    let mut normalized = String::with_capacity(unified.len());
+    normalized.push_str(prefix);
+    let mut previous_was_separator = false;
+    for ch in rest.chars() {
+        let is_separator = ch == separator;
+        if !(is_separator && previous_was_separator) {
+            normalized.push(ch);
+        }
+        previous_was_separator = is_separator;
+    }
+
+    normalized
+}
+
+/// Bytes free for the current user on the filesystem holding `path`.
+///
+/// `path` may not exist yet; its nearest existing ancestor is measured. `None`
+/// when the platform offers no way to ask or the query fails.
+pub(crate) fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|ancestor| ancestor.exists())?;
+    filesystem_available_bytes(existing)
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // statvfs field widths differ between platforms
+fn filesystem_available_bytes(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: c_path is NUL-terminated and stats is only read after statvfs filled it
+    if unsafe { libc::statvfs(c_path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stats = unsafe { stats.assume_init() };
+    Some((stats.f_bavail as u64).saturating_mul(stats.f_frsize as u64))
+}
+
+#[cfg(windows)]
+fn filesystem_available_bytes(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory_name: *const u16,
+            free_bytes_available_to_caller: *mut u64,
+            total_number_of_bytes: *mut u64,
+            total_number_of_free_bytes: *mut u64,
+        ) -> i32;
+    }
+
+    let wide_path: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut available = 0u64;
+    // SAFETY: wide_path is NUL-terminated; the totals may be null
+    let succeeded = unsafe {
+        GetDiskFreeSpaceExW(
+            wide_path.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (succeeded != 0).then_some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn filesystem_available_bytes(_path: &Path) -> Option<u64> {
+    None
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,6 +121,10 @@ pub struct FileInfo {
     pub is_dir: bool,
     pub is_file: bool,
     pub modified: Option<String>,
+    /// The path itself is a symlink; the other fields describe its target.
+    pub is_symlink: bool,
+    /// Where the symlink points, as stored in the link.
+    pub link_target: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,26 +136,51 @@ pub struct DirEntry {
     pub size: Option<u64>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DirectoryListing {
+    pub entries: Vec<DirEntry>,
+    /// Entries that could not be read, one message each.
+    pub errors: Vec<String>,
+}
+
 #[tauri::command]
-pub async fn read_directory(path: String) -> Result<Vec<DirEntry>, FsError> {
+pub async fn read_directory(path: String) -> Result<DirectoryListing, FsError> {
+    let path = normalize_local_path(&path);
     let entries = fs::read_dir(&path).map_err(|e| FsError {
         message: format!("Failed to read directory {}: {}", path, e),
+        kind: io_error_kind(&e),
     })?;
 
-    let mut result = Vec::new();
+    // One unreadable child (broken symlink, no permission) must not hide the rest
+    let mut result = DirectoryListing {
+        entries: Vec::new(),
+        errors: Vec::new(),
+    };
     for entry in entries {
-        let entry = entry.map_err(|e| FsError {
-            message: format!("Failed to read entry: {}", e),
-        })?;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                result.errors.push(format!("Failed to read entry: {}", e));
+                continue;
+            }
+        };
 
-        let metadata = entry.metadata().map_err(|e| FsError {
-            message: format!("Failed to get metadata: {}", e),
-        })?;
+        let file_path = entry.path();
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                result.errors.push(format!(
+                    "Failed to get metadata for {}: {}",
+                    file_path.to_string_lossy(),
+                    e
+                ));
+                continue;
+            }
+        };
 
         let file_type = metadata.file_type();
-        let file_path = entry.path();
 
-        result.push(DirEntry {
+        result.entries.push(DirEntry {
             name: entry.file_name().to_string_lossy().to_string(),
             path: file_path.to_string_lossy().to_string(),
             is_dir: file_type.is_dir(),
@@ -63,17 +198,111 @@ pub async fn read_directory(path: String) -> Result<Vec<DirEntry>, FsError> {
 
 #[tauri::command]
 pub async fn read_file(path: String) -> Result<String, FsError> {
+    let path = normalize_local_path(&path);
     let content = fs::read_to_string(&path).map_err(|e| FsError {
         message: format!("Failed to read file {}: {}", path, e),
+        kind: io_error_kind(&e),
     })?;
 
     Ok(content)
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TextStats {
+    pub lines: u64,
+    pub words: u64,
+    pub chars: u64,
+    pub bytes: u64,
+}
+
+#[tauri::command]
+pub async fn get_text_stats(path: String) -> Result<TextStats, FsError> {
+    let path = normalize_local_path(&path);
+    let mut file = fs::File::open(&path).map_err(|e| FsError {
+        message: format!("Failed to open file {}: {}", path, e),
+        kind: io_error_kind(&e),
+    })?;
+
+    let mut stats = TextStats {
+        lines: 0,
+        words: 0,
+        chars: 0,
+        bytes: 0,
+    };
+    let mut last_byte = None;
+    let mut in_word = false;
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    // Counted byte by byte so large logs never sit in memory and invalid UTF-8
+    // only skews the char count: every byte that does not continue a sequence is one char
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| FsError {
+            message: format!("Failed to read file {}: {}", path, e),
+            kind: io_error_kind(&e),
+        })?;
+        if read == 0 {
+            break;
+        }
+
+        for &byte in &buffer[..read] {
+            if byte == b'\n' {
+                stats.lines += 1;
+            }
+            if byte & 0xC0 != 0x80 {
+                stats.chars += 1;
+            }
+
+            if byte.is_ascii_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                in_word = true;
+                stats.words += 1;
+            }
+        }
+
+        stats.bytes += read as u64;
+        last_byte = Some(buffer[read - 1]);
+    }
+
+    // A last line without a trailing newline still counts
+    if last_byte.is_some_and(|byte| byte != b'\n') {
+        stats.lines += 1;
+    }
+
+    Ok(stats)
+}
+
+#[tauri::command]
+pub async fn read_file_range(path: String, offset: u64, length: u64) -> Result<Vec<u8>, FsError> {
+    let path = normalize_local_path(&path);
+    let mut file = fs::File::open(&path).map_err(|e| FsError {
+        message: format!("Failed to open file {}: {}", path, e),
+        kind: io_error_kind(&e),
+    })?;
+
+    file.seek(SeekFrom::Start(offset)).map_err(|e| FsError {
+        message: format!("Failed to seek to {} in {}: {}", offset, path, e),
+        kind: io_error_kind(&e),
+    })?;
+
+    // Near EOF this yields fewer bytes instead of an error
+    let mut buffer = Vec::new();
+    file.take(length.min(MAX_FILE_RANGE_BYTES))
+        .read_to_end(&mut buffer)
+        .map_err(|e| FsError {
+            message: format!("Failed to read file {}: {}", path, e),
+            kind: io_error_kind(&e),
+        })?;
+
+    Ok(buffer)
+}
+
 #[tauri::command]
 pub async fn write_file(path: String, content: String) -> Result<(), FsError> {
+    let path = normalize_local_path(&path);
     fs::write(&path, content).map_err(|e| FsError {
         message: format!("Failed to write file {}: {}", path, e),
+        kind: io_error_kind(&e),
     })?;
 
     Ok(())
@@ -81,8 +310,10 @@ pub async fn write_file(path: String, content: String) -> Result<(), FsError> {
 
 #[tauri::command]
 pub async fn create_directory(path: String) -> Result<(), FsError> {
+    let path = normalize_local_path(&path);
     fs::create_dir_all(&path).map_err(|e| FsError {
         message: format!("Failed to create directory {}: {}", path, e),
+        kind: io_error_kind(&e),
     })?;
 
     Ok(())
@@ -90,13 +321,16 @@ pub async fn create_directory(path: String) -> Result<(), FsError> {
 
 #[tauri::command]
 pub async fn delete_file(path: String) -> Result<(), FsError> {
+    let path = normalize_local_path(&path);
     if Path::new(&path).is_dir() {
         fs::remove_dir_all(&path).map_err(|e| FsError {
             message: format!("Failed to delete directory {}: {}", path, e),
+            kind: io_error_kind(&e),
         })?;
     } else {
         fs::remove_file(&path).map_err(|e| FsError {
             message: format!("Failed to delete file {}: {}", path, e),
+            kind: io_error_kind(&e),
         })?;
     }
 
@@ -104,9 +338,36 @@ pub async fn delete_file(path: String) -> Result<(), FsError> {
 }
 
 #[tauri::command]
-pub async fn rename_file(old_path: String, new_path: String) -> Result<(), FsError> {
+pub async fn rename_file(
+    old_path: String,
+    new_path: String,
+    overwrite: Option<bool>,
+) -> Result<(), FsError> {
+    let old_path = normalize_local_path(&old_path);
+    let new_path = normalize_local_path(&new_path);
+
+    // fs::rename replaces an existing target on some platforms; only do that on request.
+    // A target resolving to the source itself is a case-only rename, which is allowed.
+    if !overwrite.unwrap_or(false) && Path::new(&new_path).exists() {
+        let same_entry = match (fs::canonicalize(&old_path), fs::canonicalize(&new_path)) {
+            (Ok(old), Ok(new)) => old == new,
+            _ => false,
+        };
+
+        if !same_entry {
+            return Err(FsError {
+                message: format!(
+                    "Cannot rename {} to {}: target already exists",
+                    old_path, new_path
+                ),
+                kind: "AlreadyExists".to_string(),
+            });
+        }
+    }
+
     fs::rename(&old_path, &new_path).map_err(|e| FsError {
         message: format!("Failed to rename {} to {}: {}", old_path, new_path, e),
+        kind: io_error_kind(&e),
     })?;
 
     Ok(())
@@ -114,8 +375,11 @@ pub async fn rename_file(old_path: String, new_path: String) -> Result<(), FsErr
 
 #[tauri::command]
 pub async fn copy_file(source: String, destination: String) -> Result<(), FsError> {
+    let source = normalize_local_path(&source);
+    let destination = normalize_local_path(&destination);
     fs::copy(&source, &destination).map_err(|e| FsError {
         message: format!("Failed to copy {} to {}: {}", source, destination, e),
+        kind: io_error_kind(&e),
     })?;
 
     Ok(())
@@ -123,8 +387,11 @@ pub async fn copy_file(source: String, destination: String) -> Result<(), FsErro
 
 #[tauri::command]
 pub async fn move_file(source: String, destination: String) -> Result<(), FsError> {
+    let source = normalize_local_path(&source);
+    let destination = normalize_local_path(&destination);
     fs::rename(&source, &destination).map_err(|e| FsError {
         message: format!("Failed to move {} to {}: {}", source, destination, e),
+        kind: io_error_kind(&e),
     })?;
 
     Ok(())
@@ -132,9 +399,32 @@ pub async fn move_file(source: String, destination: String) -> Result<(), FsErro
 
 #[tauri::command]
 pub async fn get_file_info(path: String) -> Result<FileInfo, FsError> {
-    let metadata = fs::metadata(&path).map_err(|e| FsError {
+    let path = normalize_local_path(&path);
+    let link_metadata = fs::symlink_metadata(&path).map_err(|e| FsError {
         message: format!("Failed to get metadata for {}: {}", path, e),
+        kind: io_error_kind(&e),
     })?;
+    let is_symlink = link_metadata.file_type().is_symlink();
+
+    // A dangling link has no target metadata, so describe the link itself
+    let metadata = match fs::metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(_) if is_symlink => link_metadata,
+        Err(e) => {
+            return Err(FsError {
+                message: format!("Failed to get metadata for {}: {}", path, e),
+                kind: io_error_kind(&e),
+            })
+        }
+    };
+
+    let link_target = if is_symlink {
+        fs::read_link(&path)
+            .ok()
+            .map(|target| target.to_string_lossy().to_string())
+    } else {
+        None
+    };
 
     let file_name = Path::new(&path)
         .file_name()
@@ -149,26 +439,153 @@ pub async fn get_file_info(path: String) -> Result<FileInfo, FsError> {
         is_dir: metadata.is_dir(),
         is_file: metadata.is_file(),
         modified: metadata.modified().ok().map(|t| format!("{:?}", t)),
+        is_symlink,
+        link_target,
     })
 }
 
+#[tauri::command]
+pub async fn reveal_in_file_manager(app: tauri::AppHandle, path: String) -> Result<(), FsError> {
+    use tauri_plugin_shell::ShellExt;
+
+    let path = normalize_local_path(&path);
+    fs::symlink_metadata(&path).map_err(|e| FsError {
+        message: format!("Failed to reveal {}: {}", path, e),
+        kind: io_error_kind(&e),
+    })?;
+
+    let (program, args): (&str, Vec<String>) = if cfg!(target_os = "windows") {
+        ("explorer", vec![format!("/select,{}", path)])
+    } else if cfg!(target_os = "macos") {
+        ("open", vec!["-R".to_string(), path.clone()])
+    } else if cfg!(target_os = "linux") {
+        // xdg-open cannot select an item, so open the folder that contains it
+        let parent = Path::new(&path)
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .map(|parent| parent.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone());
+        ("xdg-open", vec![parent])
+    } else {
+        return Err(FsError {
+            message: "Revealing files is not supported on this platform".to_string(),
+            kind: "Unsupported".to_string(),
+        });
+    };
+
+    // Explorer exits with a non-zero code even on success, so only spawn errors count
+    app.shell()
+        .command(program)
+        .args(args)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| FsError {
+            message: format!("Failed to open the file manager for {}: {}", path, e),
+            kind: "Other".to_string(),
+        })
+}
+
 #[tauri::command]
 pub async fn search_files(directory: String, pattern: String) -> Result<Vec<String>, FsError> {
+    let directory = normalize_local_path(&directory);
+    Ok(collect_matching_paths(&directory, &pattern, None, false))
+}
+
+// Walks `directory` for names containing `pattern`, stopping after `limit` matches
+pub(crate) fn collect_matching_paths(
+    directory: &str,
+    pattern: &str,
+    limit: Option<usize>,
+    ignore_case: bool,
+) -> Vec<String> {
     let mut results = Vec::new();
+    let pattern = if ignore_case {
+        pattern.to_lowercase()
+    } else {
+        pattern.to_string()
+    };
 
-    let entries = walkdir::WalkDir::new(&directory)
+    let entries = walkdir::WalkDir::new(directory)
         .into_iter()
         .filter_map(|entry| entry.ok());
 
     for entry in entries {
+        if limit.is_some_and(|limit| results.len() >= limit) {
+            break;
+        }
+
         let file_path = entry.path();
         if let Some(file_name) = file_path.file_name() {
             let file_name_str = file_name.to_string_lossy();
-            if file_name_str.contains(&pattern) {
+            let matches = if ignore_case {
+                file_name_str.to_lowercase().contains(&pattern)
+            } else {
+                file_name_str.contains(&pattern)
+            };
+            if matches {
                 results.push(file_path.to_string_lossy().to_string());
             }
         }
     }
 
-    Ok(results)
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_names_case_insensitively_when_asked() {
+        let root = std::env::temp_dir().join(format!("skybox-search-{}", std::process::id()));
+        fs::create_dir_all(root.join("Trips")).unwrap();
+        fs::write(root.join("Trips").join("Beach-PHOTO.jpg"), b"").unwrap();
+        fs::write(root.join("notes.txt"), b"").unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        let matches = collect_matching_paths(&root_str, "photo", None, true);
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].ends_with("Beach-PHOTO.jpg"));
+        assert!(collect_matching_paths(&root_str, "photo", None, false).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn unifies_mixed_separators_on_windows() {
+        assert_eq!(normalize_local_path("C:\\a/b"), "C:\\a\\b");
+        assert_eq!(normalize_local_path("C:/a//b\\\\c/"), "C:\\a\\b\\c\\");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn keeps_unc_and_verbatim_prefixes_on_windows() {
+        assert_eq!(
+            normalize_local_path("\\\\server\\share//dir"),
+            "\\\\server\\share\\dir"
+        );
+        assert_eq!(
+            normalize_local_path("//server/share/dir"),
+            "\\\\server\\share\\dir"
+        );
+        assert_eq!(normalize_local_path("\\\\?\\C:\\a/b"), "\\\\?\\C:\\a\\b");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn keeps_backslashes_as_name_characters_elsewhere() {
+        assert_eq!(normalize_local_path("C:\\a/b"), "C:\\a/b");
+        assert_eq!(
+            normalize_local_path("\\\\server\\share"),
+            "\\\\server\\share"
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn collapses_repeated_slashes_elsewhere() {
+        assert_eq!(normalize_local_path("//home//user///a/"), "/home/user/a/");
+        assert_eq!(normalize_local_path("relative/dir"), "relative/dir");
+    }
 }
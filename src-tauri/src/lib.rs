@@ -6,6 +6,7 @@ use once_cell::sync::Lazy;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tauri::Manager;
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
 use tokio::sync::Mutex;
 
 mod db;
@@ -36,12 +37,23 @@ pub fn run() {
             tauri_plugin_log::Builder::new()
                 .level(LevelFilter::Info) // global log level
                 .level_for("grammers", LevelFilter::Warn) // silence grammers spam
+                .targets([
+                    Target::new(TargetKind::Stdout),
+                    Target::new(TargetKind::LogDir {
+                        file_name: Some(utils::logger::LOG_FILE_NAME.to_string()),
+                    }),
+                ])
+                .max_file_size(utils::logger::LOG_MAX_FILE_SIZE)
+                .rotation_strategy(RotationStrategy::KeepSome(
+                    utils::logger::LOG_ROTATED_FILES_KEPT,
+                ))
                 .build(),
         )
         .invoke_handler(tauri::generate_handler![
             // FS Commands
             fs::read_directory,
             fs::read_file,
+            fs::read_file_range,
             fs::write_file,
             fs::create_directory,
             fs::delete_file,
@@ -49,7 +61,9 @@ pub fn run() {
             fs::copy_file,
             fs::move_file,
             fs::get_file_info,
+            fs::reveal_in_file_manager,
             fs::search_files,
+            fs::get_text_stats,
             // DB Commands
             db::db_get_setting,
             db::db_set_setting,
@@ -57,12 +71,20 @@ pub fn run() {
             db::db_add_recent_path,
             db::db_get_favorites,
             db::db_add_favorite,
+            db::db_add_favorites,
             db::db_remove_favorite,
+            db::db_is_favorite,
+            db::db_are_favorites,
             db::db_get_session,
             db::db_create_session,
             db::db_update_session_profile_photo,
             db::db_update_session_user_info,
             db::db_clear_session,
+            db::db_list_sessions,
+            db::db_switch_active_session,
+            db::db_export_database,
+            db::db_import_database,
+            db::db_maintenance,
             // Telegram Commands
             telegram::tg_request_auth_code,
             telegram::tg_sign_in_with_code,
@@ -71,44 +93,83 @@ pub fn run() {
             telegram::tg_poll_qr_login,
             telegram::tg_restore_session,
             telegram::tg_ping,
+            telegram::tg_get_diagnostics,
+            telegram::tg_connection_status,
+            telegram::tg_reconnect,
             telegram::tg_logout,
+            telegram::tg_list_active_sessions,
+            telegram::tg_terminate_session,
+            telegram::tg_is_authorized,
+            telegram::tg_get_me,
+            telegram::tg_get_account_limits,
             telegram::tg_get_my_profile_photo,
+            telegram::classify_file,
             telegram::tg_index_saved_messages,
             telegram::tg_get_indexed_saved_messages,
             telegram::tg_list_saved_items,
+            telegram::tg_get_saved_item,
             telegram::tg_list_saved_items_page,
+            telegram::tg_list_recent_saved_items,
             telegram::tg_backfill_saved_messages_batch,
+            telegram::tg_get_backfill_status,
+            telegram::tg_cancel_indexing,
             telegram::tg_rebuild_saved_items_index,
+            telegram::tg_fix_missing_extensions,
             telegram::tg_create_saved_folder,
             telegram::tg_move_saved_item,
             telegram::tg_move_saved_item_to_recycle_bin,
             telegram::tg_restore_saved_item,
+            telegram::tg_restore_all_recycle_items,
             telegram::tg_delete_saved_item_permanently,
+            telegram::tg_forget_saved_item,
+            telegram::tg_pin_saved_item,
+            telegram::tg_unpin_saved_item,
             telegram::tg_rename_saved_item,
+            telegram::search_all,
             telegram::tg_send_saved_note_message,
             telegram::tg_edit_saved_note_message,
             telegram::tg_get_message_thumbnail,
+            telegram::tg_get_message_thumbnail_data_url,
+            telegram::get_thumbnail_cache_size,
+            telegram::clear_thumbnail_cache,
             telegram::tg_prefetch_message_thumbnails,
+            telegram::tg_prefetch_folder_thumbnails,
+            telegram::tg_get_folder_preview_thumbnail,
+            telegram::tg_get_folder_counts,
             telegram::tg_download_saved_file,
+            telegram::tg_download_saved_folder,
             telegram::tg_cancel_saved_file_download,
             telegram::tg_cancel_saved_file_upload,
             telegram::tg_prepare_saved_media_preview,
+            telegram::tg_prepare_text_preview,
             telegram::tg_upload_file_to_saved_messages,
+            telegram::tg_upload_local_file,
+            telegram::tg_list_pending_uploads,
+            telegram::tg_cancel_pending_upload,
+            telegram::tg_retry_pending_upload,
             telegram::tg_start_real_time_sync,
             // Logger Commands
             utils::logger::log_debug,
             utils::logger::log_info,
             utils::logger::log_warn,
             utils::logger::log_error,
+            utils::logger::get_recent_logs,
         ])
         .setup(|app| {
             // Initialize database
             let db = Database::new().expect("Failed to create database");
+            telegram::load_request_tuning_settings(&db);
+            telegram::migrate_legacy_thumbnail_cache(&db);
+            telegram::register_event_app_handle(app.handle().clone());
+            telegram::watch_live_sync_setting(app.handle(), &db);
             app.manage(db);
 
             // Initialize global Telegram client state
             app.manage(Arc::clone(&TG_CLIENT_STATE));
 
+            // Send uploads queued while offline once the connection is back
+            tauri::async_runtime::spawn(telegram::run_pending_upload_queue(app.handle().clone()));
+
             Ok(())
         })
         .on_window_event(|_window, event| {
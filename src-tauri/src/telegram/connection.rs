@@ -0,0 +1,120 @@
+use super::utils::build_client;
+#[allow(deprecated)]
+use super::TlSession;
+use super::{
+    error_code, request_error_code, run_telegram_request, ConnectionStatus, TelegramError,
+    AUTH_STATE,
+};
+use grammers_mtsender::SenderPoolHandle;
+use grammers_session::Session as _;
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+
+// Health of the active client's connection. `AuthState` owns it; request retries
+// reach it through ACTIVE_CONNECTION because their callers often hold AUTH_STATE.
+pub(crate) struct ConnectionHealth {
+    pool_handle: SenderPoolHandle,
+    #[allow(deprecated)]
+    session: Arc<TlSession>,
+    online: AtomicBool,
+}
+
+static ACTIVE_CONNECTION: Lazy<StdMutex<Weak<ConnectionHealth>>> =
+    Lazy::new(|| StdMutex::new(Weak::new()));
+
+impl ConnectionHealth {
+    /// Track the connection of a freshly built client; replaces any earlier one.
+    #[allow(deprecated)]
+    pub(crate) fn register(
+        pool_handle: &SenderPoolHandle,
+        session: &Arc<TlSession>,
+    ) -> Arc<ConnectionHealth> {
+        let health = Arc::new(ConnectionHealth {
+            pool_handle: pool_handle.clone(),
+            session: Arc::clone(session),
+            online: AtomicBool::new(true),
+        });
+
+        if let Ok(mut active) = ACTIVE_CONNECTION.lock() {
+            *active = Arc::downgrade(&health);
+        }
+
+        health
+    }
+
+    pub(crate) fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_online(&self, online: bool) {
+        if self.online.swap(online, Ordering::Relaxed) != online {
+            log::info!(
+                "Telegram connection is now {}",
+                if online { "online" } else { "offline" }
+            );
+        }
+    }
+
+    // Drop the home DC connection so the pool dials a new one on the next request.
+    // Returns false if the pool runner is gone and the client must be rebuilt.
+    pub(crate) fn drop_home_connection(&self) -> bool {
+        self.pool_handle
+            .disconnect_from_dc(self.session.home_dc_id())
+    }
+}
+
+pub(crate) fn active_connection() -> Option<Arc<ConnectionHealth>> {
+    ACTIVE_CONNECTION
+        .lock()
+        .ok()
+        .and_then(|active| active.upgrade())
+}
+
+pub async fn tg_connection_status_impl() -> Result<ConnectionStatus, TelegramError> {
+    let guard = AUTH_STATE.lock().await;
+    let Some(state) = guard.as_ref() else {
+        return Ok(ConnectionStatus {
+            authorized: false,
+            online: false,
+        });
+    };
+
+    Ok(ConnectionStatus {
+        authorized: true,
+        online: state.connection.is_online() && !state.pool_task.is_finished(),
+    })
+}
+
+pub async fn tg_reconnect_impl() -> Result<bool, TelegramError> {
+    let client = {
+        let mut guard = AUTH_STATE.lock().await;
+        let state = guard.as_mut().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+
+        if state.pool_task.is_finished() || !state.connection.drop_home_connection() {
+            log::warn!("tg_reconnect_impl: Sender pool stopped, rebuilding client");
+            state.pool_task.abort();
+            let built = build_client(Arc::clone(&state.session));
+            state.connection = ConnectionHealth::register(&built.pool_handle, &state.session);
+            state.client = built.client;
+            state.pool_handle = built.pool_handle;
+            state.pool_task = built.pool_task;
+            state.updates = built.updates;
+        }
+
+        state.client.clone()
+    };
+
+    run_telegram_request("tg_reconnect_impl.get_me", || async {
+        client.get_me().await
+    })
+    .await
+    .map(|_| true)
+    .map_err(|e| TelegramError {
+        message: format!("Reconnect failed: {e}"),
+        code: request_error_code(&e),
+    })
+}
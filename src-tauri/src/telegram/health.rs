@@ -1,4 +1,9 @@
-use super::{run_telegram_request, TelegramError, AUTH_STATE};
+use super::messages::is_thumbnail_flood_wait_active;
+use super::{
+    error_code, is_request_flood_wait_active, last_telegram_request_at, request_error_code,
+    run_telegram_request, TelegramDiagnostics, TelegramError, AUTH_STATE,
+};
+use grammers_session::Session as _;
 use tokio::time::{timeout, Duration};
 
 pub async fn tg_ping_impl() -> Result<bool, TelegramError> {
@@ -6,6 +11,7 @@ pub async fn tg_ping_impl() -> Result<bool, TelegramError> {
         let guard = AUTH_STATE.lock().await;
         let state = guard.as_ref().ok_or_else(|| TelegramError {
             message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
         state.client.clone()
     };
@@ -16,9 +22,38 @@ pub async fn tg_ping_impl() -> Result<bool, TelegramError> {
     .await
     .map_err(|_| TelegramError {
         message: "Connection check timed out".to_string(),
+        code: error_code::TIMEOUT.to_string(),
     })?;
 
     result.map(|_| true).map_err(|e| TelegramError {
         message: format!("Connection check failed: {e}"),
+        code: request_error_code(&e),
+    })
+}
+
+// Read-only snapshot of local state; makes no Telegram request
+pub async fn tg_get_diagnostics_impl() -> Result<TelegramDiagnostics, TelegramError> {
+    let (dc_id, authorized, is_migrating) = {
+        let guard = AUTH_STATE.lock().await;
+        match guard.as_ref() {
+            Some(state) => (
+                state
+                    .current_dc_id
+                    .or_else(|| Some(state.session.home_dc_id())),
+                true,
+                state.is_migrating,
+            ),
+            None => (None, false, false),
+        }
+    };
+
+    Ok(TelegramDiagnostics {
+        dc_id,
+        authorized,
+        is_migrating,
+        last_request_at: last_telegram_request_at()
+            .await
+            .map(|time| time.to_rfc3339()),
+        flood_wait_active: is_request_flood_wait_active() || is_thumbnail_flood_wait_active(),
     })
 }
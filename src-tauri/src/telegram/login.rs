@@ -1,6 +1,8 @@
+use super::connection::ConnectionHealth;
 use super::session::ensure_basic_connectivity;
 use super::utils::{build_client, encode_session};
 use super::Arc;
+use super::{error_code, replace_auth_state, request_error_code};
 use super::{get_api_hash, get_api_id, run_telegram_request, Database, AUTH_FLOW_ID, AUTH_STATE};
 #[allow(deprecated)]
 use super::{Client, SignInError, TlSession};
@@ -17,6 +19,28 @@ use std::sync::atomic::Ordering;
 use tauri::Emitter;
 use tokio::time;
 
+const QR_POLL_WAIT_MS_SETTING_KEY: &str = "tg_qr_poll_wait_ms";
+const QR_REFRESH_WINDOW_SECS_SETTING_KEY: &str = "tg_qr_refresh_window_secs";
+const DEFAULT_QR_POLL_WAIT_MS: u64 = 900;
+const MAX_QR_POLL_WAIT_MS: u64 = 10_000;
+const DEFAULT_QR_REFRESH_WINDOW_SECS: i64 = 5;
+
+// Non-negative integer setting, or `default` when unset or invalid
+fn read_qr_setting(db: &Database, key: &str, default: i64) -> i64 {
+    match db.get_setting_i64(key) {
+        Ok(Some(value)) if value >= 0 => value,
+        Ok(Some(value)) => {
+            log::warn!("Ignoring negative value {} for setting {}", value, key);
+            default
+        }
+        Ok(None) => default,
+        Err(e) => {
+            log::warn!("Ignoring setting {}: {}", key, e.message);
+            default
+        }
+    }
+}
+
 /// Compute proper expiration timestamp from the token's expires field
 ///
 /// Logic:
@@ -60,17 +84,47 @@ pub async fn resolve_export_login_token(
     .await
     .map_err(|e| TelegramError {
         message: format!("Failed to export login token: {}", e),
+        code: request_error_code(&e),
     })
 }
 
+/// Normalize a user-entered phone number into E.164 form (`+` followed by digits).
+///
+/// Spaces, dashes, dots and parentheses are stripped so pasted numbers like
+/// `+1 (415) 555-0123` are accepted.
+fn validate_phone_number(raw: &str) -> Result<String, TelegramError> {
+    let invalid = || TelegramError {
+        message: "Enter your number in international format, e.g. +14155550123".to_string(),
+        code: error_code::INVALID_INPUT.to_string(),
+    };
+
+    let compact: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '.' | '(' | ')'))
+        .collect();
+
+    let digits = compact.strip_prefix('+').ok_or_else(invalid)?;
+
+    // E.164 allows at most 15 digits; country codes never start with 0
+    if !(7..=15).contains(&digits.len())
+        || !digits.chars().all(|c| c.is_ascii_digit())
+        || digits.starts_with('0')
+    {
+        return Err(invalid());
+    }
+
+    Ok(format!("+{}", digits))
+}
+
 pub async fn tg_request_auth_code_impl(
     auth_data: TelegramAuthData,
 ) -> Result<TelegramAuthResult, TelegramError> {
+    let phone_number = validate_phone_number(&auth_data.phone_number)?;
     let flow_id = AUTH_FLOW_ID.fetch_add(1, Ordering::Relaxed) + 1;
     log::info!(
         "tg_request_auth_code_impl: flow_id={} phone={}",
         flow_id,
-        auth_data.phone_number
+        phone_number
     );
 
     // Create a fresh session and client for this operation
@@ -80,85 +134,113 @@ pub async fn tg_request_auth_code_impl(
 
     log::info!(
         "tg_request_auth_code_impl: Calling request_login_code for phone: {}",
-        auth_data.phone_number
+        phone_number
     );
 
     // request_login_code(phone, api_hash) -> LoginToken
     let token = run_telegram_request("tg_request_auth_code_impl.request_login_code", || async {
         built
             .client
-            .request_login_code(&auth_data.phone_number, get_api_hash())
+            .request_login_code(&phone_number, get_api_hash())
             .await
     })
     .await
     .map_err(|e| {
         log::error!(
             "tg_request_auth_code_impl: Failed to request auth code for phone '{}': {}",
-            auth_data.phone_number,
+            phone_number,
             e
         );
 
         let s = e.to_string().to_lowercase();
 
-        let error_message: String =
+        let (code, error_message) =
             if (s.contains("dropped") || s.contains("cancelled")) && !s.contains("timeout") {
-                format!(
-                "Connection interrupted: {}. Please check your internet connection and try again.",
-                e
-            )
+                (
+                    error_code::OFFLINE.to_string(),
+                    format!(
+                        "Connection interrupted: {}. Please check your internet connection and try again.",
+                        e
+                    ),
+                )
             } else if s.contains("timeout") {
-                format!(
-                    "Request timed out: {}. Please check your network connection and try again.",
-                    e
+                (
+                    error_code::TIMEOUT.to_string(),
+                    format!(
+                        "Request timed out: {}. Please check your network connection and try again.",
+                        e
+                    ),
                 )
             } else if s.contains("invalid") {
-                format!(
-                    "Invalid phone number format: {}. Please check the number and try again.",
-                    e
+                (
+                    error_code::INVALID_INPUT.to_string(),
+                    format!(
+                        "Invalid phone number format: {}. Please check the number and try again.",
+                        e
+                    ),
                 )
             } else if s.contains("flood") {
-                format!(
-                    "Too many requests: {}. Please wait a few minutes before trying again.",
-                    e
+                (
+                    error_code::FLOOD_WAIT.to_string(),
+                    format!(
+                        "Too many requests: {}. Please wait a few minutes before trying again.",
+                        e
+                    ),
                 )
             } else if (s.contains("api_id") || s.contains("api")) && !s.contains("invalid") {
-                format!(
-                    "Authentication service error: {}. Please try again later.",
-                    e
+                (
+                    error_code::REQUEST_FAILED.to_string(),
+                    format!(
+                        "Authentication service error: {}. Please try again later.",
+                        e
+                    ),
                 )
             } else {
-                format!("Failed to request auth code: {}", e)
+                (
+                    request_error_code(&e),
+                    format!("Failed to request auth code: {}", e),
+                )
             };
 
         TelegramError {
             message: error_message,
+            code,
         }
     })?;
 
     log::info!(
         "tg_request_auth_code_impl: Successfully requested auth code for phone: {}",
-        auth_data.phone_number
+        phone_number
     );
 
-    *AUTH_STATE.lock().await = Some(super::AuthState {
-        client: built.client,
-        session,
-        pool_handle: built.pool_handle,
-        pool_task: built.pool_task,
-        updates: built.updates,
-        login_token: Some(token),
-        password_token: None,
-        phone_number: Some(auth_data.phone_number.clone()),
-        flow_id,
-        qr_state: None,
-        is_migrating: false,
-        current_dc_id: None, // Will be determined by the client
-    });
+    let connection = ConnectionHealth::register(&built.pool_handle, &session);
+    replace_auth_state(
+        super::AuthState {
+            client: built.client,
+            session,
+            pool_handle: built.pool_handle,
+            pool_task: built.pool_task,
+            updates: built.updates,
+            login_token: Some(token),
+            password_token: None,
+            phone_number: Some(phone_number.clone()),
+            flow_id,
+            qr_state: None,
+            is_migrating: false,
+            current_dc_id: None, // Will be determined by the client
+            owner_context: None,
+            user_info: None,
+            account_limits: None,
+            connection,
+        },
+        "tg_request_auth_code_impl",
+    )
+    .await;
 
     log::info!(
         "tg_request_auth_code_impl: stored state flow_id={} for phone={}",
         flow_id,
-        auth_data.phone_number
+        phone_number
     );
 
     Ok(TelegramAuthResult {
@@ -181,6 +263,7 @@ pub async fn tg_sign_in_with_code_impl(
 
         let state = guard.as_mut().ok_or_else(|| TelegramError {
             message: "No active auth session. Call tg_request_auth_code first.".into(),
+            code: error_code::AUTH_FLOW_EXPIRED.to_string(),
         })?;
 
         log::info!(
@@ -193,6 +276,7 @@ pub async fn tg_sign_in_with_code_impl(
 
         let token = state.login_token.take().ok_or_else(|| TelegramError {
             message: "Missing login token. Restart auth flow.".into(),
+            code: error_code::AUTH_FLOW_EXPIRED.to_string(),
         })?;
 
         (
@@ -212,6 +296,7 @@ pub async fn tg_sign_in_with_code_impl(
         }
         return Err(TelegramError {
             message: "Empty code".into(),
+            code: error_code::INVALID_CODE.to_string(),
         });
     }
 
@@ -245,6 +330,7 @@ pub async fn tg_sign_in_with_code_impl(
             .await
             .map_err(|e| TelegramError {
                 message: format!("get_me failed after sign_in: {e}"),
+                code: request_error_code(&e),
             })?;
 
             log::info!(
@@ -300,6 +386,7 @@ pub async fn tg_sign_in_with_code_impl(
             }
             Err(TelegramError {
                 message: "Invalid code (check what the frontend is sending)".into(),
+                code: error_code::INVALID_CODE.to_string(),
             })
         }
 
@@ -314,6 +401,7 @@ pub async fn tg_sign_in_with_code_impl(
             }
             Err(TelegramError {
                 message: "This number requires sign-up (not logged in yet)".into(),
+                code: error_code::SIGN_UP_REQUIRED.to_string(),
             })
         }
 
@@ -327,6 +415,7 @@ pub async fn tg_sign_in_with_code_impl(
             }
             Err(TelegramError {
                 message: "Invalid password".into(),
+                code: error_code::INVALID_PASSWORD.to_string(),
             })
         }
 
@@ -338,13 +427,44 @@ pub async fn tg_sign_in_with_code_impl(
             }
             Err(TelegramError {
                 message: format!("Sign-in failed: {e}"),
+                code: request_error_code(&e),
             })
         }
     }
 }
 
+// Side length of the rendered QR PNG, large enough to scan from a phone camera
+const QR_PNG_MIN_DIMENSION: u32 = 320;
+
+/// Render the login URL as a PNG and return it as a `data:image/png;base64,...` URL.
+fn render_qr_png_data_url(qr_url: &str) -> Result<String, TelegramError> {
+    let code = qrcode::QrCode::new(qr_url.as_bytes()).map_err(|e| TelegramError {
+        message: format!("Failed to encode QR code: {}", e),
+        code: error_code::INTERNAL.to_string(),
+    })?;
+
+    let image = code
+        .render::<image::Luma<u8>>()
+        .min_dimensions(QR_PNG_MIN_DIMENSION, QR_PNG_MIN_DIMENSION)
+        .build();
+
+    let mut png_bytes = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut png_bytes, image::ImageFormat::Png)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to render QR code image: {}", e),
+            code: error_code::INTERNAL.to_string(),
+        })?;
+
+    Ok(format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(png_bytes.into_inner())
+    ))
+}
+
 pub async fn tg_generate_qr_code_impl(
     _app: tauri::AppHandle,
+    include_png: bool,
 ) -> Result<QrLoginData, TelegramError> {
     log::info!("tg_generate_qr_code_impl: Generating QR login code");
 
@@ -359,6 +479,7 @@ pub async fn tg_generate_qr_code_impl(
         return Err(TelegramError {
             message: "Network appears offline or unreachable. Please check your connection and try again."
                 .to_string(),
+            code: error_code::OFFLINE.to_string(),
         });
     }
 
@@ -373,10 +494,17 @@ pub async fn tg_generate_qr_code_impl(
                         "tg_generate_qr_code_impl: Active QR flow already exists (flow_id={}), returning existing QR",
                         state.flow_id
                     );
+                    let qr_png_data_url = if include_png {
+                        Some(render_qr_png_data_url(&qr_state.qr_url)?)
+                    } else {
+                        None
+                    };
                     return Ok(QrLoginData {
                         qr_url: qr_state.qr_url.clone(),
+                        qr_png_data_url,
                         expires_at_unix: qr_state.expires_unix,
                         flow_id: state.flow_id,
+                        login_result: None,
                     });
                 } else {
                     log::info!(
@@ -408,6 +536,7 @@ pub async fn tg_generate_qr_code_impl(
         if hops > 5 {
             return Err(TelegramError {
                 message: "Too many DC migrations while exporting login token".to_string(),
+                code: error_code::REQUEST_FAILED.to_string(),
             });
         }
 
@@ -432,19 +561,20 @@ pub async fn tg_generate_qr_code_impl(
                     "Failed to import login token during export migration to DC {}: {}",
                     m.dc_id, e
                 ),
+                code: request_error_code(&e),
             })?;
     }
 
     // Handle the response
     let (token_bytes, raw_expires_field, _token_obj) = match token_result {
         tl::enums::auth::LoginToken::Token(t) => (t.token.clone(), t.expires, t),
-        tl::enums::auth::LoginToken::Success(_s) => {
+        tl::enums::auth::LoginToken::Success(s) => {
             // Already authorized - this is rare but possible
             log::info!(
                 "tg_generate_qr_code_impl: Already authorized (flow_id={})",
                 flow_id
             );
-            return handle_already_authorized(built.client, session, flow_id).await;
+            return handle_already_authorized(built, session, s, flow_id).await;
         }
         tl::enums::auth::LoginToken::MigrateTo(_m) => unreachable!("MigrateTo handled in loop"),
     };
@@ -491,44 +621,77 @@ pub async fn tg_generate_qr_code_impl(
     // Minimal delay to allow token to settle (reduced from 1.5s to 100ms)
     time::sleep(time::Duration::from_millis(100)).await;
 
-    *AUTH_STATE.lock().await = Some(super::AuthState {
-        client: built.client,
-        session,
-        pool_handle: built.pool_handle,
-        pool_task: built.pool_task,
-        updates: built.updates,
-        login_token: None,
-        password_token: None,
-        phone_number: None,
-        flow_id,
-        qr_state: Some(qr_state),
-        is_migrating: false,
-        current_dc_id: None,
-    });
+    let connection = ConnectionHealth::register(&built.pool_handle, &session);
+    replace_auth_state(
+        super::AuthState {
+            client: built.client,
+            session,
+            pool_handle: built.pool_handle,
+            pool_task: built.pool_task,
+            updates: built.updates,
+            login_token: None,
+            password_token: None,
+            phone_number: None,
+            flow_id,
+            qr_state: Some(qr_state),
+            is_migrating: false,
+            current_dc_id: None,
+            owner_context: None,
+            user_info: None,
+            account_limits: None,
+            connection,
+        },
+        "tg_generate_qr_code_impl",
+    )
+    .await;
+
+    let qr_png_data_url = if include_png {
+        Some(render_qr_png_data_url(&qr_url)?)
+    } else {
+        None
+    };
 
     Ok(QrLoginData {
         qr_url,
+        qr_png_data_url,
         expires_at_unix,
         flow_id,
+        login_result: None,
     })
 }
 
-pub async fn tg_poll_qr_login_impl(app: tauri::AppHandle) -> Result<QrPollResult, TelegramError> {
+pub async fn tg_poll_qr_login_impl(
+    app: tauri::AppHandle,
+    db: Database,
+) -> Result<QrPollResult, TelegramError> {
     use tauri::Emitter;
 
     // SINGLE-FLIGHT: Only one poll can run at a time
     let _guard = super::QR_POLL_LOCK.lock().await;
 
+    let poll_wait_ms = (read_qr_setting(
+        &db,
+        QR_POLL_WAIT_MS_SETTING_KEY,
+        DEFAULT_QR_POLL_WAIT_MS as i64,
+    ) as u64)
+        .min(MAX_QR_POLL_WAIT_MS);
+    let refresh_window_secs = read_qr_setting(
+        &db,
+        QR_REFRESH_WINDOW_SECS_SETTING_KEY,
+        DEFAULT_QR_REFRESH_WINDOW_SECS,
+    );
+
     log::debug!("tg_poll_qr_login_impl: Polling QR login status via updates");
 
     // Step 1: Get current client and flow state
-    let (client, flow_id, _expires_unix, updates_stream) = {
+    let (client, flow_id, expires_unix, updates_stream) = {
         let guard = AUTH_STATE.lock().await;
         let state = match guard.as_ref() {
             Some(s) => s,
             None => {
                 return Err(TelegramError {
                     message: "No active QR session".into(),
+                    code: error_code::AUTH_FLOW_EXPIRED.to_string(),
                 })
             }
         };
@@ -549,6 +712,7 @@ pub async fn tg_poll_qr_login_impl(app: tauri::AppHandle) -> Result<QrPollResult
                 } else {
                     return Err(TelegramError {
                         message: "QR flow not started".into(),
+                        code: error_code::AUTH_FLOW_EXPIRED.to_string(),
                     });
                 }
             }
@@ -575,21 +739,30 @@ pub async fn tg_poll_qr_login_impl(app: tauri::AppHandle) -> Result<QrPollResult
         )
     };
 
-    // Step 2: Wait for updates with short timeout (matches front-end polling interval)
-    // We wait up to 900ms to allow some breathing room for the next poll
+    // Step 2: Wait for updates with short timeout (matches front-end polling interval,
+    // `tg_qr_poll_wait_ms`), leaving some breathing room for the next poll
     let upd_timeout = tokio::time::timeout(
-        tokio::time::Duration::from_millis(900),
+        tokio::time::Duration::from_millis(poll_wait_ms),
         updates_stream.lock().await.recv(),
     )
     .await;
 
+    // Re-export a token close to expiry so a slow scan doesn't hit "QR expired"
+    let refresh_due =
+        refresh_window_secs > 0 && Utc::now().timestamp() >= expires_unix - refresh_window_secs;
+    let mut refreshed_qr_url = None;
+
     match upd_timeout {
-        Ok(Some(_upd)) => {
+        result if refresh_due || matches!(result, Ok(Some(_))) => {
             // Check if this is an updateLoginToken
             // Note: In grammers, we need to inspect the update enum
             // For now, if we get ANY update, we try a second export to see if it finalized
             // This is safer than exhaustive matching if the enum variant is complex
-            log::info!("tg_poll_qr_login_impl: Received update, checking login status...");
+            if matches!(result, Ok(Some(_))) {
+                log::info!("tg_poll_qr_login_impl: Received update, checking login status...");
+            } else {
+                log::info!("tg_poll_qr_login_impl: QR token close to expiry, refreshing...");
+            }
 
             match resolve_export_login_token(&client).await? {
                 tl::enums::auth::LoginToken::Success(s) => {
@@ -621,6 +794,7 @@ pub async fn tg_poll_qr_login_impl(app: tauri::AppHandle) -> Result<QrPollResult
                                 qr.last_token_b64 = token_b64.clone();
                                 qr.qr_url = format!("tg://login?token={}", token_b64);
                                 qr.expires_unix = new_expires_at;
+                                refreshed_qr_url = Some(qr.qr_url.clone());
 
                                 // Notify UI
                                 let _ = app.emit(
@@ -644,7 +818,7 @@ pub async fn tg_poll_qr_login_impl(app: tauri::AppHandle) -> Result<QrPollResult
 
     Ok(QrPollResult {
         status: QrLoginStatus::Pending,
-        qr_url: None,
+        qr_url: refreshed_qr_url,
         user_info: None,
         session_data: None,
         requires_password: false,
@@ -683,6 +857,7 @@ async fn handle_dc_migration_safe(
         let mut guard = AUTH_STATE.lock().await;
         let state = guard.as_mut().ok_or_else(|| TelegramError {
             message: "No active session".into(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
 
         state.is_migrating = true;
@@ -720,6 +895,7 @@ async fn handle_dc_migration_safe(
             }
             return Err(TelegramError {
                 message: "Too many DC migrations while importing login token".to_string(),
+                code: error_code::REQUEST_FAILED.to_string(),
             });
         }
 
@@ -748,6 +924,7 @@ async fn handle_dc_migration_safe(
                     .map(|p| p.into())
                     .map_err(|err| TelegramError {
                         message: format!("Failed to get password info: {err}"),
+                        code: request_error_code(&err),
                     })?;
 
                 let password_token = PasswordToken::new(pwd);
@@ -806,6 +983,7 @@ async fn handle_dc_migration_safe(
 
                 return Err(TelegramError {
                     message: format!("Failed to import login token on DC {}: {}", current_dc, e),
+                    code: request_error_code(&e),
                 });
             }
         };
@@ -906,12 +1084,14 @@ async fn handle_login_success(
             _ => {
                 return Err(TelegramError {
                     message: "Unexpected user type on success".into(),
+                    code: error_code::INTERNAL.to_string(),
                 })
             }
         },
         _ => {
             return Err(TelegramError {
                 message: "Sign-up required or other authorization error".into(),
+                code: error_code::SIGN_UP_REQUIRED.to_string(),
             })
         }
     };
@@ -924,6 +1104,7 @@ async fn handle_login_success(
             None => {
                 return Err(TelegramError {
                     message: "Session lost during success".into(),
+                    code: error_code::NOT_AUTHORIZED.to_string(),
                 })
             }
         };
@@ -965,6 +1146,7 @@ pub async fn tg_sign_in_with_password_impl(
 
         let state = guard.as_mut().ok_or_else(|| TelegramError {
             message: "No active auth session. Start sign-in first.".into(),
+            code: error_code::AUTH_FLOW_EXPIRED.to_string(),
         })?;
 
         log::info!(
@@ -976,6 +1158,7 @@ pub async fn tg_sign_in_with_password_impl(
 
         let token = state.password_token.take().ok_or_else(|| TelegramError {
             message: "No password token available. Call sign-in with code first.".into(),
+            code: error_code::AUTH_FLOW_EXPIRED.to_string(),
         })?;
 
         (
@@ -995,6 +1178,7 @@ pub async fn tg_sign_in_with_password_impl(
         }
         return Err(TelegramError {
             message: "Empty password".into(),
+            code: error_code::INVALID_PASSWORD.to_string(),
         });
     }
 
@@ -1023,6 +1207,7 @@ pub async fn tg_sign_in_with_password_impl(
             .await
             .map_err(|e| TelegramError {
                 message: format!("Failed to get user info: {e}"),
+                code: request_error_code(&e),
             })?;
 
             log::info!(
@@ -1061,6 +1246,7 @@ pub async fn tg_sign_in_with_password_impl(
                 me.first_name(),
                 me.last_name(),
                 me.username(),
+                Some(me.raw.id()),
             ) {
                 Ok(session_id) => {
                     log::info!(
@@ -1114,11 +1300,13 @@ pub async fn tg_sign_in_with_password_impl(
             if msg.contains("password_hash_invalid") || msg.contains("invalid") {
                 return Err(TelegramError {
                     message: "Wrong 2FA password.".into(),
+                    code: error_code::INVALID_PASSWORD.to_string(),
                 });
             }
 
             Err(TelegramError {
                 message: format!("Password authentication failed: {e}"),
+                code: request_error_code(&e),
             })
         }
     }
@@ -1143,37 +1331,46 @@ pub async fn tg_cancel_qr_login_impl() -> Result<bool, TelegramError> {
     Ok(false)
 }
 
-// Restore missing handle_already_authorized function
+// The export finished the login by itself: keep this client as the session and
+// report the login as already complete instead of showing a QR code
 #[allow(deprecated)]
 async fn handle_already_authorized(
-    client: Client,
+    built: super::utils::BuiltClient,
     session: Arc<TlSession>,
-    _flow_id: u64,
+    success: tl::types::auth::LoginTokenSuccess,
+    flow_id: u64,
 ) -> Result<QrLoginData, TelegramError> {
-    let user = run_telegram_request("handle_already_authorized.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get authorized user: {}", e),
-    })?;
-
-    let _encoded_session = encode_session(&session);
-
-    log::info!(
-        "handle_already_authorized: Already logged in as user_id={}",
-        user.raw.id()
-    );
+    let connection = ConnectionHealth::register(&built.pool_handle, &session);
+    replace_auth_state(
+        super::AuthState {
+            client: built.client,
+            session,
+            pool_handle: built.pool_handle,
+            pool_task: built.pool_task,
+            updates: built.updates,
+            login_token: None,
+            password_token: None,
+            phone_number: None,
+            flow_id,
+            qr_state: None,
+            is_migrating: false,
+            current_dc_id: None,
+            owner_context: None,
+            user_info: None,
+            account_limits: None,
+            connection,
+        },
+        "handle_already_authorized",
+    )
+    .await;
 
-    // Clear QR state since we're already authorized
-    let mut guard = AUTH_STATE.lock().await;
-    if let Some(state) = guard.as_mut() {
-        state.qr_state = None;
-    }
+    let login_result = handle_login_success(success, flow_id).await?;
 
-    // Return error to indicate flow finished (or we could change return type to Result<Enum, Error>)
-    // For now, mirroring previous behavior: return error "Already authorized"
-    Err(TelegramError {
-        message: "Already authorized".to_string(),
+    Ok(QrLoginData {
+        qr_url: String::new(),
+        qr_png_data_url: None,
+        expires_at_unix: 0,
+        flow_id,
+        login_result: Some(login_result),
     })
 }
@@ -1,31 +1,67 @@
-use crate::db::{Database, TelegramMessage, TelegramSavedItem};
-use crate::telegram::{run_telegram_request, TelegramError, AUTH_STATE};
+use super::session::{ensure_basic_connectivity, upload_size_limit};
+use crate::db::{
+    get_app_data_dir, Database, PendingUpload, TelegramMessage, TelegramSavedItem,
+    DEFAULT_SAVED_FOLDERS, DOWNLOAD_DIRECTORY_SETTING_KEY, SEARCH_ROOT_SETTING_KEY,
+};
+use crate::telegram::{
+    current_owner_context, error_code, request_error_code, run_telegram_request,
+    run_telegram_request_with_policy, run_telegram_request_with_timeout,
+    run_telegram_transfer_request, AudioMetadata, BackfillSummary, CreatedSavedFolder,
+    FileClassification, IndexSummary, IndexedCategoryCounts, MediaPreview, RequestRetryPolicy,
+    SearchAllResults, TelegramError, AUTH_STATE, TELEGRAM_TRANSFER_TIMEOUT_SECS,
+};
 use directories::{BaseDirs, UserDirs};
 use grammers_client::grammers_tl_types as tl;
 use grammers_client::types::{Attribute, Downloadable, Media, Message};
 use grammers_client::InputMessage;
 use serde_json::json;
-use std::collections::HashSet;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use std::sync::{LazyLock, Mutex as StdMutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex as StdMutex};
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_dialog::DialogExt;
-use tokio::io::{AsyncRead, AsyncWriteExt, ReadBuf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
+use tokio::sync::Notify;
 use uuid::Uuid;
 
 const DEFAULT_BATCH_SIZE: usize = 50;
 const MAX_BATCH_SIZE: usize = 200;
+const BACKFILL_BATCH_SIZE_SETTING_KEY: &str = "tg_backfill_batch_size";
 const SAVED_ROOT_PATH: &str = "/Home";
 const RECYCLE_BIN_SAVED_PATH: &str = "/Home/Recycle Bin";
 const TELEGRAM_DELETE_BATCH_SIZE: usize = 100;
 const PHOTO_SIZE_REPAIR_LIMIT: i64 = 200;
 const THUMBNAIL_PREFETCH_DELAY_MS: u64 = 90;
+// messages.getMessages accepts at most 100 ids per call
+const THUMBNAIL_PREFETCH_BATCH_SIZE: usize = 100;
+const THUMBNAIL_CACHE_MAX_MB_SETTING_KEY: &str = "tg_thumbnail_cache_max_mb";
+const DEFAULT_THUMBNAIL_CACHE_MAX_MB: i64 = 512;
+// Thumbnails written this recently may still be in use by a prefetch; clearing skips them
+const THUMBNAIL_CLEAR_GRACE_SECS: u64 = 5;
 const DOWNLOAD_SPEED_SAMPLE_INTERVAL_MS: u64 = 300;
 const DOWNLOAD_SPEED_FAST_TRANSFER_THRESHOLD_MS: u64 = 300;
+// Transfer speed and ETA are averaged over this much recent progress
+const TRANSFER_RATE_WINDOW_SECS: u64 = 5;
+// A gap this long between chunks counts as a stall and the average starts over
+const TRANSFER_STALL_RESET_SECS: u64 = 5;
+const INDEX_PROGRESS_EMIT_INTERVAL: usize = 50;
+const DEFAULT_TEXT_PREVIEW_BYTES: u64 = 64 * 1024;
+const MAX_TEXT_PREVIEW_BYTES: u64 = 4 * 1024 * 1024;
+const LIVE_SYNC_MAX_CATCH_UP: usize = 200;
+const UPLOAD_PART_SIZE_KB_SETTING_KEY: &str = "tg_upload_part_size_kb";
+// Telegram accepts power-of-two parts from 1 KB up to 512 KB, and at most 4000 parts
+const MAX_UPLOAD_PART_SIZE: usize = 512 * 1024;
+const MIN_UPLOAD_PART_SIZE: usize = 1024;
+const MAX_UPLOAD_PARTS: usize = 4000;
+// Same threshold grammers uses to switch to saveBigFilePart
+const BIG_UPLOAD_FILE_SIZE: usize = 10 * 1024 * 1024;
 
 static THUMBNAIL_FLOOD_WAIT_UNTIL: LazyLock<StdMutex<Option<Instant>>> =
     LazyLock::new(|| StdMutex::new(None));
@@ -33,6 +69,13 @@ static DOWNLOAD_CANCEL_REQUESTS: LazyLock<StdMutex<HashSet<String>>> =
     LazyLock::new(|| StdMutex::new(HashSet::new()));
 static UPLOAD_CANCEL_REQUESTS: LazyLock<StdMutex<HashSet<String>>> =
     LazyLock::new(|| StdMutex::new(HashSet::new()));
+// Uploads currently streaming to Telegram, keyed by the upload id sent in progress events
+static ACTIVE_UPLOADS: LazyLock<StdMutex<HashMap<String, ActiveUpload>>> =
+    LazyLock::new(|| StdMutex::new(HashMap::new()));
+
+// Set by tg_cancel_indexing; checked by the index and backfill loops
+static INDEXING_CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+static INDEXING_RUNS_IN_PROGRESS: AtomicUsize = AtomicUsize::new(0);
 
 const DOWNLOAD_CANCELLED_MARKER: &str = "__SKYBOX_DOWNLOAD_CANCELLED__";
 const UPLOAD_CANCELLED_MARKER: &str = "__SKYBOX_UPLOAD_CANCELLED__";
@@ -75,7 +118,7 @@ fn set_thumbnail_flood_wait(seconds: u64) {
     }
 }
 
-fn is_thumbnail_flood_wait_active() -> bool {
+pub(crate) fn is_thumbnail_flood_wait_active() -> bool {
     if let Ok(mut guard) = THUMBNAIL_FLOOD_WAIT_UNTIL.lock() {
         match *guard {
             Some(until) if Instant::now() < until => true,
@@ -99,6 +142,7 @@ struct DownloadProgressPayload {
     progress: f64,
     downloaded_bytes: u64,
     bytes_per_second: Option<f64>,
+    eta_secs: Option<u64>,
     total_bytes: Option<u64>,
     destination_path: Option<String>,
     message: Option<String>,
@@ -171,18 +215,151 @@ impl Drop for UploadCancelCleanupGuard {
     }
 }
 
+struct ActiveUpload {
+    file_name: String,
+    cancel_signal: Arc<Notify>,
+}
+
+// Registers an upload under a fresh id and unregisters it when dropped
+struct ActiveUploadGuard {
+    upload_id: String,
+    cancel_signal: Arc<Notify>,
+}
+
+impl ActiveUploadGuard {
+    fn new(file_name: &str) -> Self {
+        let upload_id = Uuid::new_v4().to_string();
+        let cancel_signal = Arc::new(Notify::new());
+
+        if let Ok(mut guard) = ACTIVE_UPLOADS.lock() {
+            guard.insert(
+                upload_id.clone(),
+                ActiveUpload {
+                    file_name: file_name.to_string(),
+                    cancel_signal: Arc::clone(&cancel_signal),
+                },
+            );
+        }
+
+        Self {
+            upload_id,
+            cancel_signal,
+        }
+    }
+}
+
+impl Drop for ActiveUploadGuard {
+    fn drop(&mut self) {
+        if let Ok(mut guard) = ACTIVE_UPLOADS.lock() {
+            guard.remove(&self.upload_id);
+        }
+    }
+}
+
+// What an upload reads from: bytes sent over IPC or a file already on disk
+enum UploadSource {
+    Bytes(Vec<u8>),
+    LocalFile(PathBuf),
+}
+
+fn hash_local_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0_u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Deletes the temporary upload copy however the upload ends, including when
+// the upload future is dropped
+struct TempUploadFile {
+    path: PathBuf,
+}
+
+impl Drop for TempUploadFile {
+    fn drop(&mut self) {
+        if let Err(cleanup_error) = fs::remove_file(&self.path) {
+            if cleanup_error.kind() != std::io::ErrorKind::NotFound {
+                log::warn!(
+                    "Failed to delete temporary upload file {}: {}",
+                    self.path.display(),
+                    cleanup_error
+                );
+            }
+        }
+    }
+}
+
+// Counts an index or backfill run as active until dropped
+struct IndexingRunGuard;
+
+impl IndexingRunGuard {
+    fn new() -> Self {
+        INDEXING_RUNS_IN_PROGRESS.fetch_add(1, Ordering::AcqRel);
+        Self
+    }
+}
+
+impl Drop for IndexingRunGuard {
+    fn drop(&mut self) {
+        INDEXING_RUNS_IN_PROGRESS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+pub(crate) fn is_indexing_in_progress() -> bool {
+    INDEXING_RUNS_IN_PROGRESS.load(Ordering::Acquire) > 0
+}
+
 #[derive(serde::Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 struct UploadProgressPayload {
+    upload_id: String,
     file_name: String,
     stage: String,
     progress: f64,
     uploaded_bytes: u64,
     bytes_per_second: Option<f64>,
+    eta_secs: Option<u64>,
     total_bytes: Option<u64>,
     message: Option<String>,
 }
 
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FolderDownloadProgressPayload {
+    source_path: String,
+    completed: usize,
+    total: usize,
+    failed: usize,
+    current_file_name: Option<String>,
+}
+
+fn emit_folder_download_progress(app: &AppHandle, payload: FolderDownloadProgressPayload) {
+    if let Err(error) = app.emit("tg-folder-download-progress", payload) {
+        log::warn!("Failed to emit folder download progress event: {}", error);
+    }
+}
+
+#[derive(serde::Serialize, Clone)]
+struct IndexProgressPayload {
+    processed: usize,
+    new_count: usize,
+    current_category: Option<String>,
+}
+
+fn emit_index_progress(app: &AppHandle, payload: IndexProgressPayload) {
+    if let Err(error) = app.emit("index-progress", payload) {
+        log::warn!("Failed to emit index progress event: {}", error);
+    }
+}
+
 fn emit_download_progress(app: &AppHandle, payload: DownloadProgressPayload) {
     if let Err(error) = app.emit("tg-download-progress", payload) {
         log::warn!("Failed to emit download progress event: {}", error);
@@ -205,74 +382,119 @@ fn download_progress_percent(downloaded_bytes: u64, total_bytes: Option<u64>) ->
     }
 }
 
-fn sample_transfer_speed_bytes_per_second(
-    latest_bytes: u64,
-    last_sample_bytes: u64,
-    last_sample_at: Instant,
-) -> (Option<f64>, u64, Instant) {
-    let now = Instant::now();
-    let elapsed = now.saturating_duration_since(last_sample_at);
-    let delta_bytes = latest_bytes.saturating_sub(last_sample_bytes);
-
+fn calculate_bytes_per_second(delta_bytes: u64, elapsed: Duration) -> Option<f64> {
     if delta_bytes == 0 || elapsed.is_zero() {
-        return (None, latest_bytes, now);
+        return None;
     }
 
     let bytes_per_second = delta_bytes as f64 / elapsed.as_secs_f64();
     if bytes_per_second.is_finite() && bytes_per_second > 0.0 {
-        (Some(bytes_per_second), latest_bytes, now)
+        Some(bytes_per_second)
     } else {
-        (None, latest_bytes, now)
+        None
     }
 }
 
-fn calculate_bytes_per_second(delta_bytes: u64, elapsed: Duration) -> Option<f64> {
-    if delta_bytes == 0 || elapsed.is_zero() {
-        return None;
+// Rolling average of a transfer's speed over its last few seconds
+struct TransferRate {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl TransferRate {
+    fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+        }
     }
 
-    let bytes_per_second = delta_bytes as f64 / elapsed.as_secs_f64();
-    if bytes_per_second.is_finite() && bytes_per_second > 0.0 {
-        Some(bytes_per_second)
-    } else {
-        None
+    fn record(&mut self, transferred_bytes: u64) {
+        let now = Instant::now();
+        if self.samples.back().is_some_and(|(sampled_at, _)| {
+            now.saturating_duration_since(*sampled_at)
+                >= Duration::from_secs(TRANSFER_STALL_RESET_SECS)
+        }) {
+            self.samples.clear();
+        }
+
+        self.samples.push_back((now, transferred_bytes));
+        while self.samples.len() > 2
+            && self.samples.front().is_some_and(|(sampled_at, _)| {
+                now.saturating_duration_since(*sampled_at)
+                    > Duration::from_secs(TRANSFER_RATE_WINDOW_SECS)
+            })
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    fn bytes_per_second(&self) -> Option<f64> {
+        let (first_at, first_bytes) = self.samples.front()?;
+        let (last_at, last_bytes) = self.samples.back()?;
+        calculate_bytes_per_second(
+            last_bytes.saturating_sub(*first_bytes),
+            last_at.saturating_duration_since(*first_at),
+        )
+    }
+
+    fn eta_secs(&self, transferred_bytes: u64, total_bytes: Option<u64>) -> Option<u64> {
+        let remaining_bytes = total_bytes?.saturating_sub(transferred_bytes);
+        if remaining_bytes == 0 {
+            return Some(0);
+        }
+
+        let bytes_per_second = self.bytes_per_second()?;
+        Some((remaining_bytes as f64 / bytes_per_second).ceil() as u64)
     }
 }
 
 struct UploadProgressReader<R> {
     inner: R,
     app: AppHandle,
+    upload_id: String,
     file_name: String,
     total_bytes: u64,
     uploaded_bytes: u64,
     last_emit_at: Instant,
-    last_speed_sample_bytes: u64,
-    last_speed_sample_at: Instant,
+    rate: TransferRate,
 }
 
 impl<R: AsyncRead + Unpin> UploadProgressReader<R> {
-    fn new(inner: R, app: AppHandle, file_name: String, total_bytes: u64) -> Self {
+    fn new(
+        inner: R,
+        app: AppHandle,
+        upload_id: String,
+        file_name: String,
+        total_bytes: u64,
+    ) -> Self {
         Self {
             inner,
             app,
+            upload_id,
             file_name,
             total_bytes,
             uploaded_bytes: 0,
             last_emit_at: Instant::now(),
-            last_speed_sample_bytes: 0,
-            last_speed_sample_at: Instant::now(),
+            rate: TransferRate::new(),
         }
     }
 
-    fn emit_progress(&self, stage: &str, message: Option<String>, bytes_per_second: Option<f64>) {
+    fn emit_progress(
+        &self,
+        stage: &str,
+        message: Option<String>,
+        bytes_per_second: Option<f64>,
+        eta_secs: Option<u64>,
+    ) {
         emit_upload_progress(
             &self.app,
             UploadProgressPayload {
+                upload_id: self.upload_id.clone(),
                 file_name: self.file_name.clone(),
                 stage: stage.to_string(),
                 progress: download_progress_percent(self.uploaded_bytes, Some(self.total_bytes)),
                 uploaded_bytes: self.uploaded_bytes,
                 bytes_per_second,
+                eta_secs,
                 total_bytes: Some(self.total_bytes),
                 message,
             },
@@ -306,15 +528,11 @@ impl<R: AsyncRead + Unpin> AsyncRead for UploadProgressReader<R> {
                 if self.last_emit_at.elapsed() >= Duration::from_millis(120)
                     || self.uploaded_bytes >= self.total_bytes
                 {
-                    let (bytes_per_second, sampled_bytes, sampled_at) =
-                        sample_transfer_speed_bytes_per_second(
-                            self.uploaded_bytes,
-                            self.last_speed_sample_bytes,
-                            self.last_speed_sample_at,
-                        );
-                    self.last_speed_sample_bytes = sampled_bytes;
-                    self.last_speed_sample_at = sampled_at;
-                    self.emit_progress("uploading", None, bytes_per_second);
+                    let uploaded_bytes = self.uploaded_bytes;
+                    self.rate.record(uploaded_bytes);
+                    let bytes_per_second = self.rate.bytes_per_second();
+                    let eta_secs = self.rate.eta_secs(uploaded_bytes, Some(self.total_bytes));
+                    self.emit_progress("uploading", None, bytes_per_second, eta_secs);
                     self.last_emit_at = Instant::now();
                 }
             }
@@ -332,8 +550,34 @@ fn backfill_complete_key(chat_id: i64) -> String {
     format!("tg_saved_backfill_complete_{}", chat_id)
 }
 
-fn clamp_batch_size(input: Option<i32>) -> usize {
-    let parsed = input.unwrap_or(DEFAULT_BATCH_SIZE as i32).max(1) as usize;
+// Where an incremental index stopped early: "<next offset id>:<last id before the run>"
+fn index_resume_key(chat_id: i64) -> String {
+    format!("tg_saved_index_resume_{}", chat_id)
+}
+
+fn parse_index_resume(value: &str) -> Option<(i32, i32)> {
+    let (offset_id, floor_id) = value.split_once(':')?;
+    let offset_id = offset_id.parse::<i32>().ok().filter(|id| *id > 0)?;
+    let floor_id = floor_id.parse::<i32>().ok()?;
+    Some((offset_id, floor_id))
+}
+
+// An explicit batch size wins; otherwise the setting, then DEFAULT_BATCH_SIZE
+fn clamp_batch_size(db: &Database, input: Option<i32>) -> usize {
+    let default_size = match db.get_setting_i64(BACKFILL_BATCH_SIZE_SETTING_KEY) {
+        Ok(Some(value)) if value > 0 => value as usize,
+        Ok(_) => DEFAULT_BATCH_SIZE,
+        Err(e) => {
+            log::warn!(
+                "Ignoring setting {}: {}",
+                BACKFILL_BATCH_SIZE_SETTING_KEY,
+                e.message
+            );
+            DEFAULT_BATCH_SIZE
+        }
+    };
+
+    let parsed = input.map_or(default_size, |size| size.max(1) as usize);
     parsed.min(MAX_BATCH_SIZE)
 }
 
@@ -372,6 +616,93 @@ enum UploadMediaKind {
     Document,
 }
 
+fn upload_file_stem(safe_path: &Path) -> String {
+    safe_path
+        .file_stem()
+        .and_then(|value| value.to_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(sanitize_file_name)
+        .unwrap_or_else(|| "upload".to_string())
+}
+
+// What to do when the target folder already has an item with the uploaded file's name
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum UploadConflictMode {
+    // Keep both, naming the new one "name (2).ext", "name (3).ext", ...
+    Rename,
+    // Fail with DESTINATION_EXISTS so the UI can ask the user
+    Error,
+}
+
+fn parse_upload_conflict_mode(
+    value: Option<&str>,
+) -> Result<Option<UploadConflictMode>, TelegramError> {
+    match value.map(str::trim).filter(|value| !value.is_empty()) {
+        None => Ok(None),
+        Some("rename") => Ok(Some(UploadConflictMode::Rename)),
+        Some("error") => Ok(Some(UploadConflictMode::Error)),
+        Some(other) => Err(TelegramError {
+            message: format!(
+                "Unknown conflict mode '{}', expected 'rename' or 'error'",
+                other
+            ),
+            code: error_code::INVALID_INPUT.to_string(),
+        }),
+    }
+}
+
+// The original file name, or the first free "name (n).ext" in `folder_path`
+fn resolve_upload_conflict_name(
+    db: &Database,
+    owner_id: &str,
+    folder_path: &str,
+    file_name: &str,
+    extension: Option<&str>,
+    mode: UploadConflictMode,
+) -> Result<String, TelegramError> {
+    let safe_file_name = sanitize_file_name(file_name);
+    let stem = upload_file_stem(Path::new(&safe_file_name));
+
+    let mut index = 1_i32;
+    loop {
+        let candidate = match (index, extension) {
+            (1, Some(ext)) => format!("{}.{}", stem, ext),
+            (1, None) => stem.clone(),
+            (_, Some(ext)) => format!("{} ({}).{}", stem, index, ext),
+            (_, None) => format!("{} ({})", stem, index),
+        };
+
+        let taken = db
+            .telegram_saved_item_name_exists(owner_id, folder_path, &candidate)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to check for existing item: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+        if !taken {
+            return Ok(candidate);
+        }
+
+        if mode == UploadConflictMode::Error {
+            return Err(TelegramError {
+                message: format!(
+                    "An item named {} already exists in {}",
+                    candidate, folder_path
+                ),
+                code: error_code::DESTINATION_EXISTS.to_string(),
+            });
+        }
+
+        index += 1;
+    }
+}
+
+fn upload_target_folder(file_path: Option<&str>, extension: Option<&str>) -> String {
+    file_path
+        .map(normalize_saved_path)
+        .unwrap_or_else(|| category_to_saved_path(classify_extension(extension).category))
+}
+
 fn build_upload_file_name(file_name: &str) -> (String, Option<String>) {
     let safe_file_name = sanitize_file_name(file_name);
     let safe_path = Path::new(&safe_file_name);
@@ -381,13 +712,7 @@ fn build_upload_file_name(file_name: &str) -> (String, Option<String>) {
         .and_then(|ext| ext.to_str())
         .and_then(|ext| normalize_extension(Some(ext)));
 
-    let stem = safe_path
-        .file_stem()
-        .and_then(|value| value.to_str())
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(sanitize_file_name)
-        .unwrap_or_else(|| "upload".to_string());
+    let stem = upload_file_stem(safe_path);
 
     let uuid = Uuid::new_v4().simple().to_string();
     let upload_file_name = match extension.as_deref() {
@@ -398,6 +723,168 @@ fn build_upload_file_name(file_name: &str) -> (String, Option<String>) {
     (upload_file_name, extension)
 }
 
+// Reads width and height from the image header, since `image` is built with PNG support only.
+// Known formats it cannot size yield Ok(None); anything not a readable image is an error.
+fn probe_image_dimensions<R: std::io::BufRead + std::io::Seek>(
+    mut source: R,
+) -> Result<Option<(u32, u32)>, String> {
+    let header = read_image_header(&mut source, 30)?;
+    source
+        .seek(std::io::SeekFrom::Start(0))
+        .map_err(|e| e.to_string())?;
+
+    if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return probe_jpeg_dimensions(source).map(Some);
+    }
+    if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        return probe_gif_dimensions(&header).map(Some);
+    }
+    if header.len() >= 12 && &header[0..4] == b"RIFF" && &header[8..12] == b"WEBP" {
+        return probe_webp_dimensions(&header).map(Some);
+    }
+    if header.starts_with(b"BM") {
+        return probe_bmp_dimensions(&header).map(Some);
+    }
+
+    let reader = image::ImageReader::new(source)
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+
+    if reader.format().is_none() {
+        return Err("Unrecognized image format".to_string());
+    }
+
+    match reader.into_dimensions() {
+        Ok(dimensions) => Ok(Some(dimensions)),
+        Err(image::ImageError::Unsupported(_)) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn read_image_header<R: Read>(source: &mut R, len: u64) -> Result<Vec<u8>, String> {
+    let mut header = Vec::new();
+    source
+        .take(len)
+        .read_to_end(&mut header)
+        .map_err(|e| e.to_string())?;
+    Ok(header)
+}
+
+fn non_zero_dimensions(width: u32, height: u32) -> Result<(u32, u32), String> {
+    if width == 0 || height == 0 {
+        return Err("Image header has a zero dimension".to_string());
+    }
+    Ok((width, height))
+}
+
+// Walks the JPEG segments up to the first start-of-frame marker
+fn probe_jpeg_dimensions<R: Read + std::io::Seek>(mut source: R) -> Result<(u32, u32), String> {
+    let mut byte = [0_u8; 1];
+    let mut read_byte = |source: &mut R| -> Result<u8, String> {
+        source.read_exact(&mut byte).map_err(|e| e.to_string())?;
+        Ok(byte[0])
+    };
+
+    source
+        .seek(std::io::SeekFrom::Start(2))
+        .map_err(|e| e.to_string())?;
+    loop {
+        if read_byte(&mut source)? != 0xFF {
+            return Err("Malformed JPEG segment".to_string());
+        }
+        let mut marker = read_byte(&mut source)?;
+        while marker == 0xFF {
+            marker = read_byte(&mut source)?;
+        }
+
+        match marker {
+            0x01 | 0xD0..=0xD7 => continue,
+            0xD9 | 0xDA => return Err("JPEG has no frame header".to_string()),
+            _ => {}
+        }
+
+        let mut length = [0_u8; 2];
+        source.read_exact(&mut length).map_err(|e| e.to_string())?;
+        let length = u16::from_be_bytes(length);
+        if length < 2 {
+            return Err("Malformed JPEG segment length".to_string());
+        }
+
+        let is_frame_header =
+            matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_frame_header {
+            let mut frame = [0_u8; 5];
+            source.read_exact(&mut frame).map_err(|e| e.to_string())?;
+            let height = u16::from_be_bytes([frame[1], frame[2]]) as u32;
+            let width = u16::from_be_bytes([frame[3], frame[4]]) as u32;
+            return non_zero_dimensions(width, height);
+        }
+
+        source
+            .seek(std::io::SeekFrom::Current(length as i64 - 2))
+            .map_err(|e| e.to_string())?;
+    }
+}
+
+fn probe_gif_dimensions(header: &[u8]) -> Result<(u32, u32), String> {
+    if header.len() < 10 {
+        return Err("Truncated GIF header".to_string());
+    }
+    let width = u16::from_le_bytes([header[6], header[7]]) as u32;
+    let height = u16::from_le_bytes([header[8], header[9]]) as u32;
+    non_zero_dimensions(width, height)
+}
+
+// Lossy (VP8), lossless (VP8L) and extended (VP8X) files keep their size in the first chunk
+fn probe_webp_dimensions(header: &[u8]) -> Result<(u32, u32), String> {
+    if header.len() < 30 {
+        return Err("Truncated WebP header".to_string());
+    }
+
+    match &header[12..16] {
+        b"VP8 " => {
+            if header[23..26] != [0x9D, 0x01, 0x2A] {
+                return Err("Malformed VP8 frame header".to_string());
+            }
+            let width = u16::from_le_bytes([header[26], header[27]]) as u32 & 0x3FFF;
+            let height = u16::from_le_bytes([header[28], header[29]]) as u32 & 0x3FFF;
+            non_zero_dimensions(width, height)
+        }
+        b"VP8L" => {
+            if header[20] != 0x2F {
+                return Err("Malformed VP8L header".to_string());
+            }
+            let bits = u32::from_le_bytes([header[21], header[22], header[23], header[24]]);
+            Ok(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => {
+            let width = u32::from_le_bytes([header[24], header[25], header[26], 0]) + 1;
+            let height = u32::from_le_bytes([header[27], header[28], header[29], 0]) + 1;
+            Ok((width, height))
+        }
+        _ => Err("Unknown WebP chunk".to_string()),
+    }
+}
+
+fn probe_bmp_dimensions(header: &[u8]) -> Result<(u32, u32), String> {
+    if header.len() < 26 {
+        return Err("Truncated BMP header".to_string());
+    }
+
+    let dib_size = u32::from_le_bytes([header[14], header[15], header[16], header[17]]);
+    if dib_size == 12 {
+        // OS/2 BITMAPCOREHEADER stores 16-bit sizes
+        let width = u16::from_le_bytes([header[18], header[19]]) as u32;
+        let height = u16::from_le_bytes([header[20], header[21]]) as u32;
+        return non_zero_dimensions(width, height);
+    }
+
+    let width = i32::from_le_bytes([header[18], header[19], header[20], header[21]]);
+    // Negative heights mark top-down bitmaps
+    let height = i32::from_le_bytes([header[22], header[23], header[24], header[25]]);
+    non_zero_dimensions(width.unsigned_abs(), height.unsigned_abs())
+}
+
 fn upload_media_kind_for_extension(extension: Option<&str>) -> UploadMediaKind {
     match extension.unwrap_or_default() {
         "jpg" | "jpeg" | "png" | "webp" | "gif" | "bmp" => UploadMediaKind::Photo,
@@ -471,6 +958,29 @@ fn classify_extension(extension: Option<&str>) -> ExtensionClassification {
     }
 }
 
+// Accepts a file name ("photo.JPG"), a dotted extension (".jpg") or a bare one ("jpg")
+pub fn classify_file_impl(name_or_extension: String) -> Result<FileClassification, TelegramError> {
+    let trimmed = name_or_extension.trim();
+    if trimmed.is_empty() {
+        return Err(TelegramError {
+            message: "File name or extension is required".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
+    let raw_extension = trimmed
+        .rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .unwrap_or(trimmed);
+    let extension = normalize_extension(Some(raw_extension));
+    let classification = classify_extension(extension.as_deref());
+
+    Ok(FileClassification {
+        category: classification.category.to_string(),
+        file_type: classification.file_type.to_string(),
+    })
+}
+
 fn extension_from_mime_type(mime_type: Option<&str>) -> Option<String> {
     let mime = mime_type?.trim().to_lowercase();
     let ext = match mime.as_str() {
@@ -574,50 +1084,161 @@ fn build_temp_upload_path(file_name: &str) -> PathBuf {
     ))
 }
 
-fn get_thumbnail_cache_dir() -> Result<PathBuf, TelegramError> {
-    let base_dirs = BaseDirs::new().ok_or_else(|| TelegramError {
-        message: "Failed to resolve app data directory".to_string(),
-    })?;
+// Same directory the database lives in, so all app data stays together
+fn get_skybox_data_dir() -> Result<PathBuf, TelegramError> {
+    get_app_data_dir().map_err(|e| TelegramError {
+        message: format!("Failed to resolve app data directory: {}", e.message),
+        code: error_code::IO.to_string(),
+    })
+}
+
+// Older builds cached thumbnails under `Skybox/.thumbnails` next to the `skybox` data dir.
+// Files are copied first and the stored paths rewritten; the legacy copies are only
+// removed once that update has committed, so no thumbnail is left without its file.
+pub(crate) fn migrate_legacy_thumbnail_cache(db: &Database) {
+    let Some(base_dirs) = BaseDirs::new() else {
+        return;
+    };
 
-    let thumbnails_dir = base_dirs
+    let legacy_dir = base_dirs
         .data_local_dir()
         .join("Skybox")
         .join(".thumbnails");
-    fs::create_dir_all(&thumbnails_dir).map_err(|e| TelegramError {
-        message: format!(
-            "Failed to create thumbnail cache directory {}: {}",
-            thumbnails_dir.display(),
-            e
-        ),
-    })?;
+    if !legacy_dir.is_dir() {
+        return;
+    }
 
-    Ok(thumbnails_dir)
-}
+    let thumbnails_dir = match get_thumbnail_cache_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!("Skipping legacy thumbnail migration: {}", e.message);
+            return;
+        }
+    };
 
-fn detect_thumbnail_extension(bytes: &[u8]) -> &'static str {
-    if bytes.len() >= 3 && bytes[0] == 0xFF && bytes[1] == 0xD8 && bytes[2] == 0xFF {
-        return "jpg";
-    }
-    if bytes.len() >= 8
-        && bytes[0] == 0x89
-        && bytes[1] == 0x50
-        && bytes[2] == 0x4E
-        && bytes[3] == 0x47
-        && bytes[4] == 0x0D
-        && bytes[5] == 0x0A
-        && bytes[6] == 0x1A
-        && bytes[7] == 0x0A
-    {
-        return "png";
-    }
-    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
-        return "webp";
-    }
-    if bytes.len() >= 4 && &bytes[0..4] == b"GIF8" {
-        return "gif";
+    // On case-insensitive filesystems both spellings are the same directory
+    if let (Ok(legacy_canonical), Ok(current_canonical)) = (
+        fs::canonicalize(&legacy_dir),
+        fs::canonicalize(&thumbnails_dir),
+    ) {
+        if legacy_canonical == current_canonical {
+            return;
+        }
     }
 
-    "jpg"
+    let entries = match fs::read_dir(&legacy_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!(
+                "Failed to read legacy thumbnail cache {}: {}",
+                legacy_dir.display(),
+                e
+            );
+            return;
+        }
+    };
+
+    let mut copied = Vec::new();
+    for entry in entries.flatten() {
+        let source_path = entry.path();
+        if !source_path.is_file() {
+            continue;
+        }
+
+        let target_path = thumbnails_dir.join(entry.file_name());
+        if target_path.exists() {
+            copied.push(source_path);
+            continue;
+        }
+
+        match fs::copy(&source_path, &target_path) {
+            Ok(_) => copied.push(source_path),
+            Err(e) => log::warn!(
+                "Failed to migrate cached thumbnail {}: {}",
+                source_path.display(),
+                e
+            ),
+        }
+    }
+
+    // Stored paths use `/` separators, as written by cache_thumbnail_bytes
+    let old_prefix = format!("{}/", legacy_dir.to_string_lossy().replace('\\', "/"));
+    let new_prefix = format!("{}/", thumbnails_dir.to_string_lossy().replace('\\', "/"));
+    let rewritten_count = match db.rewrite_telegram_thumbnail_path_prefix(&old_prefix, &new_prefix)
+    {
+        Ok(count) => count,
+        Err(e) => {
+            log::warn!(
+                "Failed to update migrated thumbnail paths, keeping {}: {}",
+                legacy_dir.display(),
+                e.message
+            );
+            return;
+        }
+    };
+
+    for source_path in &copied {
+        if let Err(e) = fs::remove_file(source_path) {
+            log::warn!(
+                "Failed to remove legacy thumbnail {}: {}",
+                source_path.display(),
+                e
+            );
+        }
+    }
+
+    let _ = fs::remove_dir(&legacy_dir);
+    if let Some(legacy_parent) = legacy_dir.parent() {
+        let _ = fs::remove_dir(legacy_parent);
+    }
+
+    log::info!(
+        "Migrated {} cached thumbnails from {} to {}, updated {} stored paths",
+        copied.len(),
+        legacy_dir.display(),
+        thumbnails_dir.display(),
+        rewritten_count
+    );
+}
+
+fn get_thumbnail_cache_dir() -> Result<PathBuf, TelegramError> {
+    let thumbnails_dir = get_skybox_data_dir()?.join(".thumbnails");
+    fs::create_dir_all(&thumbnails_dir).map_err(|e| TelegramError {
+        message: format!(
+            "Failed to create thumbnail cache directory {}: {}",
+            thumbnails_dir.display(),
+            e
+        ),
+        code: error_code::IO.to_string(),
+    })?;
+
+    Ok(thumbnails_dir)
+}
+
+fn detect_thumbnail_extension(bytes: &[u8]) -> &'static str {
+    if bytes.len() >= 3 && bytes[0] == 0xFF && bytes[1] == 0xD8 && bytes[2] == 0xFF {
+        return "jpg";
+    }
+    if bytes.len() >= 8
+        && bytes[0] == 0x89
+        && bytes[1] == 0x50
+        && bytes[2] == 0x4E
+        && bytes[3] == 0x47
+        && bytes[4] == 0x0D
+        && bytes[5] == 0x0A
+        && bytes[6] == 0x1A
+        && bytes[7] == 0x0A
+    {
+        return "png";
+    }
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return "webp";
+    }
+    if bytes.len() >= 4 && &bytes[0..4] == b"GIF8" {
+        return "gif";
+    }
+
+    "jpg"
 }
 
 fn cache_thumbnail_bytes(
@@ -635,63 +1256,337 @@ fn cache_thumbnail_bytes(
             thumbnail_path.display(),
             e
         ),
+        code: error_code::IO.to_string(),
     })?;
 
     Ok(thumbnail_path.to_string_lossy().replace('\\', "/"))
 }
 
-fn decode_data_url_image_bytes(data_url: &str) -> Option<Vec<u8>> {
-    let base64_marker = "base64,";
-    let payload_index = data_url.find(base64_marker)? + base64_marker.len();
-    let payload = &data_url[payload_index..];
+// Part size for an upload of `total_bytes`, from the setting rounded down to a legal
+// size. Grows again if the file would otherwise need more parts than Telegram allows.
+fn upload_part_size_bytes(db: &Database, total_bytes: usize) -> usize {
+    let configured = match db.get_setting_i64(UPLOAD_PART_SIZE_KB_SETTING_KEY) {
+        Ok(Some(value)) if value > 0 => (value as usize).saturating_mul(1024),
+        Ok(_) => MAX_UPLOAD_PART_SIZE,
+        Err(e) => {
+            log::warn!(
+                "Ignoring setting {}: {}",
+                UPLOAD_PART_SIZE_KB_SETTING_KEY,
+                e.message
+            );
+            MAX_UPLOAD_PART_SIZE
+        }
+    };
+
+    let clamped = configured.clamp(MIN_UPLOAD_PART_SIZE, MAX_UPLOAD_PART_SIZE);
+    let mut part_size = MIN_UPLOAD_PART_SIZE;
+    while part_size * 2 <= clamped {
+        part_size *= 2;
+    }
+
+    while part_size < MAX_UPLOAD_PART_SIZE && total_bytes.div_ceil(part_size) > MAX_UPLOAD_PARTS {
+        part_size *= 2;
+    }
+
+    part_size
+}
+
+// Sequential counterpart of Client::upload_stream for part sizes below its fixed 512 KB
+async fn upload_stream_with_part_size<S: AsyncRead + Unpin>(
+    client: &grammers_client::Client,
+    stream: &mut S,
+    size: usize,
+    name: String,
+    part_size: usize,
+) -> Result<grammers_client::types::media::Uploaded, std::io::Error> {
+    let file_id = Uuid::new_v4().as_u64_pair().0 as i64;
+    let total_parts = size.div_ceil(part_size).max(1) as i32;
+    let big_file = size > BIG_UPLOAD_FILE_SIZE;
+    let mut buffer = vec![0u8; part_size];
+
+    for file_part in 0..total_parts {
+        let mut read = 0;
+        while read < part_size {
+            let n = stream.read(&mut buffer[read..]).await?;
+            if n == 0 {
+                break;
+            }
+            read += n;
+        }
+
+        if read < part_size && file_part != total_parts - 1 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "reached EOF before reaching the last file part",
+            ));
+        }
+
+        let bytes = buffer[..read].to_vec();
+        let stored = if big_file {
+            client
+                .invoke(&tl::functions::upload::SaveBigFilePart {
+                    file_id,
+                    file_part,
+                    file_total_parts: total_parts,
+                    bytes,
+                })
+                .await
+        } else {
+            client
+                .invoke(&tl::functions::upload::SaveFilePart {
+                    file_id,
+                    file_part,
+                    bytes,
+                })
+                .await
+        }
+        .map_err(std::io::Error::other)?;
+
+        if !stored {
+            return Err(std::io::Error::other(
+                "server failed to store uploaded data",
+            ));
+        }
+    }
+
+    let raw = if big_file {
+        tl::types::InputFileBig {
+            id: file_id,
+            parts: total_parts,
+            name,
+        }
+        .into()
+    } else {
+        // The checksum is optional for small files
+        tl::types::InputFile {
+            id: file_id,
+            parts: total_parts,
+            name,
+            md5_checksum: String::new(),
+        }
+        .into()
+    };
+
+    Ok(grammers_client::types::media::Uploaded { raw })
+}
+
+fn thumbnail_cache_limit_bytes(db: &Database) -> u64 {
+    let max_mb = match db.get_setting_i64(THUMBNAIL_CACHE_MAX_MB_SETTING_KEY) {
+        Ok(Some(value)) if value > 0 => value,
+        Ok(_) => DEFAULT_THUMBNAIL_CACHE_MAX_MB,
+        Err(e) => {
+            log::warn!(
+                "Ignoring setting {}: {}",
+                THUMBNAIL_CACHE_MAX_MB_SETTING_KEY,
+                e.message
+            );
+            DEFAULT_THUMBNAIL_CACHE_MAX_MB
+        }
+    };
+
+    (max_mb as u64).saturating_mul(1024 * 1024)
+}
+
+// Cache hits bump mtime so eviction approximates least-recently-used
+fn touch_cached_thumbnail(path: &Path) {
+    if let Ok(file) = fs::OpenOptions::new().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+// Modification time, size and path of every file in the thumbnail cache
+fn list_thumbnail_cache_files() -> Result<Vec<(SystemTime, u64, PathBuf)>, TelegramError> {
+    let thumbnail_dir = get_thumbnail_cache_dir()?;
+    let entries = fs::read_dir(&thumbnail_dir).map_err(|e| TelegramError {
+        message: format!(
+            "Failed to read thumbnail cache directory {}: {}",
+            thumbnail_dir.display(),
+            e
+        ),
+        code: error_code::IO.to_string(),
+    })?;
+
+    Ok(entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            if !metadata.is_file() {
+                return None;
+            }
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            Some((modified, metadata.len(), entry.path()))
+        })
+        .collect())
+}
+
+/// Delete the oldest cached thumbnails (by mtime) until the cache is at most `max_bytes`.
+/// Returns the number of files removed.
+fn enforce_thumbnail_cache_limit(max_bytes: u64) -> Result<usize, TelegramError> {
+    let mut files = list_thumbnail_cache_files()?;
+
+    let mut total_bytes: u64 = files.iter().map(|(_, size, _)| *size).sum();
+    if total_bytes <= max_bytes {
+        return Ok(0);
+    }
 
+    files.sort_by_key(|(modified, _, _)| *modified);
+
+    let mut evicted_count = 0usize;
+    for (_, size, path) in files {
+        if total_bytes <= max_bytes {
+            break;
+        }
+
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                total_bytes = total_bytes.saturating_sub(size);
+                evicted_count += 1;
+            }
+            Err(e) => log::warn!("Failed to evict cached thumbnail {}: {}", path.display(), e),
+        }
+    }
+
+    log::info!(
+        "Evicted {} cached thumbnails, cache is now {} bytes",
+        evicted_count,
+        total_bytes
+    );
+
+    Ok(evicted_count)
+}
+
+pub async fn get_thumbnail_cache_size_impl() -> Result<u64, TelegramError> {
+    Ok(list_thumbnail_cache_files()?
+        .iter()
+        .map(|(_, size, _)| *size)
+        .sum())
+}
+
+/// Delete every cached thumbnail and return the bytes freed. Files written in the
+/// last few seconds may belong to a running prefetch and are left alone; the
+/// database keeps their paths and they are fetched again when next shown.
+pub async fn clear_thumbnail_cache_impl() -> Result<u64, TelegramError> {
+    let files = list_thumbnail_cache_files()?;
+    let grace = Duration::from_secs(THUMBNAIL_CLEAR_GRACE_SECS);
+    let now = SystemTime::now();
+
+    let mut freed_bytes = 0_u64;
+    let mut skipped_count = 0usize;
+    for (modified, size, path) in files {
+        if now.duration_since(modified).unwrap_or_default() < grace {
+            skipped_count += 1;
+            continue;
+        }
+
+        match fs::remove_file(&path) {
+            Ok(()) => freed_bytes += size,
+            Err(e) => log::warn!(
+                "Failed to delete cached thumbnail {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    log::info!(
+        "Cleared thumbnail cache: freed {} bytes, kept {} recently written files",
+        freed_bytes,
+        skipped_count
+    );
+
+    Ok(freed_bytes)
+}
+
+// Decodes a `data:image/...;base64,` URL from a legacy thumbnail. Both the standard
+// and the URL-safe alphabet are accepted, with or without padding.
+fn decode_data_url_image_bytes(data_url: &str) -> Option<Vec<u8>> {
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
     use base64::Engine;
-    base64::engine::general_purpose::STANDARD
-        .decode(payload)
-        .ok()
+
+    let decoded = data_url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .filter(|(header, _)| header.starts_with("image/") && header.ends_with(";base64"))
+        .and_then(|(_, payload)| {
+            let payload = payload.trim();
+            [STANDARD, URL_SAFE, STANDARD_NO_PAD, URL_SAFE_NO_PAD]
+                .iter()
+                .find_map(|engine| engine.decode(payload).ok())
+        })
+        .filter(|bytes| !bytes.is_empty());
+
+    if decoded.is_none() {
+        log::debug!(
+            "Could not decode thumbnail data URL ({} chars, starts with {:?})",
+            data_url.len(),
+            data_url.chars().take(32).collect::<String>()
+        );
+    }
+
+    decoded
 }
 
-fn normalize_saved_path(path: &str) -> String {
+/// Resolve `.`/`..` segments of a saved path under `/Home`.
+///
+/// Returns `None` when the path would climb above `/Home`, so callers that
+/// gate on the resolved path (e.g. the Recycle Bin checks) cannot be bypassed.
+fn resolve_saved_path(path: &str) -> Option<String> {
     let normalized = path.replace('\\', "/");
-    let trimmed = normalized.trim();
-    if trimmed.is_empty() || trimmed == "/" {
-        return "/Home".to_string();
+    let mut segments = normalized
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .peekable();
+
+    if segments.peek() == Some(&SAVED_ROOT_PATH.trim_start_matches('/')) {
+        segments.next();
     }
 
-    let without_trailing = trimmed.trim_end_matches('/');
-    if without_trailing.starts_with("/Home") {
-        return without_trailing.to_string();
+    let mut resolved: Vec<&str> = Vec::new();
+    for segment in segments {
+        match segment {
+            "." => {}
+            ".." => {
+                resolved.pop()?;
+            }
+            _ => resolved.push(segment),
+        }
     }
 
-    if without_trailing.starts_with('/') {
-        return format!("/Home{}", without_trailing);
+    if resolved.is_empty() {
+        return Some(SAVED_ROOT_PATH.to_string());
     }
 
-    format!("/Home/{}", without_trailing)
+    Some(format!("{}/{}", SAVED_ROOT_PATH, resolved.join("/")))
+}
+
+fn normalize_saved_path(path: &str) -> String {
+    resolve_saved_path(path).unwrap_or_else(|| SAVED_ROOT_PATH.to_string())
 }
 
 fn virtual_to_saved_path(path: &str) -> Option<String> {
     let trimmed = path.trim();
-    if trimmed.starts_with("tg://saved") {
-        let relative = trimmed
-            .trim_start_matches("tg://saved")
-            .trim_start_matches('/')
-            .trim_end_matches('/');
-
-        if relative.is_empty() {
-            return Some("/Home".to_string());
-        }
-
-        return Some(format!("/Home/{}", relative));
+    if let Some(relative) = trimmed.strip_prefix("tg://saved") {
+        return resolve_saved_path(&format!("{}/{}", SAVED_ROOT_PATH, relative));
     }
 
-    if trimmed.starts_with("/Home") || trimmed.starts_with('/') {
-        return Some(normalize_saved_path(trimmed));
+    if trimmed.starts_with('/') {
+        return resolve_saved_path(trimmed);
     }
 
     None
 }
 
+// Inverse of `virtual_to_saved_path` for an already normalized saved path
+fn saved_to_virtual_path(saved_path: &str) -> String {
+    match saved_path.strip_prefix(SAVED_ROOT_PATH) {
+        Some(relative) if !relative.trim_matches('/').is_empty() => {
+            format!("tg://saved/{}", relative.trim_matches('/'))
+        }
+        _ => "tg://saved".to_string(),
+    }
+}
+
 fn parse_message_id_from_virtual_path(path: &str) -> Option<i32> {
     path.trim()
         .strip_prefix("tg://msg/")
@@ -721,17 +1616,14 @@ fn split_saved_parent_and_name(path: &str) -> Option<(String, String)> {
 }
 
 fn get_download_staging_dir() -> Result<PathBuf, TelegramError> {
-    let base_dirs = BaseDirs::new().ok_or_else(|| TelegramError {
-        message: "Failed to resolve app data directory".to_string(),
-    })?;
-
-    let downloads_dir = base_dirs.data_local_dir().join("Skybox").join("Downloads");
+    let downloads_dir = get_skybox_data_dir()?.join("Downloads");
     fs::create_dir_all(&downloads_dir).map_err(|e| TelegramError {
         message: format!(
             "Failed to create staging download directory {}: {}",
             downloads_dir.display(),
             e
         ),
+        code: error_code::IO.to_string(),
     })?;
 
     Ok(downloads_dir)
@@ -740,10 +1632,12 @@ fn get_download_staging_dir() -> Result<PathBuf, TelegramError> {
 fn get_device_downloads_dir() -> Result<PathBuf, TelegramError> {
     let user_dirs = UserDirs::new().ok_or_else(|| TelegramError {
         message: "Failed to resolve device download directory".to_string(),
+        code: error_code::IO.to_string(),
     })?;
 
     let downloads_root_dir = user_dirs.download_dir().ok_or_else(|| TelegramError {
         message: "Device Downloads folder is not available".to_string(),
+        code: error_code::NOT_FOUND.to_string(),
     })?;
 
     let downloads_dir = downloads_root_dir.join("SkyBox");
@@ -754,31 +1648,73 @@ fn get_device_downloads_dir() -> Result<PathBuf, TelegramError> {
             downloads_dir.display(),
             e
         ),
+        code: error_code::IO.to_string(),
     })?;
 
     Ok(downloads_dir)
 }
 
-fn get_media_preview_cache_dir() -> Result<PathBuf, TelegramError> {
-    let base_dirs = BaseDirs::new().ok_or_else(|| TelegramError {
-        message: "Failed to resolve app data directory".to_string(),
-    })?;
+// The configured download directory if it is still usable, otherwise Downloads/SkyBox
+fn get_default_download_dir(db: &Database) -> Result<PathBuf, TelegramError> {
+    match db.get_setting(DOWNLOAD_DIRECTORY_SETTING_KEY) {
+        Ok(Some(value)) if !value.trim().is_empty() => {
+            let configured_dir = PathBuf::from(value.trim());
+            if configured_dir.is_dir() {
+                return Ok(configured_dir);
+            }
 
-    let cache_dir = base_dirs
-        .data_local_dir()
-        .join("Skybox")
-        .join(".media-preview");
+            log::warn!(
+                "get_default_download_dir: Configured download directory {} is missing, using device Downloads",
+                configured_dir.display()
+            );
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!(
+            "get_default_download_dir: Failed to read download directory setting: {}",
+            e.message
+        ),
+    }
+
+    get_device_downloads_dir()
+}
+
+fn get_media_preview_cache_dir() -> Result<PathBuf, TelegramError> {
+    let cache_dir = get_skybox_data_dir()?.join(".media-preview");
     fs::create_dir_all(&cache_dir).map_err(|e| TelegramError {
         message: format!(
             "Failed to create media preview cache directory {}: {}",
             cache_dir.display(),
             e
         ),
+        code: error_code::IO.to_string(),
     })?;
 
     Ok(cache_dir)
 }
 
+// Remove everything SkyBox keeps on disk for previews: thumbnails, media previews and staged downloads
+pub(crate) fn clear_local_media_caches() -> Result<(), TelegramError> {
+    let cache_dirs = [
+        get_thumbnail_cache_dir()?,
+        get_media_preview_cache_dir()?,
+        get_download_staging_dir()?,
+    ];
+
+    for cache_dir in cache_dirs {
+        fs::remove_dir_all(&cache_dir).map_err(|e| TelegramError {
+            message: format!(
+                "Failed to clear cache directory {}: {}",
+                cache_dir.display(),
+                e
+            ),
+            code: error_code::IO.to_string(),
+        })?;
+        log::info!("Cleared cache directory {}", cache_dir.display());
+    }
+
+    Ok(())
+}
+
 fn build_preview_cache_path(cache_dir: &Path, message_id: i32, file_name: &str) -> PathBuf {
     let safe_name = sanitize_file_name(file_name);
     let safe_path = Path::new(&safe_name);
@@ -838,53 +1774,180 @@ fn build_unique_file_path(directory: &Path, file_name: &str) -> PathBuf {
     }
 }
 
-fn move_staged_download(staged_path: &Path, destination_path: &Path) -> Result<(), TelegramError> {
-    if destination_path.exists() {
-        fs::remove_file(destination_path).map_err(|e| TelegramError {
-            message: format!(
-                "Failed to replace existing destination file {}: {}",
-                destination_path.display(),
-                e
-            ),
-        })?;
+// Without `overwrite` the destination is never replaced: a file that appeared
+// there since the pre-check fails the move with DESTINATION_EXISTS
+fn move_staged_download(
+    staged_path: &Path,
+    destination_path: &Path,
+    overwrite: bool,
+) -> Result<(), TelegramError> {
+    let destination_exists = |destination_path: &Path| TelegramError {
+        message: format!(
+            "Destination file already exists: {}",
+            destination_path.display()
+        ),
+        code: error_code::DESTINATION_EXISTS.to_string(),
+    };
+
+    if overwrite {
+        // rename replaces an existing destination in one step
+        if fs::rename(staged_path, destination_path).is_ok() {
+            return Ok(());
+        }
+    } else {
+        // hard_link fails instead of replacing when the destination exists
+        match fs::hard_link(staged_path, destination_path) {
+            Ok(()) => {
+                if let Err(e) = fs::remove_file(staged_path) {
+                    log::warn!(
+                        "move_staged_download: Failed to clean staging file {}: {}",
+                        staged_path.display(),
+                        e
+                    );
+                }
+                return Ok(());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(destination_exists(destination_path));
+            }
+            // Another volume, or links unsupported: copy below
+            Err(_) => {}
+        }
     }
 
-    match fs::rename(staged_path, destination_path) {
-        Ok(()) => Ok(()),
-        Err(rename_error) => {
-            fs::copy(staged_path, destination_path).map_err(|copy_error| TelegramError {
-                message: format!(
-                    "Failed to move downloaded file to device Downloads (rename: {}; copy: {})",
-                    rename_error, copy_error
-                ),
-            })?;
+    let copy_result = fs::File::open(staged_path).and_then(|mut staged_file| {
+        let mut destination_file = if overwrite {
+            fs::File::create(destination_path)?
+        } else {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(destination_path)?
+        };
+        std::io::copy(&mut staged_file, &mut destination_file)
+    });
 
-            fs::remove_file(staged_path).map_err(|remove_error| TelegramError {
+    match copy_result {
+        Ok(_) => {}
+        Err(e) if !overwrite && e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(destination_exists(destination_path));
+        }
+        Err(e) => {
+            return Err(TelegramError {
                 message: format!(
-                    "Downloaded file copied but failed to clean staging file {}: {}",
-                    staged_path.display(),
-                    remove_error
+                    "Failed to move downloaded file to {}: {}",
+                    destination_path.display(),
+                    e
                 ),
-            })?;
-
-            Ok(())
+                code: error_code::IO.to_string(),
+            });
         }
     }
+
+    fs::remove_file(staged_path).map_err(|remove_error| TelegramError {
+        message: format!(
+            "Downloaded file copied but failed to clean staging file {}: {}",
+            staged_path.display(),
+            remove_error
+        ),
+        code: error_code::IO.to_string(),
+    })
+}
+
+// Checked with the expected size before a download starts, so a full disk fails
+// up front instead of part way through
+fn ensure_free_space(directory: &Path, required_bytes: u64) -> Result<(), TelegramError> {
+    let Some(available_bytes) = crate::fs::available_space(directory) else {
+        return Ok(());
+    };
+
+    if available_bytes >= required_bytes {
+        return Ok(());
+    }
+
+    Err(TelegramError {
+        message: format!(
+            "Not enough free space in {}: {} bytes needed, {} bytes available",
+            directory.display(),
+            required_bytes,
+            available_bytes
+        ),
+        code: error_code::INSUFFICIENT_SPACE.to_string(),
+    })
 }
 
 fn is_recycle_bin_saved_path(path: &str) -> bool {
     path == RECYCLE_BIN_SAVED_PATH || path.starts_with(&format!("{}/", RECYCLE_BIN_SAVED_PATH))
 }
 
-fn ensure_saved_folder_hierarchy(
+fn resolve_restored_item_name(
     db: &Database,
     owner_id: &str,
     destination_path: &str,
-    modified_date: &str,
-) -> Result<(), TelegramError> {
-    let normalized_destination = normalize_saved_path(destination_path);
-    if normalized_destination == SAVED_ROOT_PATH {
-        return Ok(());
+    source_parent_path: Option<&str>,
+    name: &str,
+    keep_extension: bool,
+) -> Result<String, TelegramError> {
+    let name_taken = |parent_path: &str, candidate: &str| -> Result<bool, TelegramError> {
+        db.telegram_saved_item_name_exists(owner_id, parent_path, candidate)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to check restore destination: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })
+    };
+
+    if !name_taken(destination_path, name)? {
+        return Ok(name.to_string());
+    }
+
+    let (stem, extension) = if keep_extension {
+        let path = Path::new(name);
+        match (
+            path.file_stem().and_then(|value| value.to_str()),
+            path.extension().and_then(|value| value.to_str()),
+        ) {
+            (Some(stem), Some(ext)) if !stem.trim().is_empty() && !ext.trim().is_empty() => {
+                (stem.to_string(), Some(ext.to_string()))
+            }
+            _ => (name.to_string(), None),
+        }
+    } else {
+        (name.to_string(), None)
+    };
+
+    let mut index = 1_i32;
+    loop {
+        let suffix = if index == 1 {
+            " (restored)".to_string()
+        } else {
+            format!(" (restored {})", index)
+        };
+        let candidate = match &extension {
+            Some(ext) => format!("{}{}.{}", stem, suffix, ext),
+            None => format!("{}{}", stem, suffix),
+        };
+
+        let taken_at_source = match source_parent_path {
+            Some(parent_path) => name_taken(parent_path, &candidate)?,
+            None => false,
+        };
+        if !taken_at_source && !name_taken(destination_path, &candidate)? {
+            return Ok(candidate);
+        }
+
+        index += 1;
+    }
+}
+
+fn ensure_saved_folder_hierarchy(
+    db: &Database,
+    owner_id: &str,
+    destination_path: &str,
+    modified_date: &str,
+) -> Result<(), TelegramError> {
+    let normalized_destination = normalize_saved_path(destination_path);
+    if normalized_destination == SAVED_ROOT_PATH {
+        return Ok(());
     }
 
     let relative = normalized_destination
@@ -901,38 +1964,68 @@ fn ensure_saved_folder_hierarchy(
             continue;
         }
 
+        let folder_item = TelegramSavedItem {
+            chat_id: 0,
+            message_id: 0,
+            thumbnail: None,
+            file_type: "folder".to_string(),
+            file_unique_id: new_folder_unique_id(),
+            file_size: 0,
+            file_name: folder_name.to_string(),
+            file_caption: Some(folder_name.to_string()),
+            file_path: parent_path.clone(),
+            recycle_origin_path: None,
+            modified_date: modified_date.to_string(),
+            owner_id: owner_id.to_string(),
+            pinned: false,
+            image_width: None,
+            image_height: None,
+            indexed_at: None,
+            audio_duration_secs: None,
+            audio_title: None,
+            audio_performer: None,
+        };
+
+        // A folder that already exists, or that a concurrent call just created, is kept
+        db.insert_telegram_saved_folder(&folder_item)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to create folder hierarchy: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+
+        parent_path = format!("{}/{}", parent_path.trim_end_matches('/'), folder_name);
+    }
+
+    Ok(())
+}
+
+fn saved_folder_hierarchy_exists(
+    db: &Database,
+    owner_id: &str,
+    folder_path: &str,
+) -> Result<bool, TelegramError> {
+    let normalized_path = normalize_saved_path(folder_path);
+    let relative = match normalized_path.strip_prefix(SAVED_ROOT_PATH) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => return Ok(false),
+    };
+
+    let mut parent_path = SAVED_ROOT_PATH.to_string();
+    for folder_name in relative.split('/').filter(|segment| !segment.is_empty()) {
         let folder_exists = db
             .telegram_saved_folder_exists(owner_id, &parent_path, folder_name)
             .map_err(|e| TelegramError {
                 message: format!("Failed to check folder hierarchy: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
-
         if !folder_exists {
-            let folder_item = TelegramSavedItem {
-                chat_id: 0,
-                message_id: 0,
-                thumbnail: None,
-                file_type: "folder".to_string(),
-                file_unique_id: build_folder_unique_id(owner_id, &parent_path, folder_name),
-                file_size: 0,
-                file_name: folder_name.to_string(),
-                file_caption: Some(folder_name.to_string()),
-                file_path: parent_path.clone(),
-                recycle_origin_path: None,
-                modified_date: modified_date.to_string(),
-                owner_id: owner_id.to_string(),
-            };
-
-            db.upsert_telegram_saved_item(&folder_item)
-                .map_err(|e| TelegramError {
-                    message: format!("Failed to create folder hierarchy: {}", e.message),
-                })?;
+            return Ok(false);
         }
 
         parent_path = format!("{}/{}", parent_path.trim_end_matches('/'), folder_name);
     }
 
-    Ok(())
+    Ok(true)
 }
 
 fn category_to_saved_path(category: &str) -> String {
@@ -946,12 +2039,10 @@ fn category_to_saved_path(category: &str) -> String {
     }
 }
 
-fn build_folder_unique_id(owner_id: &str, parent_path: &str, folder_name: &str) -> String {
-    let token = format!("{}_{}_{}", owner_id, parent_path, folder_name)
-        .chars()
-        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
-        .collect::<String>();
-    format!("folder_{}", token)
+// Generated once when the folder is created; renames and moves keep it, so two
+// folders never end up sharing an id the way path-derived ones could
+fn new_folder_unique_id() -> String {
+    format!("folder-{}", Uuid::new_v4().simple())
 }
 
 fn extension_from_name(file_name: &str) -> Option<String> {
@@ -1031,11 +2122,19 @@ fn upsert_saved_item_from_message(
         recycle_origin_path: None,
         modified_date: message.timestamp.clone(),
         owner_id: owner_id.to_string(),
+        pinned: false,
+        image_width: None,
+        image_height: None,
+        indexed_at: None,
+        audio_duration_secs: None,
+        audio_title: None,
+        audio_performer: None,
     };
 
     db.upsert_telegram_saved_item(&saved_item)
         .map_err(|e| TelegramError {
             message: format!("Failed to save item metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })
 }
 
@@ -1048,6 +2147,7 @@ fn hydrate_saved_items_from_cached_messages(
         db.count_all_indexed_messages(chat_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to count indexed messages: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
 
     if indexed_messages_count == 0 {
@@ -1059,12 +2159,14 @@ fn hydrate_saved_items_from_cached_messages(
         .count_telegram_saved_non_folder_items(owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to count saved items: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let unnamed_items = db
         .count_telegram_saved_items_with_empty_name(owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to count unnamed saved items: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let generated_without_extension = db
@@ -1074,6 +2176,7 @@ fn hydrate_saved_items_from_cached_messages(
                 "Failed to count generated names without extension: {}",
                 e.message
             ),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     if existing_items >= indexed_messages_count
@@ -1102,6 +2205,7 @@ fn hydrate_saved_items_from_cached_messages(
         .get_all_indexed_messages(chat_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to read cached telegram messages: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     if cached_messages.is_empty() {
@@ -1118,6 +2222,7 @@ fn hydrate_saved_items_from_cached_messages(
         db.get_oldest_indexed_message_id(chat_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to read oldest cached message id: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
 
     if oldest_message_id > 0 {
@@ -1127,10 +2232,12 @@ fn hydrate_saved_items_from_cached_messages(
         )
         .map_err(|e| TelegramError {
             message: format!("Failed to update backfill cursor: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
         db.set_setting(&backfill_complete_key(chat_id), "0")
             .map_err(|e| TelegramError {
                 message: format!("Failed to update backfill completion state: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
     }
 
@@ -1148,6 +2255,7 @@ async fn repair_zero_sized_image_items(
         .get_telegram_saved_zero_sized_image_message_ids(owner_id, PHOTO_SIZE_REPAIR_LIMIT)
         .map_err(|e| TelegramError {
             message: format!("Failed to read zero-size image candidates: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     if message_ids.is_empty() {
@@ -1164,6 +2272,7 @@ async fn repair_zero_sized_image_items(
         .await
         .map_err(|e| TelegramError {
             message: format!("Failed to fetch messages for size repair: {}", e),
+            code: request_error_code(&e),
         })?;
 
         for message in fetched_messages.into_iter().flatten() {
@@ -1178,11 +2287,13 @@ async fn repair_zero_sized_image_items(
             db.update_telegram_message_size(chat_id, parsed.message_id, file_size)
                 .map_err(|e| TelegramError {
                     message: format!("Failed to update telegram_messages size: {}", e.message),
+                    code: error_code::DATABASE.to_string(),
                 })?;
 
             db.update_telegram_saved_item_size(owner_id, parsed.message_id, file_size)
                 .map_err(|e| TelegramError {
                     message: format!("Failed to update telegram_saved_items size: {}", e.message),
+                    code: error_code::DATABASE.to_string(),
                 })?;
 
             repaired += 1;
@@ -1192,34 +2303,101 @@ async fn repair_zero_sized_image_items(
     Ok(repaired)
 }
 
+// Whether a fetched message matches its cached row. The thumbnail and file reference
+// are left out: the cache holds a local thumbnail path and references are refreshed on demand.
+// A user rename rewrites the cached name, extension and category, so those are skipped for it.
+fn is_unchanged_message(
+    cached: &TelegramMessage,
+    fetched: &TelegramMessage,
+    user_renamed: bool,
+) -> bool {
+    (user_renamed
+        || (cached.category == fetched.category
+            && cached.filename == fetched.filename
+            && cached.extension == fetched.extension))
+        && cached.mime_type == fetched.mime_type
+        && cached.timestamp == fetched.timestamp
+        && cached.size == fetched.size
+        && cached.text == fetched.text
+}
+
+// Saves a fetched message and its saved item unless the cached row already matches it.
+// Returns whether anything was written.
+fn save_indexed_message(
+    db: &Database,
+    owner_id: &str,
+    fetched: &TelegramMessage,
+) -> Result<bool, TelegramError> {
+    let cached = db
+        .get_telegram_message(fetched.chat_id, fetched.message_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read cached message: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+    let user_renamed = cached.is_some()
+        && db
+            .is_telegram_saved_item_user_renamed(owner_id, fetched.message_id)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to read rename state: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+
+    let message = match cached {
+        Some(cached) if is_unchanged_message(&cached, fetched, user_renamed) => return Ok(false),
+        // Keep the renamed name so a later rebuild does not bring back the original
+        Some(cached) if user_renamed => TelegramMessage {
+            category: cached.category,
+            filename: cached.filename,
+            extension: cached.extension,
+            ..fetched.clone()
+        },
+        _ => fetched.clone(),
+    };
+
+    db.save_telegram_message(&message)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to save message: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+    upsert_saved_item_from_message(db, owner_id, &message, None, None)?;
+    Ok(true)
+}
+
 pub async fn tg_index_saved_messages_impl(
+    app: AppHandle,
     db: Database,
-) -> Result<serde_json::Value, TelegramError> {
-    let state_guard = AUTH_STATE.lock().await;
-    let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-        message: "Not authorized".to_string(),
-    })?;
+    max_messages: Option<usize>,
+) -> Result<IndexSummary, TelegramError> {
+    if max_messages == Some(0) {
+        return Err(TelegramError {
+            message: "max_messages must be at least 1".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
 
-    let client = &state.client;
-    let me = run_telegram_request("tg_index_saved_messages_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
+
+    // Clone the client so other Telegram commands can run while the loop fetches
+    let client = {
+        let state_guard = AUTH_STATE.lock().await;
+        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+        state.client.clone()
+    };
 
-    let chat_id = me.raw.id();
-    let owner_id = chat_id.to_string();
     let last_id = db
         .get_last_indexed_message_id(chat_id)
         .map_err(|e| TelegramError {
             message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     db.ensure_telegram_saved_folders(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let hydrated_count = hydrate_saved_items_from_cached_messages(&db, &owner_id, chat_id)?;
@@ -1230,70 +2408,157 @@ pub async fn tg_index_saved_messages_impl(
         );
     }
 
+    // An earlier run that stopped early left a gap below what it indexed; fill it first
+    let resume_key = index_resume_key(chat_id);
+    let resume = db
+        .get_setting(&resume_key)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read index resume point: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+        .as_deref()
+        .and_then(parse_index_resume)
+        .filter(|_| last_id > 0);
+    let floor_id = resume.map_or(last_id, |(_, floor_id)| floor_id);
+
     log::info!(
         "Indexing Saved Messages for user {} starting from message ID {}",
         chat_id,
-        last_id
+        floor_id
     );
 
     // Fetch messages for Saved Messages
-    let input_peer = match &me.raw {
-        tl::enums::User::User(u) => tl::enums::InputPeer::User(tl::types::InputPeerUser {
-            user_id: u.id,
-            access_hash: u.access_hash.unwrap_or(0),
-        }),
-        _ => {
-            return Err(TelegramError {
-                message: "Invalid user type".to_string(),
-            })
-        }
-    };
     let started_from_empty_db = last_id == 0;
-    let mut messages_iter = client.iter_messages(input_peer.clone());
+    let mut messages_iter = match resume {
+        Some((offset_id, _)) => client
+            .iter_messages(input_peer.clone())
+            .offset_id(offset_id),
+        None => client.iter_messages(input_peer.clone()),
+    };
+
+    let _indexing_run_guard = IndexingRunGuard::new();
+    INDEXING_CANCEL_REQUESTED.store(false, Ordering::Release);
 
     let mut new_count = 0;
-    let mut category_counts = std::collections::HashMap::new();
+    let mut skipped_unchanged = 0usize;
+    let mut cancelled = false;
+    let mut processed_count = 0usize;
+    let mut current_category: Option<String> = None;
+    let mut category_counts = IndexedCategoryCounts::default();
     let mut min_indexed_id = 0;
+    // Lowest id whose message was fully handled (saved, unchanged or not indexable)
+    let mut min_committed_id = 0;
+    let mut limit_reached = false;
+
+    let scan_result: Result<(), TelegramError> = async {
+        loop {
+            if INDEXING_CANCEL_REQUESTED.swap(false, Ordering::AcqRel) {
+                log::info!(
+                    "tg_index_saved_messages_impl: Cancelled after {} messages",
+                    processed_count
+                );
+                cancelled = true;
+                return Ok(());
+            }
 
-    while let Some(message) = messages_iter.next().await.map_err(|e| TelegramError {
-        message: format!("Failed to fetch messages: {}", e),
-    })? {
-        if !started_from_empty_db && message.id() <= last_id {
-            break;
-        }
+            if max_messages.is_some_and(|max| processed_count >= max) {
+                limit_reached = true;
+                return Ok(());
+            }
 
-        if let Some(tg_msg) = categorize_message(&message, chat_id) {
-            db.save_telegram_message(&tg_msg)
-                .map_err(|e| TelegramError {
-                    message: format!("Failed to save message: {}", e.message),
-                })?;
+            let Some(message) = messages_iter.next().await.map_err(|e| TelegramError {
+                message: format!("Failed to fetch messages: {}", e),
+                code: request_error_code(&e),
+            })?
+            else {
+                return Ok(());
+            };
 
-            upsert_saved_item_from_message(&db, &owner_id, &tg_msg, None, None)?;
+            if !started_from_empty_db && message.id() <= floor_id {
+                return Ok(());
+            }
+
+            processed_count += 1;
+            if processed_count % INDEX_PROGRESS_EMIT_INTERVAL == 0 {
+                emit_index_progress(
+                    &app,
+                    IndexProgressPayload {
+                        processed: processed_count,
+                        new_count,
+                        current_category: current_category.clone(),
+                    },
+                );
+            }
+
+            if let Some(tg_msg) = categorize_message(&message, chat_id) {
+                if !save_indexed_message(&db, &owner_id, &tg_msg)? {
+                    skipped_unchanged += 1;
+                } else {
+                    new_count += 1;
+                    category_counts.add(&tg_msg.category);
+                    current_category = Some(tg_msg.category.clone());
+                }
 
-            new_count += 1;
-            if min_indexed_id == 0 || tg_msg.message_id < min_indexed_id {
-                min_indexed_id = tg_msg.message_id;
+                if min_indexed_id == 0 || tg_msg.message_id < min_indexed_id {
+                    min_indexed_id = tg_msg.message_id;
+                }
             }
-            *category_counts.entry(tg_msg.category.clone()).or_insert(0) += 1;
+
+            min_committed_id = message.id();
         }
     }
+    .await;
+
+    emit_index_progress(
+        &app,
+        IndexProgressPayload {
+            processed: processed_count,
+            new_count,
+            current_category,
+        },
+    );
 
+    // A failed scan already stored the newest messages, so without a resume point
+    // the next run's floor would skip everything it did not reach
+    let stopped_early = cancelled || limit_reached || scan_result.is_err();
     if started_from_empty_db {
-        db.set_setting(&backfill_complete_key(chat_id), "1")
-            .map_err(|e| TelegramError {
-                message: format!("Failed to update backfill completion state: {}", e.message),
-            })?;
+        // A first scan that stopped early leaves the rest to backfill from the cursor
+        db.set_setting(
+            &backfill_complete_key(chat_id),
+            if stopped_early { "0" } else { "1" },
+        )
+        .map_err(|e| TelegramError {
+            message: format!("Failed to update backfill completion state: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
 
         if min_indexed_id > 0 {
             db.set_setting(&backfill_cursor_key(chat_id), &min_indexed_id.to_string())
                 .map_err(|e| TelegramError {
                     message: format!("Failed to update backfill cursor: {}", e.message),
+                    code: error_code::DATABASE.to_string(),
                 })?;
         }
+    } else if stopped_early && min_committed_id > 0 {
+        db.set_setting(&resume_key, &format!("{}:{}", min_committed_id, floor_id))
+            .map_err(|e| TelegramError {
+                message: format!("Failed to update index resume point: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+    } else if resume.is_some() && !stopped_early {
+        db.set_setting(&resume_key, "").map_err(|e| TelegramError {
+            message: format!("Failed to clear index resume point: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
     }
 
-    let repaired_image_sizes =
-        repair_zero_sized_image_items(&db, client, &owner_id, chat_id, &input_peer).await?;
+    scan_result?;
+
+    let repaired_image_sizes = if stopped_early {
+        0
+    } else {
+        repair_zero_sized_image_items(&db, &client, &owner_id, chat_id, &input_peer).await?
+    };
 
     if repaired_image_sizes > 0 {
         log::info!(
@@ -1302,34 +2567,57 @@ pub async fn tg_index_saved_messages_impl(
         );
     }
 
-    Ok(json!({
-        "total_new_messages": new_count,
-        "categories": category_counts,
-        "started_from_empty_db": started_from_empty_db,
-        "repaired_image_sizes": repaired_image_sizes
-    }))
+    Ok(IndexSummary {
+        total_new_messages: new_count,
+        categories: category_counts,
+        skipped_unchanged,
+        started_from_empty_db,
+        repaired_image_sizes,
+        cancelled,
+        has_more: limit_reached,
+    })
+}
+
+pub async fn tg_get_saved_item_impl(
+    db: Database,
+    source_path: String,
+) -> Result<Option<TelegramSavedItem>, TelegramError> {
+    let (_, owner_id, _) = current_owner_context().await?;
+    let db_error = |e: crate::db::DbError| TelegramError {
+        message: format!("Database error: {}", e.message),
+        code: error_code::DATABASE.to_string(),
+    };
+
+    if let Some(message_id) = parse_message_id_from_virtual_path(&source_path) {
+        return db
+            .get_telegram_saved_file_by_message_id(&owner_id, message_id)
+            .map_err(db_error);
+    }
+
+    let saved_path = virtual_to_saved_path(&source_path).ok_or_else(|| TelegramError {
+        message: "Invalid source path".to_string(),
+        code: error_code::INVALID_INPUT.to_string(),
+    })?;
+
+    // Home itself has no row
+    let Some((parent_path, folder_name)) = split_saved_parent_and_name(&saved_path) else {
+        return Ok(None);
+    };
+
+    db.get_telegram_saved_folder(&owner_id, &parent_path, &folder_name)
+        .map_err(db_error)
 }
 
 pub async fn tg_get_indexed_saved_messages_impl(
     db: Database,
     category: String,
 ) -> Result<Vec<TelegramMessage>, TelegramError> {
-    let state_guard = AUTH_STATE.lock().await;
-    let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-        message: "Not authorized".to_string(),
-    })?;
-
-    let me = run_telegram_request("tg_get_indexed_saved_messages_impl.get_me", || async {
-        state.client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
+    let (chat_id, _, _) = current_owner_context().await?;
 
-    db.get_indexed_messages_by_category(me.raw.id(), &category)
+    db.get_indexed_messages_by_category(chat_id, &category)
         .map_err(|e| TelegramError {
             message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })
 }
 
@@ -1337,53 +2625,111 @@ pub async fn tg_list_saved_items_impl(
     db: Database,
     file_path: String,
 ) -> Result<Vec<TelegramSavedItem>, TelegramError> {
-    let state_guard = AUTH_STATE.lock().await;
-    let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-        message: "Not authorized".to_string(),
-    })?;
-
-    let me = run_telegram_request("tg_list_saved_items_impl.get_me", || async {
-        state.client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
+    let (_, owner_id, _) = current_owner_context().await?;
 
-    let owner_id = me.raw.id().to_string();
     let normalized_path = normalize_saved_path(&file_path);
 
     db.ensure_telegram_saved_folders(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     db.get_telegram_saved_items_by_path(&owner_id, &normalized_path)
         .map_err(|e| TelegramError {
             message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })
+}
+
+pub async fn tg_list_recent_saved_items_impl(
+    db: Database,
+    limit: Option<i64>,
+) -> Result<Vec<TelegramSavedItem>, TelegramError> {
+    let (_, owner_id, _) = current_owner_context().await?;
+
+    let safe_limit = limit
+        .unwrap_or(DEFAULT_BATCH_SIZE as i64)
+        .clamp(1, MAX_BATCH_SIZE as i64);
+
+    db.get_recently_modified_saved_items(&owner_id, safe_limit)
+        .map_err(|e| TelegramError {
+            message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })
 }
 
+// Upper bound for each side of a search_all query
+const SEARCH_ALL_RESULT_LIMIT: usize = 200;
+
+// The configured search root, otherwise the user's home directory
+// Walking the whole home directory would be far too slow, so without a
+// configured root the search covers the download directory
+fn get_search_root(db: &Database) -> Option<PathBuf> {
+    match db.get_setting(SEARCH_ROOT_SETTING_KEY) {
+        Ok(Some(value)) if !value.trim().is_empty() => Some(PathBuf::from(value.trim())),
+        _ => get_default_download_dir(db).ok(),
+    }
+}
+
+pub async fn search_all_impl(
+    db: Database,
+    query: String,
+) -> Result<SearchAllResults, TelegramError> {
+    let trimmed_query = query.trim().to_string();
+    if trimmed_query.is_empty() {
+        return Err(TelegramError {
+            message: "Search query cannot be empty".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
+    let search_root = get_search_root(&db);
+    let local_query = trimmed_query.clone();
+    let local_search = tokio::task::spawn_blocking(move || match search_root {
+        Some(root) => crate::fs::collect_matching_paths(
+            &root.to_string_lossy(),
+            &local_query,
+            Some(SEARCH_ALL_RESULT_LIMIT),
+            true,
+        ),
+        None => Vec::new(),
+    });
+
+    let saved_search = async {
+        let owner_id = match current_owner_context().await {
+            Ok((_, owner_id, _)) => owner_id,
+            // Logged out: only the local side has anything to search
+            Err(error) if error.code == error_code::NOT_AUTHORIZED => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+
+        db.search_telegram_saved_items(&owner_id, &trimmed_query, SEARCH_ALL_RESULT_LIMIT as i64)
+            .map_err(|e| TelegramError {
+                message: format!("Database error: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })
+    };
+
+    let (local, saved) = tokio::join!(local_search, saved_search);
+
+    Ok(SearchAllResults {
+        local: local.map_err(|e| TelegramError {
+            message: format!("Local search failed: {}", e),
+            code: error_code::INTERNAL.to_string(),
+        })?,
+        saved: saved?,
+    })
+}
+
 pub async fn tg_list_saved_items_page_impl(
     db: Database,
     file_path: String,
     offset: i64,
     limit: i64,
 ) -> Result<serde_json::Value, TelegramError> {
-    let state_guard = AUTH_STATE.lock().await;
-    let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-        message: "Not authorized".to_string(),
-    })?;
-
-    let me = run_telegram_request("tg_list_saved_items_page_impl.get_me", || async {
-        state.client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
+    let (_, owner_id, _) = current_owner_context().await?;
 
-    let owner_id = me.raw.id().to_string();
     let normalized_path = normalize_saved_path(&file_path);
     let safe_offset = offset.max(0);
     let safe_limit = limit.clamp(1, MAX_BATCH_SIZE as i64);
@@ -1391,6 +2737,7 @@ pub async fn tg_list_saved_items_page_impl(
     db.ensure_telegram_saved_folders(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let mut items = db
@@ -1402,6 +2749,7 @@ pub async fn tg_list_saved_items_page_impl(
         )
         .map_err(|e| TelegramError {
             message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let has_more = (items.len() as i64) > safe_limit;
@@ -1409,57 +2757,158 @@ pub async fn tg_list_saved_items_page_impl(
         let _ = items.pop();
     }
 
+    let total = db
+        .count_telegram_saved_items_by_path(&owner_id, &normalized_path)
+        .map_err(|e| TelegramError {
+            message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
     Ok(json!({
         "items": items,
         "has_more": has_more,
-        "next_offset": safe_offset + (items.len() as i64)
+        "next_offset": safe_offset + (items.len() as i64),
+        "total": total
     }))
 }
 
-pub async fn tg_backfill_saved_messages_batch_impl(
-    db: Database,
-    batch_size: Option<i32>,
-) -> Result<serde_json::Value, TelegramError> {
-    let state_guard = AUTH_STATE.lock().await;
-    let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-        message: "Not authorized".to_string(),
-    })?;
-
-    let client = &state.client;
-    let me = run_telegram_request("tg_backfill_saved_messages_batch_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-
-    let chat_id = me.raw.id();
-    let owner_id = chat_id.to_string();
-    let limit = clamp_batch_size(batch_size);
+// Indexes Saved Messages newer than the local index and emits `saved-item-added`
+// for each. Walking back to the last indexed id, instead of taking only the ids
+// named in an update, keeps the incremental index free of gaps.
+pub(crate) async fn sync_new_saved_messages(
+    app: &AppHandle,
+    db: &Database,
+) -> Result<usize, TelegramError> {
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
 
-    db.ensure_telegram_saved_folders(&owner_id)
+    let last_id = db
+        .get_last_indexed_message_id(chat_id)
         .map_err(|e| TelegramError {
-            message: format!("Failed to ensure default folders: {}", e.message),
+            message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
-    let complete_key = backfill_complete_key(chat_id);
-    let complete = db
-        .get_setting(&complete_key)
-        .map_err(|e| TelegramError {
-            message: format!("Failed to read backfill state: {}", e.message),
-        })?
-        .unwrap_or_default()
-        == "1";
+    // Nothing indexed yet: the first full index will pick the message up
+    if last_id == 0 {
+        return Ok(0);
+    }
 
-    if complete {
-        return Ok(json!({
-            "fetched_count": 0,
-            "indexed_count": 0,
-            "has_more": false,
-            "is_complete": true,
-            "next_offset_id": serde_json::Value::Null
-        }));
+    let client = {
+        let state_guard = AUTH_STATE.lock().await;
+        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+        state.client.clone()
+    };
+
+    let _indexing_run_guard = IndexingRunGuard::new();
+
+    let mut messages_iter = client.iter_messages(input_peer);
+    let mut new_messages = Vec::new();
+    loop {
+        let Some(message) = messages_iter.next().await.map_err(|e| TelegramError {
+            message: format!("Failed to fetch messages: {}", e),
+            code: request_error_code(&e),
+        })?
+        else {
+            break;
+        };
+
+        if message.id() <= last_id {
+            break;
+        }
+
+        // Too many to hold in memory oldest-first: the incremental index pages
+        // through them and keeps a resume point if it is interrupted
+        if new_messages.len() >= LIVE_SYNC_MAX_CATCH_UP {
+            log::info!(
+                "sync_new_saved_messages: More than {} new messages, catching up with an index run",
+                LIVE_SYNC_MAX_CATCH_UP
+            );
+            drop(new_messages);
+            drop(_indexing_run_guard);
+            let summary = tg_index_saved_messages_impl(app.clone(), db.clone(), None).await?;
+            return Ok(summary.total_new_messages);
+        }
+
+        new_messages.push(message);
+    }
+
+    let mut added_count = 0;
+    // Oldest first, so an interrupted sync never leaves a gap below the newest id
+    for message in new_messages.iter().rev() {
+        let Some(tg_msg) = categorize_message(message, chat_id) else {
+            continue;
+        };
+
+        db.save_telegram_message(&tg_msg)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to save message: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+
+        // Uploads from this device are already placed in their chosen folder
+        let already_saved = db
+            .telegram_saved_file_exists_by_message_id(&owner_id, tg_msg.message_id)
+            .unwrap_or(false);
+        if !already_saved {
+            upsert_saved_item_from_message(db, &owner_id, &tg_msg, None, None)?;
+        }
+
+        if let Err(error) = app.emit("saved-item-added", &tg_msg) {
+            log::warn!("Failed to emit saved item added event: {}", error);
+        }
+        added_count += 1;
+    }
+
+    Ok(added_count)
+}
+
+pub async fn tg_backfill_saved_messages_batch_impl(
+    db: Database,
+    batch_size: Option<i32>,
+) -> Result<BackfillSummary, TelegramError> {
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
+
+    // Clone the client so other Telegram commands can run while the loop fetches
+    let client = {
+        let state_guard = AUTH_STATE.lock().await;
+        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+        state.client.clone()
+    };
+
+    let limit = clamp_batch_size(&db, batch_size);
+
+    db.ensure_telegram_saved_folders(&owner_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    let complete_key = backfill_complete_key(chat_id);
+    let complete = db
+        .get_setting(&complete_key)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read backfill state: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+        .unwrap_or_default()
+        == "1";
+
+    if complete {
+        return Ok(BackfillSummary {
+            fetched_count: 0,
+            indexed_count: 0,
+            categories: IndexedCategoryCounts::default(),
+            has_more: false,
+            is_complete: true,
+            next_offset_id: None,
+            cancelled: false,
+        });
     }
 
     let cursor_key = backfill_cursor_key(chat_id);
@@ -1467,6 +2916,7 @@ pub async fn tg_backfill_saved_messages_batch_impl(
         .get_setting(&cursor_key)
         .map_err(|e| TelegramError {
             message: format!("Failed to read backfill cursor: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?
         .and_then(|value| value.parse::<i32>().ok())
         .unwrap_or(0);
@@ -1477,21 +2927,10 @@ pub async fn tg_backfill_saved_messages_batch_impl(
         db.get_oldest_indexed_message_id(chat_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to read oldest indexed message: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?
     };
 
-    let input_peer = match &me.raw {
-        tl::enums::User::User(u) => tl::enums::InputPeer::User(tl::types::InputPeerUser {
-            user_id: u.id,
-            access_hash: u.access_hash.unwrap_or(0),
-        }),
-        _ => {
-            return Err(TelegramError {
-                message: "Invalid user type".to_string(),
-            })
-        }
-    };
-
     let mut messages_iter = if initial_cursor > 0 {
         client.iter_messages(input_peer).offset_id(initial_cursor)
     } else {
@@ -1499,13 +2938,33 @@ pub async fn tg_backfill_saved_messages_batch_impl(
     }
     .limit(limit);
 
+    let _indexing_run_guard = IndexingRunGuard::new();
+    INDEXING_CANCEL_REQUESTED.store(false, Ordering::Release);
+
     let mut fetched_count = 0usize;
     let mut indexed_count = 0usize;
+    let mut category_counts = IndexedCategoryCounts::default();
     let mut min_message_id = initial_cursor;
+    let mut cancelled = false;
+
+    loop {
+        if INDEXING_CANCEL_REQUESTED.swap(false, Ordering::AcqRel) {
+            log::info!(
+                "tg_backfill_saved_messages_batch_impl: Cancelled after {} messages",
+                fetched_count
+            );
+            cancelled = true;
+            break;
+        }
+
+        let Some(message) = messages_iter.next().await.map_err(|e| TelegramError {
+            message: format!("Failed to fetch messages: {}", e),
+            code: request_error_code(&e),
+        })?
+        else {
+            break;
+        };
 
-    while let Some(message) = messages_iter.next().await.map_err(|e| TelegramError {
-        message: format!("Failed to fetch messages: {}", e),
-    })? {
         fetched_count += 1;
         if min_message_id == 0 || message.id() < min_message_id {
             min_message_id = message.id();
@@ -1515,10 +2974,12 @@ pub async fn tg_backfill_saved_messages_batch_impl(
             db.save_telegram_message(&tg_msg)
                 .map_err(|e| TelegramError {
                     message: format!("Failed to save message: {}", e.message),
+                    code: error_code::DATABASE.to_string(),
                 })?;
 
             upsert_saved_item_from_message(&db, &owner_id, &tg_msg, None, None)?;
             indexed_count += 1;
+            category_counts.add(&tg_msg.category);
         }
     }
 
@@ -1526,62 +2987,117 @@ pub async fn tg_backfill_saved_messages_batch_impl(
         db.set_setting(&cursor_key, &min_message_id.to_string())
             .map_err(|e| TelegramError {
                 message: format!("Failed to update backfill cursor: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
     }
 
-    let has_more = fetched_count == limit;
+    let has_more = cancelled || fetched_count == limit;
     db.set_setting(&complete_key, if has_more { "0" } else { "1" })
         .map_err(|e| TelegramError {
             message: format!("Failed to update backfill completion state: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
-    Ok(json!({
-        "fetched_count": fetched_count,
-        "indexed_count": indexed_count,
-        "has_more": has_more,
-        "is_complete": !has_more,
-        "next_offset_id": if min_message_id > 0 { serde_json::Value::from(min_message_id) } else { serde_json::Value::Null }
-    }))
+    Ok(BackfillSummary {
+        fetched_count,
+        indexed_count,
+        categories: category_counts,
+        has_more,
+        is_complete: !has_more,
+        next_offset_id: (min_message_id > 0).then_some(min_message_id),
+        cancelled,
+    })
 }
 
-pub async fn tg_rebuild_saved_items_index_impl(
+// Appends an extension to generated names like `image_<uuid>` that were
+// indexed before extensions were derived, instead of waiting for hydration
+pub async fn tg_fix_missing_extensions_impl(
     db: Database,
 ) -> Result<serde_json::Value, TelegramError> {
-    let state_guard = AUTH_STATE.lock().await;
-    let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-        message: "Not authorized".to_string(),
-    })?;
+    let (chat_id, owner_id, _) = current_owner_context().await?;
 
-    let me = run_telegram_request("tg_rebuild_saved_items_index_impl.get_me", || async {
-        state.client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
+    let items = db
+        .get_telegram_generated_names_missing_extension(&owner_id)
+        .map_err(|e| TelegramError {
+            message: format!(
+                "Failed to read generated names without extension: {}",
+                e.message
+            ),
+            code: error_code::DATABASE.to_string(),
+        })?;
 
-    let chat_id = me.raw.id();
-    let owner_id = chat_id.to_string();
+    let modified_date = chrono::Utc::now().to_rfc3339();
+    let mut fixed_count = 0usize;
+    for (message_id, file_type, file_name) in items {
+        let message = db
+            .get_telegram_message(chat_id, message_id)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to read cached telegram message: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+
+        let extension = message
+            .as_ref()
+            .and_then(|message| {
+                normalize_extension(message.extension.as_deref())
+                    .or_else(|| extension_from_mime_type(message.mime_type.as_deref()))
+            })
+            .unwrap_or_else(|| default_extension_for_file_type(&file_type).to_string());
+
+        let fixed_name = format!("{}.{}", file_name, extension);
+        db.rename_telegram_saved_file_by_message_id(
+            &owner_id,
+            message_id,
+            &fixed_name,
+            &modified_date,
+        )
+        .map_err(|e| TelegramError {
+            message: format!("Failed to rename file metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+        // Keep the name across index rebuilds, which would generate a new one
+        if message.is_some() {
+            db.update_telegram_message_filename(chat_id, message_id, &fixed_name)
+                .map_err(|e| TelegramError {
+                    message: format!("Failed to update cached message filename: {}", e.message),
+                    code: error_code::DATABASE.to_string(),
+                })?;
+        }
+        fixed_count += 1;
+    }
+
+    Ok(json!({ "fixed_count": fixed_count }))
+}
+
+pub async fn tg_rebuild_saved_items_index_impl(
+    db: Database,
+) -> Result<serde_json::Value, TelegramError> {
+    let (chat_id, owner_id, _) = current_owner_context().await?;
 
     db.ensure_telegram_saved_folders(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let indexed_messages_count =
         db.count_all_indexed_messages(chat_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to count indexed messages: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
     let saved_items_count = db
         .count_telegram_saved_non_folder_items(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to count saved items: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
     let unnamed_items_count = db
         .count_telegram_saved_items_with_empty_name(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to count unnamed saved items: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
     let generated_without_extension_count = db
         .count_telegram_generated_names_missing_extension(&owner_id)
@@ -1590,6 +3106,7 @@ pub async fn tg_rebuild_saved_items_index_impl(
                 "Failed to count generated names without extension: {}",
                 e.message
             ),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     if indexed_messages_count == 0
@@ -1607,10 +3124,39 @@ pub async fn tg_rebuild_saved_items_index_impl(
         .get_all_indexed_messages(chat_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to read cached telegram messages: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
+    let missing_extension_ids = db
+        .get_telegram_generated_names_missing_extension(&owner_id)
+        .map_err(|e| TelegramError {
+            message: format!(
+                "Failed to read generated names without extension: {}",
+                e.message
+            ),
+            code: error_code::DATABASE.to_string(),
+        })?
+        .into_iter()
+        .map(|(message_id, _, _)| message_id)
+        .collect::<HashSet<_>>();
+
     let mut upserted = 0usize;
+    let mut skipped_unchanged = 0usize;
     for message in cached_messages {
+        // An item that is already named needs no repair; rewriting it would only
+        // replace a generated name with a new one
+        let named = db
+            .get_telegram_saved_file_name_by_message_id(&owner_id, message.message_id)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to read saved file metadata: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?
+            .is_some();
+        if named && !missing_extension_ids.contains(&message.message_id) {
+            skipped_unchanged += 1;
+            continue;
+        }
+
         upsert_saved_item_from_message(&db, &owner_id, &message, None, None)?;
         upserted += 1;
     }
@@ -1619,6 +3165,7 @@ pub async fn tg_rebuild_saved_items_index_impl(
         db.get_oldest_indexed_message_id(chat_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to read oldest cached message id: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
 
     if oldest_message_id > 0 {
@@ -1628,50 +3175,74 @@ pub async fn tg_rebuild_saved_items_index_impl(
         )
         .map_err(|e| TelegramError {
             message: format!("Failed to update backfill cursor: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
         db.set_setting(&backfill_complete_key(chat_id), "0")
             .map_err(|e| TelegramError {
                 message: format!("Failed to update backfill completion state: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
     }
 
     Ok(json!({
         "upserted_count": upserted,
+        "skipped_unchanged": skipped_unchanged,
         "oldest_message_id": oldest_message_id
     }))
 }
 
-pub async fn tg_create_saved_folder_impl(
-    db: Database,
-    parent_path: String,
-    folder_name: String,
-) -> Result<TelegramSavedItem, TelegramError> {
+// Names that would break the path invariants this module relies on are refused
+fn validate_new_folder_name<'a>(
+    normalized_parent: &str,
+    folder_name: &'a str,
+) -> Result<&'a str, TelegramError> {
     let trimmed_name = folder_name.trim();
     if trimmed_name.is_empty() {
         return Err(TelegramError {
             message: "Folder name cannot be empty".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
-    let state_guard = AUTH_STATE.lock().await;
-    let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-        message: "Not authorized".to_string(),
-    })?;
+    if trimmed_name.contains(['/', '\\']) {
+        return Err(TelegramError {
+            message: "Folder name cannot contain / or \\".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
 
-    let me = run_telegram_request("tg_create_saved_folder_impl.get_me", || async {
-        state.client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
+    // Paths like /Home/Recycle Bin are special-cased throughout this module
+    if normalized_parent == SAVED_ROOT_PATH
+        && DEFAULT_SAVED_FOLDERS
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(trimmed_name))
+    {
+        return Err(TelegramError {
+            message: format!("\"{}\" is reserved for a default folder", trimmed_name),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
+    Ok(trimmed_name)
+}
+
+pub async fn tg_create_saved_folder_impl(
+    db: Database,
+    parent_path: String,
+    folder_name: String,
+) -> Result<CreatedSavedFolder, TelegramError> {
+    let normalized_parent = resolve_saved_path(&parent_path).ok_or_else(|| TelegramError {
+        message: "Parent path must stay within Home".to_string(),
+        code: error_code::INVALID_INPUT.to_string(),
     })?;
+    let trimmed_name = validate_new_folder_name(&normalized_parent, &folder_name)?;
 
-    let owner_id = me.raw.id().to_string();
-    let normalized_parent = normalize_saved_path(&parent_path);
+    let (_, owner_id, _) = current_owner_context().await?;
 
     db.ensure_telegram_saved_folders(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let folder_item = TelegramSavedItem {
@@ -1679,7 +3250,7 @@ pub async fn tg_create_saved_folder_impl(
         message_id: 0,
         thumbnail: None,
         file_type: "folder".to_string(),
-        file_unique_id: build_folder_unique_id(&owner_id, &normalized_parent, trimmed_name),
+        file_unique_id: new_folder_unique_id(),
         file_size: 0,
         file_name: trimmed_name.to_string(),
         file_caption: Some(trimmed_name.to_string()),
@@ -1687,40 +3258,51 @@ pub async fn tg_create_saved_folder_impl(
         recycle_origin_path: None,
         modified_date: chrono::Utc::now().to_rfc3339(),
         owner_id,
+        pinned: false,
+        image_width: None,
+        image_height: None,
+        indexed_at: None,
+        audio_duration_secs: None,
+        audio_title: None,
+        audio_performer: None,
     };
 
-    db.upsert_telegram_saved_item(&folder_item)
+    // The unique folder index makes the existence check and the insert one step
+    let inserted = db
+        .insert_telegram_saved_folder(&folder_item)
         .map_err(|e| TelegramError {
             message: format!("Failed to save folder metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
+    if !inserted {
+        return Err(TelegramError {
+            message: "A folder with that name already exists here".to_string(),
+            code: error_code::DESTINATION_EXISTS.to_string(),
+        });
+    }
+
+    let virtual_path = saved_to_virtual_path(&format!(
+        "{}/{}",
+        folder_item.file_path.trim_end_matches('/'),
+        folder_item.file_name
+    ));
 
-    Ok(folder_item)
+    Ok(CreatedSavedFolder {
+        item: folder_item,
+        virtual_path,
+    })
 }
 
 pub async fn tg_move_saved_item_to_recycle_bin_impl(
     db: Database,
     source_path: String,
 ) -> Result<(), TelegramError> {
-    let client = {
-        let state_guard = AUTH_STATE.lock().await;
-        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-            message: "Not authorized".to_string(),
-        })?;
-        state.client.clone()
-    };
-
-    let me = run_telegram_request("tg_move_saved_item_to_recycle_bin_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
+    let (_, owner_id, _) = current_owner_context().await?;
 
-    let owner_id = me.raw.id().to_string();
     db.ensure_telegram_saved_folders(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let modified_date = chrono::Utc::now().to_rfc3339();
@@ -1730,17 +3312,20 @@ pub async fn tg_move_saved_item_to_recycle_bin_impl(
             .get_telegram_saved_file_path_and_recycle_origin_by_message_id(&owner_id, message_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to read source file metadata: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
 
         let Some((current_file_path, _)) = file_location else {
             return Err(TelegramError {
                 message: "Source file was not found in local index".to_string(),
+                code: error_code::NOT_FOUND.to_string(),
             });
         };
 
         if is_recycle_bin_saved_path(&current_file_path) {
             return Err(TelegramError {
                 message: "Item is already in Recycle Bin".to_string(),
+                code: error_code::INVALID_INPUT.to_string(),
             });
         }
 
@@ -1752,6 +3337,7 @@ pub async fn tg_move_saved_item_to_recycle_bin_impl(
         )
         .map_err(|e| TelegramError {
             message: format!("Failed to move file to Recycle Bin: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
         return Ok(());
@@ -1759,33 +3345,39 @@ pub async fn tg_move_saved_item_to_recycle_bin_impl(
 
     let source_saved_path = virtual_to_saved_path(&source_path).ok_or_else(|| TelegramError {
         message: "Invalid source path".to_string(),
+        code: error_code::INVALID_INPUT.to_string(),
     })?;
 
     if source_saved_path == SAVED_ROOT_PATH {
         return Err(TelegramError {
             message: "Cannot move the root folder".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
     if is_recycle_bin_saved_path(&source_saved_path) {
         return Err(TelegramError {
             message: "Item is already in Recycle Bin".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
     let (source_parent_path, folder_name) = split_saved_parent_and_name(&source_saved_path)
         .ok_or_else(|| TelegramError {
             message: "Invalid source folder path".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         })?;
 
     if !db
         .telegram_saved_folder_exists(&owner_id, &source_parent_path, &folder_name)
         .map_err(|e| TelegramError {
             message: format!("Failed to check source folder: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?
     {
         return Err(TelegramError {
             message: "Source folder was not found in local index".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
         });
     }
 
@@ -1806,6 +3398,7 @@ pub async fn tg_move_saved_item_to_recycle_bin_impl(
     )
     .map_err(|e| TelegramError {
         message: format!("Failed to move folder to Recycle Bin: {}", e.message),
+        code: error_code::DATABASE.to_string(),
     })?;
 
     Ok(())
@@ -1815,26 +3408,12 @@ pub async fn tg_restore_saved_item_impl(
     db: Database,
     source_path: String,
 ) -> Result<(), TelegramError> {
-    let client = {
-        let state_guard = AUTH_STATE.lock().await;
-        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-            message: "Not authorized".to_string(),
-        })?;
-        state.client.clone()
-    };
+    let (_, owner_id, _) = current_owner_context().await?;
 
-    let me = run_telegram_request("tg_restore_saved_item_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-
-    let owner_id = me.raw.id().to_string();
     db.ensure_telegram_saved_folders(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let modified_date = chrono::Utc::now().to_rfc3339();
@@ -1844,23 +3423,44 @@ pub async fn tg_restore_saved_item_impl(
             .get_telegram_saved_file_path_and_recycle_origin_by_message_id(&owner_id, message_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to read source file metadata: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
 
         let Some((current_file_path, recycle_origin_path)) = file_location else {
             return Err(TelegramError {
                 message: "Source file was not found in local index".to_string(),
+                code: error_code::NOT_FOUND.to_string(),
             });
         };
 
         if !is_recycle_bin_saved_path(&current_file_path) {
             return Err(TelegramError {
                 message: "Only items in Recycle Bin can be restored".to_string(),
+                code: error_code::INVALID_INPUT.to_string(),
             });
         }
 
         let destination_path = recycle_origin_path.unwrap_or_else(|| SAVED_ROOT_PATH.to_string());
         ensure_saved_folder_hierarchy(&db, &owner_id, &destination_path, &modified_date)?;
 
+        let current_file_name = db
+            .get_telegram_saved_file_name_by_message_id(&owner_id, message_id)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to read source file metadata: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+        let restored_file_name = match &current_file_name {
+            Some(file_name) => Some(resolve_restored_item_name(
+                &db,
+                &owner_id,
+                &destination_path,
+                None,
+                file_name,
+                true,
+            )?),
+            None => None,
+        };
+
         db.restore_telegram_saved_file_by_message_id(
             &owner_id,
             message_id,
@@ -1869,34 +3469,55 @@ pub async fn tg_restore_saved_item_impl(
         )
         .map_err(|e| TelegramError {
             message: format!("Failed to restore file metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
+        if let Some(restored_file_name) = restored_file_name {
+            if current_file_name.as_deref() != Some(restored_file_name.as_str()) {
+                db.rename_telegram_saved_file_by_message_id(
+                    &owner_id,
+                    message_id,
+                    &restored_file_name,
+                    &modified_date,
+                )
+                .map_err(|e| TelegramError {
+                    message: format!("Failed to rename restored file metadata: {}", e.message),
+                    code: error_code::DATABASE.to_string(),
+                })?;
+            }
+        }
+
         return Ok(());
     }
 
     let source_saved_path = virtual_to_saved_path(&source_path).ok_or_else(|| TelegramError {
         message: "Invalid source path".to_string(),
+        code: error_code::INVALID_INPUT.to_string(),
     })?;
 
     if !is_recycle_bin_saved_path(&source_saved_path) {
         return Err(TelegramError {
             message: "Only folders in Recycle Bin can be restored".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
     let (source_parent_path, folder_name) = split_saved_parent_and_name(&source_saved_path)
         .ok_or_else(|| TelegramError {
             message: "Invalid source folder path".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         })?;
 
     if !db
         .telegram_saved_folder_exists(&owner_id, &source_parent_path, &folder_name)
         .map_err(|e| TelegramError {
             message: format!("Failed to check source folder: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?
     {
         return Err(TelegramError {
             message: "Source folder was not found in local index".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
         });
     }
 
@@ -1904,11 +3525,46 @@ pub async fn tg_restore_saved_item_impl(
         .get_telegram_saved_folder_recycle_origin(&owner_id, &source_parent_path, &folder_name)
         .map_err(|e| TelegramError {
             message: format!("Failed to read folder restore path: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?
         .unwrap_or_else(|| SAVED_ROOT_PATH.to_string());
 
     ensure_saved_folder_hierarchy(&db, &owner_id, &destination_parent_path, &modified_date)?;
 
+    // Rename inside Recycle Bin first so the restored tree never shares a path
+    // with the folder that now occupies the original name.
+    let restored_folder_name = resolve_restored_item_name(
+        &db,
+        &owner_id,
+        &destination_parent_path,
+        Some(&source_parent_path),
+        &folder_name,
+        false,
+    )?;
+    let (folder_name, source_saved_path) = if restored_folder_name != folder_name {
+        let renamed_source_path = format!(
+            "{}/{}",
+            source_parent_path.trim_end_matches('/'),
+            restored_folder_name
+        );
+        db.rename_telegram_saved_folder_tree(
+            &owner_id,
+            &source_parent_path,
+            &folder_name,
+            &restored_folder_name,
+            &source_saved_path,
+            &renamed_source_path,
+            &modified_date,
+        )
+        .map_err(|e| TelegramError {
+            message: format!("Failed to rename restored folder metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+        (restored_folder_name, renamed_source_path)
+    } else {
+        (folder_name, source_saved_path)
+    };
+
     let destination_folder_path = format!(
         "{}/{}",
         destination_parent_path.trim_end_matches('/'),
@@ -1926,64 +3582,161 @@ pub async fn tg_restore_saved_item_impl(
     )
     .map_err(|e| TelegramError {
         message: format!("Failed to restore folder metadata: {}", e.message),
+        code: error_code::DATABASE.to_string(),
     })?;
 
     Ok(())
 }
 
-pub async fn tg_delete_saved_item_permanently_impl(
+// Restores every top-level Recycle Bin item through the per-item restore, so
+// name clashes at the origin are resolved the same way
+pub async fn tg_restore_all_recycle_items_impl(
     db: Database,
-    source_path: String,
-) -> Result<(), TelegramError> {
-    let client = {
-        let state_guard = AUTH_STATE.lock().await;
-        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-            message: "Not authorized".to_string(),
+) -> Result<serde_json::Value, TelegramError> {
+    let (_, owner_id, _) = current_owner_context().await?;
+
+    let items = db
+        .get_telegram_saved_items_by_path(&owner_id, RECYCLE_BIN_SAVED_PATH)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read Recycle Bin items: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
-        state.client.clone()
-    };
 
-    let me = run_telegram_request("tg_delete_saved_item_permanently_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
+    let mut restored_files = 0usize;
+    let mut restored_folders = 0usize;
+    let mut restored_to_home = 0usize;
+    // One item that cannot be restored must not keep the rest in the bin
+    let mut failed = Vec::new();
 
-    let chat_id = me.raw.id();
-    let owner_id = chat_id.to_string();
-    let input_peer = match &me.raw {
-        tl::enums::User::User(user) => tl::enums::InputPeer::User(tl::types::InputPeerUser {
-            user_id: user.id,
-            access_hash: user.access_hash.unwrap_or(0),
-        }),
-        _ => {
-            return Err(TelegramError {
-                message: "Invalid user type".to_string(),
-            })
+    for item in items {
+        let is_folder = item.file_type == "folder";
+        let source_path = if is_folder {
+            saved_to_virtual_path(&format!("{}/{}", RECYCLE_BIN_SAVED_PATH, item.file_name))
+        } else {
+            format!("tg://msg/{}", item.message_id)
+        };
+
+        if let Err(error) = tg_restore_saved_item_impl(db.clone(), source_path.clone()).await {
+            log::warn!(
+                "tg_restore_all_recycle_items_impl: Failed to restore {}: {}",
+                source_path,
+                error.message
+            );
+            failed.push(json!({ "source_path": source_path, "message": error.message }));
+            continue;
         }
-    };
 
-    if let Some(message_id) = parse_message_id_from_virtual_path(&source_path) {
-        let file_location = db
-            .get_telegram_saved_file_path_and_recycle_origin_by_message_id(&owner_id, message_id)
-            .map_err(|e| TelegramError {
-                message: format!("Failed to read source file metadata: {}", e.message),
-            })?;
+        if is_folder {
+            restored_folders += 1;
+        } else {
+            restored_files += 1;
+        }
+        if item.recycle_origin_path.is_none() {
+            restored_to_home += 1;
+        }
+    }
 
-        let Some((current_file_path, _)) = file_location else {
+    Ok(json!({
+        "restored_files": restored_files,
+        "restored_folders": restored_folders,
+        "restored_to_home": restored_to_home,
+        "failed": failed
+    }))
+}
+
+// Drops a file from the local index only, for entries whose message was deleted
+// from another client; nothing is sent to Telegram
+pub async fn tg_forget_saved_item_impl(
+    db: Database,
+    source_path: String,
+) -> Result<(), TelegramError> {
+    let message_id =
+        parse_message_id_from_virtual_path(&source_path).ok_or_else(|| TelegramError {
+            message: "Only Saved Message files can be removed from the local index".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        })?;
+
+    let (chat_id, owner_id, _) = current_owner_context().await?;
+
+    if !db
+        .telegram_saved_file_exists_by_message_id(&owner_id, message_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to check source file: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+    {
+        return Err(TelegramError {
+            message: "Source file was not found in local index".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
+        });
+    }
+
+    db.delete_telegram_saved_file_by_message_id(&owner_id, message_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to delete local file metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    db.delete_telegram_messages_by_ids(chat_id, &[message_id])
+        .map_err(|e| TelegramError {
+            message: format!("Failed to delete cached telegram message: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    Ok(())
+}
+
+fn permanent_delete_result(message_ids: &[i32], dry_run: bool) -> serde_json::Value {
+    json!({
+        "message_ids": message_ids,
+        "count": message_ids.len(),
+        "dry_run": dry_run
+    })
+}
+
+// With `dry_run` nothing is deleted; the result lists what would be
+pub async fn tg_delete_saved_item_permanently_impl(
+    db: Database,
+    source_path: String,
+    dry_run: bool,
+) -> Result<serde_json::Value, TelegramError> {
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
+
+    let client = {
+        let state_guard = AUTH_STATE.lock().await;
+        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+        state.client.clone()
+    };
+
+    if let Some(message_id) = parse_message_id_from_virtual_path(&source_path) {
+        let file_location = db
+            .get_telegram_saved_file_path_and_recycle_origin_by_message_id(&owner_id, message_id)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to read source file metadata: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+
+        let Some((current_file_path, _)) = file_location else {
             return Err(TelegramError {
                 message: "Source file was not found in local index".to_string(),
+                code: error_code::NOT_FOUND.to_string(),
             });
         };
 
         if !is_recycle_bin_saved_path(&current_file_path) {
             return Err(TelegramError {
                 message: "Only items in Recycle Bin can be deleted permanently".to_string(),
+                code: error_code::INVALID_INPUT.to_string(),
             });
         }
 
+        if dry_run {
+            return Ok(permanent_delete_result(&[message_id], true));
+        }
+
         run_telegram_request(
             "tg_delete_saved_item_permanently_impl.delete_message",
             || async {
@@ -1995,44 +3748,52 @@ pub async fn tg_delete_saved_item_permanently_impl(
         .await
         .map_err(|e| TelegramError {
             message: format!("Failed to delete Telegram message: {}", e),
+            code: request_error_code(&e),
         })?;
 
         db.delete_telegram_saved_file_by_message_id(&owner_id, message_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to delete local file metadata: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
 
         db.delete_telegram_messages_by_ids(chat_id, &[message_id])
             .map_err(|e| TelegramError {
                 message: format!("Failed to delete cached telegram message: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?;
 
-        return Ok(());
+        return Ok(permanent_delete_result(&[message_id], false));
     }
 
     let source_saved_path = virtual_to_saved_path(&source_path).ok_or_else(|| TelegramError {
         message: "Invalid source path".to_string(),
+        code: error_code::INVALID_INPUT.to_string(),
     })?;
 
     if !is_recycle_bin_saved_path(&source_saved_path) {
         return Err(TelegramError {
             message: "Only folders in Recycle Bin can be deleted permanently".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
     let (source_parent_path, folder_name) = split_saved_parent_and_name(&source_saved_path)
         .ok_or_else(|| TelegramError {
             message: "Invalid source folder path".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         })?;
 
     if !db
         .telegram_saved_folder_exists(&owner_id, &source_parent_path, &folder_name)
         .map_err(|e| TelegramError {
             message: format!("Failed to check source folder: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?
     {
         return Err(TelegramError {
             message: "Source folder was not found in local index".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
         });
     }
 
@@ -2040,8 +3801,13 @@ pub async fn tg_delete_saved_item_permanently_impl(
         .get_telegram_saved_message_ids_by_folder_tree(&owner_id, &source_saved_path)
         .map_err(|e| TelegramError {
             message: format!("Failed to collect folder message ids: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
+    if dry_run {
+        return Ok(permanent_delete_result(&message_ids, true));
+    }
+
     for chunk in message_ids.chunks(TELEGRAM_DELETE_BATCH_SIZE) {
         if chunk.is_empty() {
             continue;
@@ -2054,6 +3820,7 @@ pub async fn tg_delete_saved_item_permanently_impl(
         .await
         .map_err(|e| TelegramError {
             message: format!("Failed to delete Telegram messages: {}", e),
+            code: request_error_code(&e),
         })?;
     }
 
@@ -2065,14 +3832,16 @@ pub async fn tg_delete_saved_item_permanently_impl(
     )
     .map_err(|e| TelegramError {
         message: format!("Failed to delete local folder metadata: {}", e.message),
+        code: error_code::DATABASE.to_string(),
     })?;
 
     db.delete_telegram_messages_by_ids(chat_id, &message_ids)
         .map_err(|e| TelegramError {
             message: format!("Failed to delete cached telegram messages: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
-    Ok(())
+    Ok(permanent_delete_result(&message_ids, false))
 }
 
 pub async fn tg_move_saved_item_impl(
@@ -2080,42 +3849,43 @@ pub async fn tg_move_saved_item_impl(
     source_path: String,
     destination_path: String,
 ) -> Result<(), TelegramError> {
-    let state_guard = AUTH_STATE.lock().await;
-    let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-        message: "Not authorized".to_string(),
-    })?;
-
-    let me = run_telegram_request("tg_move_saved_item_impl.get_me", || async {
-        state.client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
+    let (_, owner_id, _) = current_owner_context().await?;
 
-    let owner_id = me.raw.id().to_string();
     let normalized_destination =
         virtual_to_saved_path(&destination_path).ok_or_else(|| TelegramError {
             message: "Invalid destination path".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         })?;
 
+    if is_recycle_bin_saved_path(&normalized_destination) {
+        return Err(TelegramError {
+            message: "Use Move to Recycle Bin to delete items".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
     db.ensure_telegram_saved_folders(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let modified_date = chrono::Utc::now().to_rfc3339();
 
     if let Some(message_id) = parse_message_id_from_virtual_path(&source_path) {
-        if !db
-            .telegram_saved_file_exists_by_message_id(&owner_id, message_id)
+        let (current_path, _) = db
+            .get_telegram_saved_file_path_and_recycle_origin_by_message_id(&owner_id, message_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to check source file: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?
-        {
-            return Err(TelegramError {
+            .ok_or_else(|| TelegramError {
                 message: "Source file was not found in local index".to_string(),
-            });
+                code: error_code::NOT_FOUND.to_string(),
+            })?;
+
+        if current_path == normalized_destination {
+            return Ok(());
         }
 
         db.move_telegram_saved_file_by_message_id(
@@ -2126,6 +3896,7 @@ pub async fn tg_move_saved_item_impl(
         )
         .map_err(|e| TelegramError {
             message: format!("Failed to move file metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
         return Ok(());
@@ -2133,11 +3904,13 @@ pub async fn tg_move_saved_item_impl(
 
     let source_saved_path = virtual_to_saved_path(&source_path).ok_or_else(|| TelegramError {
         message: "Invalid source path".to_string(),
+        code: error_code::INVALID_INPUT.to_string(),
     })?;
 
     if source_saved_path == "/Home" {
         return Err(TelegramError {
             message: "Cannot move the root folder".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
@@ -2149,22 +3922,47 @@ pub async fn tg_move_saved_item_impl(
     if normalized_destination.starts_with(&destination_prefix) {
         return Err(TelegramError {
             message: "Cannot move a folder into its own child".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
     let (source_parent_path, folder_name) = split_saved_parent_and_name(&source_saved_path)
         .ok_or_else(|| TelegramError {
             message: "Invalid source folder path".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         })?;
 
     if !db
         .telegram_saved_folder_exists(&owner_id, &source_parent_path, &folder_name)
         .map_err(|e| TelegramError {
             message: format!("Failed to check source folder: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?
     {
         return Err(TelegramError {
             message: "Source folder was not found in local index".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
+        });
+    }
+
+    if source_parent_path == normalized_destination {
+        return Ok(());
+    }
+
+    // Moving the tree onto a same-named folder would merge both silently
+    if db
+        .telegram_saved_folder_exists(&owner_id, &normalized_destination, &folder_name)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to check destination folder: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+    {
+        return Err(TelegramError {
+            message: format!(
+                "A folder named \"{}\" already exists in the destination",
+                folder_name
+            ),
+            code: error_code::DESTINATION_EXISTS.to_string(),
         });
     }
 
@@ -2185,43 +3983,75 @@ pub async fn tg_move_saved_item_impl(
     )
     .map_err(|e| TelegramError {
         message: format!("Failed to move folder metadata: {}", e.message),
+        code: error_code::DATABASE.to_string(),
     })?;
 
     Ok(())
 }
 
+pub async fn tg_set_saved_item_pinned_impl(
+    db: Database,
+    source_path: String,
+    pinned: bool,
+) -> Result<(), TelegramError> {
+    let message_id =
+        parse_message_id_from_virtual_path(&source_path).ok_or_else(|| TelegramError {
+            message: "Only Saved Message files can be pinned".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        })?;
+
+    let (_, owner_id, _) = current_owner_context().await?;
+
+    if !db
+        .telegram_saved_file_exists_by_message_id(&owner_id, message_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to check source file: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+    {
+        return Err(TelegramError {
+            message: "Source file was not found in local index".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
+        });
+    }
+
+    db.set_telegram_saved_item_pinned(&owner_id, message_id, pinned)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to update pinned state: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })
+}
+
+// Renames only ever change SkyBox's metadata; the file name stored on the
+// Telegram document stays what it was at upload.
+fn rename_result(file_name: &str) -> serde_json::Value {
+    json!({
+        "file_name": file_name,
+        "telegram_file_name_updated": false
+    })
+}
+
 pub async fn tg_rename_saved_item_impl(
     db: Database,
     source_path: String,
     new_name: String,
-) -> Result<(), TelegramError> {
+) -> Result<serde_json::Value, TelegramError> {
     let trimmed_name = new_name.trim();
     if trimmed_name.is_empty() {
         return Err(TelegramError {
             message: "New name cannot be empty".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
     let normalized_name = sanitize_file_name(trimmed_name);
 
-    let state_guard = AUTH_STATE.lock().await;
-    let state = state_guard.as_ref().ok_or_else(|| TelegramError {
-        message: "Not authorized".to_string(),
-    })?;
-
-    let me = run_telegram_request("tg_rename_saved_item_impl.get_me", || async {
-        state.client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-
-    let owner_id = me.raw.id().to_string();
+    let (chat_id, owner_id, _) = current_owner_context().await?;
 
     db.ensure_telegram_saved_folders(&owner_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let modified_date = chrono::Utc::now().to_rfc3339();
@@ -2231,43 +4061,79 @@ pub async fn tg_rename_saved_item_impl(
             .telegram_saved_file_exists_by_message_id(&owner_id, message_id)
             .map_err(|e| TelegramError {
                 message: format!("Failed to check source file: {}", e.message),
+                code: error_code::DATABASE.to_string(),
             })?
         {
             return Err(TelegramError {
                 message: "Source file was not found in local index".to_string(),
+                code: error_code::NOT_FOUND.to_string(),
             });
         }
 
-        db.rename_telegram_saved_file_by_message_id(
-            &owner_id,
-            message_id,
-            &normalized_name,
-            &modified_date,
-        )
-        .map_err(|e| TelegramError {
-            message: format!("Failed to rename file metadata: {}", e.message),
-        })?;
+        // A new extension can move the file to another type (e.g. image -> document)
+        match extension_from_name(&normalized_name) {
+            Some(extension) => {
+                let classification = classify_extension(Some(extension.as_str()));
+                db.rename_telegram_saved_file_with_type_by_message_id(
+                    &owner_id,
+                    chat_id,
+                    message_id,
+                    &normalized_name,
+                    &extension,
+                    classification.file_type,
+                    classification.category,
+                    &modified_date,
+                )
+                .map_err(|e| TelegramError {
+                    message: format!("Failed to rename file metadata: {}", e.message),
+                    code: error_code::DATABASE.to_string(),
+                })?;
+            }
+            None => {
+                db.rename_telegram_saved_file_by_message_id(
+                    &owner_id,
+                    message_id,
+                    &normalized_name,
+                    &modified_date,
+                )
+                .map_err(|e| TelegramError {
+                    message: format!("Failed to rename file metadata: {}", e.message),
+                    code: error_code::DATABASE.to_string(),
+                })?;
 
-        return Ok(());
+                // Telegram only changes a document's file name attribute on re-upload, so
+                // the rename is kept in the cached message that index rebuilds read from.
+                db.update_telegram_message_filename(chat_id, message_id, &normalized_name)
+                    .map_err(|e| TelegramError {
+                        message: format!("Failed to update cached message filename: {}", e.message),
+                        code: error_code::DATABASE.to_string(),
+                    })?;
+            }
+        }
+
+        return Ok(rename_result(&normalized_name));
     }
 
     let source_saved_path = virtual_to_saved_path(&source_path).ok_or_else(|| TelegramError {
         message: "Invalid source path".to_string(),
+        code: error_code::INVALID_INPUT.to_string(),
     })?;
 
     if source_saved_path == "/Home" {
         return Err(TelegramError {
             message: "Cannot rename the root folder".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
     let (parent_path, current_folder_name) = split_saved_parent_and_name(&source_saved_path)
         .ok_or_else(|| TelegramError {
             message: "Invalid source folder path".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         })?;
 
     if current_folder_name == normalized_name {
-        return Ok(());
+        return Ok(rename_result(&normalized_name));
     }
 
     let destination_folder_path = format!(
@@ -2287,55 +4153,70 @@ pub async fn tg_rename_saved_item_impl(
     )
     .map_err(|e| TelegramError {
         message: format!("Failed to rename folder metadata: {}", e.message),
+        code: error_code::DATABASE.to_string(),
     })?;
 
-    Ok(())
+    Ok(rename_result(&normalized_name))
 }
 
 pub async fn tg_send_saved_note_message_impl(
     db: Database,
     text: String,
+    file_path: Option<String>,
 ) -> Result<TelegramMessage, TelegramError> {
     let trimmed_text = text.trim();
     if trimmed_text.is_empty() {
         return Err(TelegramError {
             message: "Message cannot be empty".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
+
+    db.ensure_telegram_saved_folders(&owner_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    // Checked before sending so a bad folder does not leave a stray note in Telegram
+    let target_path = match file_path.as_deref() {
+        Some(path) => {
+            let resolved = resolve_saved_path(path).ok_or_else(|| TelegramError {
+                message: "Destination path must stay within Home".to_string(),
+                code: error_code::INVALID_INPUT.to_string(),
+            })?;
+
+            if is_recycle_bin_saved_path(&resolved) {
+                return Err(TelegramError {
+                    message: "Notes cannot be created in Recycle Bin".to_string(),
+                    code: error_code::INVALID_INPUT.to_string(),
+                });
+            }
+
+            if !saved_folder_hierarchy_exists(&db, &owner_id, &resolved)? {
+                return Err(TelegramError {
+                    message: format!("Destination folder does not exist: {}", resolved),
+                    code: error_code::NOT_FOUND.to_string(),
+                });
+            }
+
+            resolved
+        }
+        None => format!("{}/Notes", SAVED_ROOT_PATH),
+    };
+
     let client = {
         let state_guard = AUTH_STATE.lock().await;
         let state = state_guard.as_ref().ok_or_else(|| TelegramError {
             message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
 
         state.client.clone()
     };
 
-    let me = run_telegram_request("tg_send_saved_note_message_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-
-    let owner_id = me.raw.id().to_string();
-    let chat_id = me.raw.id();
-
-    let input_peer: tl::enums::InputPeer = match &me.raw {
-        tl::enums::User::User(user) => tl::types::InputPeerUser {
-            user_id: user.id,
-            access_hash: user.access_hash.unwrap_or(0),
-        }
-        .into(),
-        _ => {
-            return Err(TelegramError {
-                message: "Unable to resolve Saved Messages peer".to_string(),
-            });
-        }
-    };
-
     let text_to_send = trimmed_text.to_string();
     let sent_message = run_telegram_request("tg_send_saved_note_message_impl.send_message", || {
         let text_to_send = text_to_send.clone();
@@ -2348,11 +4229,13 @@ pub async fn tg_send_saved_note_message_impl(
     .await
     .map_err(|e| TelegramError {
         message: format!("Failed to send note message: {}", e),
+        code: request_error_code(&e),
     })?;
 
     let mut telegram_message =
         categorize_message(&sent_message, chat_id).ok_or_else(|| TelegramError {
             message: "Failed to map sent message".to_string(),
+            code: error_code::INTERNAL.to_string(),
         })?;
 
     telegram_message.category = "Notes".to_string();
@@ -2360,14 +4243,10 @@ pub async fn tg_send_saved_note_message_impl(
     db.save_telegram_message(&telegram_message)
         .map_err(|e| TelegramError {
             message: format!("Failed to save sent note metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
-    db.ensure_telegram_saved_folders(&owner_id)
-        .map_err(|e| TelegramError {
-            message: format!("Failed to ensure default folders: {}", e.message),
-        })?;
-
-    upsert_saved_item_from_message(&db, &owner_id, &telegram_message, Some("/Home/Notes"), None)?;
+    upsert_saved_item_from_message(&db, &owner_id, &telegram_message, Some(&target_path), None)?;
 
     Ok(telegram_message)
 }
@@ -2376,69 +4255,53 @@ pub async fn tg_edit_saved_note_message_impl(
     db: Database,
     source_path: String,
     text: String,
-) -> Result<(), TelegramError> {
+) -> Result<TelegramMessage, TelegramError> {
     let message_id =
         parse_message_id_from_virtual_path(&source_path).ok_or_else(|| TelegramError {
             message: "Only note messages can be edited".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         })?;
 
     let trimmed_text = text.trim();
     if trimmed_text.is_empty() {
         return Err(TelegramError {
             message: "Message cannot be empty".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
+
     let client = {
         let state_guard = AUTH_STATE.lock().await;
         let state = state_guard.as_ref().ok_or_else(|| TelegramError {
             message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
 
         state.client.clone()
     };
 
-    let me = run_telegram_request("tg_edit_saved_note_message_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-
-    let owner_id = me.raw.id().to_string();
-    let chat_id = me.raw.id();
-
-    let cached_message = db
+    let mut cached_message = db
         .get_telegram_message(chat_id, message_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to read cached message: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?
         .ok_or_else(|| TelegramError {
             message: "Message not found in local index".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
         })?;
 
     if !cached_message.category.eq_ignore_ascii_case("Notes") {
         return Err(TelegramError {
             message: "Only Notes messages can be edited".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         });
     }
 
-    let input_peer: tl::enums::InputPeer = match &me.raw {
-        tl::enums::User::User(user) => tl::types::InputPeerUser {
-            user_id: user.id,
-            access_hash: user.access_hash.unwrap_or(0),
-        }
-        .into(),
-        _ => {
-            return Err(TelegramError {
-                message: "Unable to resolve Saved Messages peer".to_string(),
-            });
-        }
-    };
-
     let text_to_send = trimmed_text.to_string();
-    run_telegram_request("tg_edit_saved_note_message_impl.edit_message", || {
+    let edit_result = run_telegram_request("tg_edit_saved_note_message_impl.edit_message", || {
         let text_to_send = text_to_send.clone();
         async {
             client
@@ -2450,16 +4313,31 @@ pub async fn tg_edit_saved_note_message_impl(
                 .await
         }
     })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to edit note message: {}", e),
-    })?;
+    .await;
+
+    match edit_result {
+        Ok(_) => {}
+        // Telegram rejects an edit that keeps the same text; nothing to update then
+        Err(e) if e.is("MESSAGE_NOT_MODIFIED") => {
+            log::debug!(
+                "tg_edit_saved_note_message_impl: Message {} already has this text",
+                message_id
+            );
+        }
+        Err(e) => {
+            return Err(TelegramError {
+                message: format!("Failed to edit note message: {}", e),
+                code: request_error_code(&e),
+            });
+        }
+    }
 
     let modified_date = chrono::Utc::now().to_rfc3339();
 
     db.update_telegram_message_text(chat_id, message_id, trimmed_text, &modified_date)
         .map_err(|e| TelegramError {
             message: format!("Failed to update cached message text: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     db.update_telegram_saved_item_caption_by_message_id(
@@ -2470,91 +4348,23 @@ pub async fn tg_edit_saved_note_message_impl(
     )
     .map_err(|e| TelegramError {
         message: format!("Failed to update saved item caption: {}", e.message),
+        code: error_code::DATABASE.to_string(),
     })?;
 
-    Ok(())
+    cached_message.text = Some(trimmed_text.to_string());
+    cached_message.timestamp = modified_date;
+
+    Ok(cached_message)
 }
 
-async fn get_or_fetch_message_thumbnail_impl(
-    db: &Database,
-    client: &grammers_client::Client,
-    chat_id: i64,
-    input_peer: &tl::enums::InputPeer,
-    message_id: i32,
-) -> Result<Option<String>, TelegramError> {
-    match db.get_telegram_message(chat_id, message_id) {
-        Ok(Some(msg)) => {
-            if let Some(thumb) = msg.thumbnail {
-                if !thumb.is_empty() {
-                    if thumb.starts_with("data:") {
-                        if let Some(image_bytes) = decode_data_url_image_bytes(&thumb) {
-                            if let Ok(cached_path) =
-                                cache_thumbnail_bytes(chat_id, message_id, &image_bytes)
-                            {
-                                if let Err(e) = db.update_telegram_message_thumbnail(
-                                    chat_id,
-                                    message_id,
-                                    &cached_path,
-                                ) {
-                                    log::error!(
-                                        "tg_get_message_thumbnail_impl: Failed to update cached thumbnail path in telegram_messages: {}",
-                                        e.message
-                                    );
-                                }
-
-                                let owner_id = chat_id.to_string();
-                                if let Err(e) = db.update_telegram_saved_item_thumbnail(
-                                    &owner_id,
-                                    message_id,
-                                    &cached_path,
-                                ) {
-                                    log::error!(
-                                        "tg_get_message_thumbnail_impl: Failed to update cached thumbnail path in telegram_saved_items: {}",
-                                        e.message
-                                    );
-                                }
-
-                                return Ok(Some(cached_path));
-                            }
-                        }
-
-                        return Ok(Some(thumb));
-                    }
-
-                    if Path::new(&thumb).exists() {
-                        return Ok(Some(thumb));
-                    }
-                }
-            }
-        }
-        _ => {}
-    }
-
-    let mut messages = run_telegram_request(
-        "get_or_fetch_message_thumbnail_impl.get_messages_by_id",
-        || async {
-            client
-                .get_messages_by_id(input_peer.clone(), &[message_id])
-                .await
-        },
-    )
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to fetch message: {}", e),
-    })?;
-
-    let message = messages.pop().flatten().ok_or_else(|| TelegramError {
-        message: "Message not found".to_string(),
-    })?;
-
-    let media = message.media();
-    let file_location = match media {
-        Some(Media::Photo(photo)) => {
-            if let Some(tl::enums::Photo::Photo(p)) = &photo.raw.photo {
-                let smallest = p.sizes.iter().find_map(|s| match s {
-                    tl::enums::PhotoSize::Size(sz) => Some(sz.r#type.clone()),
-                    _ => None,
-                });
+fn thumbnail_file_location(message: &Message) -> Option<tl::enums::InputFileLocation> {
+    match message.media() {
+        Some(Media::Photo(photo)) => {
+            if let Some(tl::enums::Photo::Photo(p)) = &photo.raw.photo {
+                let smallest = p.sizes.iter().find_map(|s| match s {
+                    tl::enums::PhotoSize::Size(sz) => Some(sz.r#type.clone()),
+                    _ => None,
+                });
 
                 if let Some(thumb_size) = smallest {
                     Some(tl::enums::InputFileLocation::InputPhotoFileLocation(
@@ -2598,16 +4408,167 @@ async fn get_or_fetch_message_thumbnail_impl(
             }
         }
         _ => None,
-    };
+    }
+}
+
+fn is_file_reference_expired(error_message: &str) -> bool {
+    error_message
+        .to_uppercase()
+        .contains("FILE_REFERENCE_EXPIRED")
+}
+
+// Telegram invalidates file references over time; fetching the message again
+// returns a fresh one, which is also persisted for later downloads.
+async fn refresh_message_file_reference(
+    db: &Database,
+    client: &grammers_client::Client,
+    chat_id: i64,
+    input_peer: &tl::enums::InputPeer,
+    message_id: i32,
+) -> Result<Message, TelegramError> {
+    log::info!(
+        "refresh_message_file_reference: Refetching message {} for a fresh file reference",
+        message_id
+    );
+
+    let mut messages = run_telegram_request(
+        "refresh_message_file_reference.get_messages_by_id",
+        || async {
+            client
+                .get_messages_by_id(input_peer.clone(), &[message_id])
+                .await
+        },
+    )
+    .await
+    .map_err(|e| TelegramError {
+        message: format!("Failed to refetch message: {}", e),
+        code: request_error_code(&e),
+    })?;
+
+    let message = messages.pop().flatten().ok_or_else(|| TelegramError {
+        message: "Message not found".to_string(),
+        code: error_code::NOT_FOUND.to_string(),
+    })?;
+
+    if let Some(refreshed) = categorize_message(&message, chat_id) {
+        if let Err(e) = db.update_telegram_message_file_reference(
+            chat_id,
+            message_id,
+            &refreshed.file_reference,
+        ) {
+            log::warn!(
+                "refresh_message_file_reference: Failed to persist file reference for message {}: {}",
+                message_id,
+                e.message
+            );
+        }
+    }
+
+    Ok(message)
+}
+
+fn cached_message_thumbnail(db: &Database, chat_id: i64, message_id: i32) -> Option<String> {
+    match db.get_telegram_message(chat_id, message_id) {
+        Ok(Some(msg)) => {
+            if let Some(thumb) = msg.thumbnail {
+                if !thumb.is_empty() {
+                    if thumb.starts_with("data:") {
+                        if let Some(image_bytes) = decode_data_url_image_bytes(&thumb) {
+                            if let Ok(cached_path) =
+                                cache_thumbnail_bytes(chat_id, message_id, &image_bytes)
+                            {
+                                if let Err(e) = db.update_telegram_message_thumbnail(
+                                    chat_id,
+                                    message_id,
+                                    &cached_path,
+                                ) {
+                                    log::error!(
+                                        "tg_get_message_thumbnail_impl: Failed to update cached thumbnail path in telegram_messages: {}",
+                                        e.message
+                                    );
+                                }
+
+                                let owner_id = chat_id.to_string();
+                                if let Err(e) = db.update_telegram_saved_item_thumbnail(
+                                    &owner_id,
+                                    message_id,
+                                    &cached_path,
+                                ) {
+                                    log::error!(
+                                        "tg_get_message_thumbnail_impl: Failed to update cached thumbnail path in telegram_saved_items: {}",
+                                        e.message
+                                    );
+                                }
+
+                                return Some(cached_path);
+                            }
+                        }
+
+                        return Some(thumb);
+                    }
+
+                    if Path::new(&thumb).exists() {
+                        touch_cached_thumbnail(Path::new(&thumb));
+                        return Some(thumb);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+
+    None
+}
+
+async fn get_or_fetch_message_thumbnail_impl(
+    db: &Database,
+    client: &grammers_client::Client,
+    chat_id: i64,
+    input_peer: &tl::enums::InputPeer,
+    message_id: i32,
+) -> Result<Option<String>, TelegramError> {
+    if let Some(cached_path) = cached_message_thumbnail(db, chat_id, message_id) {
+        return Ok(Some(cached_path));
+    }
+
+    let mut messages = run_telegram_transfer_request(
+        "get_or_fetch_message_thumbnail_impl.get_messages_by_id",
+        || async {
+            client
+                .get_messages_by_id(input_peer.clone(), &[message_id])
+                .await
+        },
+    )
+    .await
+    .map_err(|e| TelegramError {
+        message: format!("Failed to fetch message: {}", e),
+        code: request_error_code(&e),
+    })?;
+
+    let message = messages.pop().flatten().ok_or_else(|| TelegramError {
+        message: "Message not found".to_string(),
+        code: error_code::NOT_FOUND.to_string(),
+    })?;
 
-    let location = match file_location {
-        Some(l) => l,
-        None => return Ok(None),
+    download_message_thumbnail(db, client, chat_id, input_peer, &message).await
+}
+
+async fn download_message_thumbnail(
+    db: &Database,
+    client: &grammers_client::Client,
+    chat_id: i64,
+    input_peer: &tl::enums::InputPeer,
+    message: &Message,
+) -> Result<Option<String>, TelegramError> {
+    let message_id = message.id();
+    let Some(mut location) = thumbnail_file_location(message) else {
+        return Ok(None);
     };
 
     let mut bytes = Vec::new();
     let mut offset = 0;
     let limit = 1024 * 512;
+    let mut refreshed_file_reference = false;
 
     loop {
         let request = tl::functions::upload::GetFile {
@@ -2618,7 +4579,7 @@ async fn get_or_fetch_message_thumbnail_impl(
             cdn_supported: false,
         };
 
-        match run_telegram_request(
+        match run_telegram_transfer_request(
             "get_or_fetch_message_thumbnail_impl.upload_get_file",
             || async { client.invoke(&request).await },
         )
@@ -2631,6 +4592,20 @@ async fn get_or_fetch_message_thumbnail_impl(
                 }
                 offset += f.bytes.len() as i64;
             }
+            Err(error)
+                if !refreshed_file_reference && is_file_reference_expired(&error.to_string()) =>
+            {
+                refreshed_file_reference = true;
+                let message =
+                    refresh_message_file_reference(db, client, chat_id, input_peer, message_id)
+                        .await?;
+                let Some(fresh_location) = thumbnail_file_location(&message) else {
+                    return Ok(None);
+                };
+                location = fresh_location;
+                bytes.clear();
+                offset = 0;
+            }
             _ => break,
         }
     }
@@ -2676,35 +4651,17 @@ pub async fn tg_get_message_thumbnail_impl(
         return Ok(None);
     }
 
+    let (chat_id, _, input_peer) = current_owner_context().await?;
+
     let client = {
         let state_guard = AUTH_STATE.lock().await;
         let state = state_guard.as_ref().ok_or_else(|| TelegramError {
             message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
         state.client.clone()
     };
 
-    let me = run_telegram_request("tg_get_message_thumbnail_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-    let chat_id = me.raw.id();
-
-    let input_peer = match &me.raw {
-        tl::enums::User::User(u) => tl::enums::InputPeer::User(tl::types::InputPeerUser {
-            user_id: u.id,
-            access_hash: u.access_hash.unwrap_or(0),
-        }),
-        _ => {
-            return Err(TelegramError {
-                message: "Invalid user type".to_string(),
-            })
-        }
-    };
-
     match get_or_fetch_message_thumbnail_impl(&db, &client, chat_id, &input_peer, message_id).await
     {
         Ok(result) => Ok(result),
@@ -2723,72 +4680,98 @@ pub async fn tg_get_message_thumbnail_impl(
     }
 }
 
+pub async fn tg_get_message_thumbnail_data_url_impl(
+    db: Database,
+    message_id: i32,
+) -> Result<Option<String>, TelegramError> {
+    let Some(thumbnail_path) = tg_get_message_thumbnail_impl(db, message_id).await? else {
+        return Ok(None);
+    };
+
+    let bytes = tokio::fs::read(&thumbnail_path)
+        .await
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read cached thumbnail {}: {}", thumbnail_path, e),
+            code: error_code::IO.to_string(),
+        })?;
+
+    let mime_type = match detect_thumbnail_extension(&bytes) {
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        _ => "image/jpeg",
+    };
+
+    use base64::Engine;
+    Ok(Some(format!(
+        "data:{};base64,{}",
+        mime_type,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )))
+}
+
 pub async fn tg_prefetch_message_thumbnails_impl(
     db: Database,
     message_ids: Vec<i32>,
 ) -> Result<serde_json::Value, TelegramError> {
+    let (chat_id, _, input_peer) = current_owner_context().await?;
+
     let client = {
         let state_guard = AUTH_STATE.lock().await;
         let state = state_guard.as_ref().ok_or_else(|| TelegramError {
             message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
         state.client.clone()
     };
 
-    let me = run_telegram_request("tg_prefetch_message_thumbnails_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-    let chat_id = me.raw.id();
-
-    let input_peer = match &me.raw {
-        tl::enums::User::User(u) => tl::enums::InputPeer::User(tl::types::InputPeerUser {
-            user_id: u.id,
-            access_hash: u.access_hash.unwrap_or(0),
-        }),
-        _ => {
-            return Err(TelegramError {
-                message: "Invalid user type".to_string(),
-            })
-        }
-    };
-
     let mut ids: Vec<i32> = message_ids.into_iter().filter(|id| *id > 0).collect();
     ids.sort_unstable();
     ids.dedup();
 
     let mut cached_count = 0usize;
-    let mut failed_count = 0usize;
+    let mut failed_ids: Vec<i32> = Vec::new();
     let mut deferred_count = 0usize;
 
     if is_thumbnail_flood_wait_active() {
         return Ok(json!({
             "cached_count": 0,
             "failed_count": 0,
+            "failed_ids": [],
             "deferred_count": ids.len()
         }));
     }
 
-    for (index, message_id) in ids.iter().copied().enumerate() {
+    let mut pending_ids = Vec::with_capacity(ids.len());
+    for message_id in ids {
+        if cached_message_thumbnail(&db, chat_id, message_id).is_some() {
+            cached_count += 1;
+        } else {
+            pending_ids.push(message_id);
+        }
+    }
+
+    'batches: for (batch_index, chunk) in pending_ids
+        .chunks(THUMBNAIL_PREFETCH_BATCH_SIZE)
+        .enumerate()
+    {
+        let batch_start = batch_index * THUMBNAIL_PREFETCH_BATCH_SIZE;
         if is_thumbnail_flood_wait_active() {
-            deferred_count += ids.len().saturating_sub(index);
+            deferred_count += pending_ids.len().saturating_sub(batch_start);
             break;
         }
 
-        match get_or_fetch_message_thumbnail_impl(&db, &client, chat_id, &input_peer, message_id)
-            .await
+        let batch_messages = match run_telegram_transfer_request(
+            "tg_prefetch_message_thumbnails_impl.get_messages_by_id",
+            || async { client.get_messages_by_id(input_peer.clone(), chunk).await },
+        )
+        .await
         {
-            Ok(Some(_)) => {
-                cached_count += 1;
-            }
-            Ok(None) => {}
+            Ok(messages) => messages,
             Err(error) => {
-                if let Some(wait_seconds) = parse_flood_wait_seconds(&error.message) {
+                if let Some(wait_seconds) = parse_flood_wait_seconds(&error.to_string()) {
                     set_thumbnail_flood_wait(wait_seconds);
-                    deferred_count += ids.len().saturating_sub(index + 1);
+                    deferred_count += pending_ids.len().saturating_sub(batch_start);
                     log::warn!(
                         "tg_prefetch_message_thumbnails_impl: Flood wait detected ({}s), deferring remaining thumbnail prefetches",
                         wait_seconds
@@ -2796,39 +4779,171 @@ pub async fn tg_prefetch_message_thumbnails_impl(
                     break;
                 }
 
-                failed_count += 1;
                 log::warn!(
-                    "tg_prefetch_message_thumbnails_impl: Failed to prefetch thumbnail for message {}: {}",
-                    message_id,
-                    error.message
+                    "tg_prefetch_message_thumbnails_impl: Batch fetch of {} messages failed, falling back to per-message fetch: {}",
+                    chunk.len(),
+                    error
                 );
+                Vec::new()
+            }
+        };
+
+        // Ids missing from the batch response go through the per-message path
+        let mut batch_messages = batch_messages.into_iter();
+        for (offset, message_id) in chunk.iter().copied().enumerate() {
+            let processed = batch_start + offset;
+            if is_thumbnail_flood_wait_active() {
+                deferred_count += pending_ids.len().saturating_sub(processed);
+                break 'batches;
+            }
+
+            let result = match batch_messages.next().flatten() {
+                Some(message) => {
+                    download_message_thumbnail(&db, &client, chat_id, &input_peer, &message).await
+                }
+                None => {
+                    get_or_fetch_message_thumbnail_impl(
+                        &db,
+                        &client,
+                        chat_id,
+                        &input_peer,
+                        message_id,
+                    )
+                    .await
+                }
+            };
+
+            match result {
+                Ok(Some(_)) => {
+                    cached_count += 1;
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    if let Some(wait_seconds) = parse_flood_wait_seconds(&error.message) {
+                        set_thumbnail_flood_wait(wait_seconds);
+                        deferred_count += pending_ids.len().saturating_sub(processed + 1);
+                        log::warn!(
+                            "tg_prefetch_message_thumbnails_impl: Flood wait detected ({}s), deferring remaining thumbnail prefetches",
+                            wait_seconds
+                        );
+                        break 'batches;
+                    }
+
+                    failed_ids.push(message_id);
+                    log::warn!(
+                        "tg_prefetch_message_thumbnails_impl: Failed to prefetch thumbnail for message {}: {}",
+                        message_id,
+                        error.message
+                    );
+                }
             }
-        }
 
-        tokio::time::sleep(Duration::from_millis(THUMBNAIL_PREFETCH_DELAY_MS)).await;
+            tokio::time::sleep(Duration::from_millis(THUMBNAIL_PREFETCH_DELAY_MS)).await;
+        }
     }
 
+    let evicted_count = if cached_count > 0 {
+        enforce_thumbnail_cache_limit(thumbnail_cache_limit_bytes(&db)).unwrap_or_else(|error| {
+            log::warn!(
+                "tg_prefetch_message_thumbnails_impl: Failed to enforce thumbnail cache limit: {}",
+                error.message
+            );
+            0
+        })
+    } else {
+        0
+    };
+
     Ok(json!({
         "cached_count": cached_count,
-        "failed_count": failed_count,
-        "deferred_count": deferred_count
+        "failed_count": failed_ids.len(),
+        "failed_ids": failed_ids,
+        "deferred_count": deferred_count,
+        "evicted_count": evicted_count
     }))
 }
 
-async fn download_saved_media_with_progress(
-    client: &grammers_client::Client,
-    message: &Message,
-    staged_file_path: &Path,
-    source_path: &str,
-    file_name: &str,
-    total_bytes_hint: Option<u64>,
-    app: &AppHandle,
-) -> Result<(u64, Option<u64>), TelegramError> {
-    let media = message.media().ok_or_else(|| TelegramError {
-        message: "Selected item does not contain downloadable media".to_string(),
-    })?;
-
-    let mut total_bytes = media.size().map(|value| value as u64);
+pub async fn tg_prefetch_folder_thumbnails_impl(
+    db: Database,
+    file_path: String,
+) -> Result<serde_json::Value, TelegramError> {
+    let (_, owner_id, _) = current_owner_context().await?;
+    let folder_path = normalize_saved_path(&file_path);
+
+    let (message_ids, skipped_folders) = db
+        .get_telegram_saved_message_ids_in_folder(&owner_id, &folder_path)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to list folder items: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    let mut result = tg_prefetch_message_thumbnails_impl(db, message_ids).await?;
+    result["skipped_folders"] = json!(skipped_folders);
+
+    Ok(result)
+}
+
+pub async fn tg_get_folder_preview_thumbnail_impl(
+    db: Database,
+    file_path: String,
+) -> Result<Option<String>, TelegramError> {
+    let (_, owner_id, _) = current_owner_context().await?;
+    let folder_path = normalize_saved_path(&file_path);
+    if is_recycle_bin_saved_path(&folder_path) {
+        return Ok(None);
+    }
+
+    let thumbnail = db
+        .get_first_thumbnail_in_folder(&owner_id, &folder_path)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to look up folder preview: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    // The cached file may have been evicted since it was recorded
+    Ok(thumbnail.filter(|path| Path::new(path).is_file()))
+}
+
+// Counts are keyed by the paths as passed in, so the UI can match them to its rows
+pub async fn tg_get_folder_counts_impl(
+    db: Database,
+    file_paths: Vec<String>,
+) -> Result<HashMap<String, i64>, TelegramError> {
+    let (_, owner_id, _) = current_owner_context().await?;
+
+    let mut counts = HashMap::with_capacity(file_paths.len());
+    for file_path in file_paths {
+        let Some(folder_path) = virtual_to_saved_path(&file_path) else {
+            continue;
+        };
+
+        let count = db
+            .count_items_in_folder_tree(&owner_id, &folder_path)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to count folder items: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+        counts.insert(file_path, count);
+    }
+
+    Ok(counts)
+}
+
+async fn download_saved_media_with_progress(
+    client: &grammers_client::Client,
+    message: &Message,
+    staged_file_path: &Path,
+    source_path: &str,
+    file_name: &str,
+    total_bytes_hint: Option<u64>,
+    app: &AppHandle,
+) -> Result<(u64, Option<u64>), TelegramError> {
+    let media = message.media().ok_or_else(|| TelegramError {
+        message: "Selected item does not contain downloadable media".to_string(),
+        code: error_code::NOT_FOUND.to_string(),
+    })?;
+
+    let mut total_bytes = media.size().map(|value| value as u64);
     if total_bytes.is_none() {
         total_bytes = total_bytes_hint.filter(|value| *value > 0);
     }
@@ -2841,14 +4956,14 @@ async fn download_saved_media_with_progress(
                 staged_file_path.display(),
                 e
             ),
+            code: error_code::IO.to_string(),
         })?;
 
     let mut download = client.iter_download(&media);
     let mut downloaded_bytes = 0_u64;
     let download_started_at = Instant::now();
     let mut last_emit_at = download_started_at;
-    let mut last_speed_sample_bytes = 0_u64;
-    let mut last_speed_sample_at: Option<Instant> = None;
+    let mut rate = TransferRate::new();
     let mut emitted_non_zero_speed = false;
 
     emit_download_progress(
@@ -2860,6 +4975,7 @@ async fn download_saved_media_with_progress(
             progress: 0.0,
             downloaded_bytes,
             bytes_per_second: None,
+            eta_secs: None,
             total_bytes,
             destination_path: None,
             message: None,
@@ -2877,6 +4993,7 @@ async fn download_saved_media_with_progress(
                     progress: download_progress_percent(downloaded_bytes, total_bytes),
                     downloaded_bytes,
                     bytes_per_second: None,
+                    eta_secs: None,
                     total_bytes,
                     destination_path: None,
                     message: Some("Download cancelled".to_string()),
@@ -2885,11 +5002,13 @@ async fn download_saved_media_with_progress(
 
             return Err(TelegramError {
                 message: DOWNLOAD_CANCELLED_MARKER.to_string(),
+                code: error_code::CANCELLED.to_string(),
             });
         }
 
         let maybe_chunk = download.next().await.map_err(|e| TelegramError {
             message: format!("Failed while downloading media chunks: {}", e),
+            code: request_error_code(&e),
         })?;
 
         let Some(chunk) = maybe_chunk else {
@@ -2901,30 +5020,26 @@ async fn download_saved_media_with_progress(
             .await
             .map_err(|e| TelegramError {
                 message: format!("Failed writing staged download chunk: {}", e),
+                code: error_code::IO.to_string(),
             })?;
 
         downloaded_bytes += chunk.len() as u64;
 
         let now = Instant::now();
 
-        if last_speed_sample_at.is_none() {
-            last_speed_sample_at = Some(now);
-            last_speed_sample_bytes = downloaded_bytes;
+        // The first chunk starts the average, so connection setup does not count
+        if rate.samples.is_empty() {
+            rate.record(downloaded_bytes);
             last_emit_at = now;
         }
 
         if now.saturating_duration_since(last_emit_at)
             >= Duration::from_millis(DOWNLOAD_SPEED_SAMPLE_INTERVAL_MS)
         {
-            let mut bytes_per_second = None;
-
-            if let Some(sampled_at) = last_speed_sample_at {
-                let elapsed = now.saturating_duration_since(sampled_at);
-                let delta_bytes = downloaded_bytes.saturating_sub(last_speed_sample_bytes);
-                bytes_per_second = calculate_bytes_per_second(delta_bytes, elapsed);
-                if bytes_per_second.is_some() {
-                    emitted_non_zero_speed = true;
-                }
+            rate.record(downloaded_bytes);
+            let bytes_per_second = rate.bytes_per_second();
+            if bytes_per_second.is_some() {
+                emitted_non_zero_speed = true;
             }
 
             emit_download_progress(
@@ -2936,26 +5051,20 @@ async fn download_saved_media_with_progress(
                     progress: download_progress_percent(downloaded_bytes, total_bytes),
                     downloaded_bytes,
                     bytes_per_second,
+                    eta_secs: rate.eta_secs(downloaded_bytes, total_bytes),
                     total_bytes,
                     destination_path: None,
                     message: None,
                 },
             );
 
-            last_speed_sample_bytes = downloaded_bytes;
-            last_speed_sample_at = Some(now);
             last_emit_at = now;
         }
     }
 
     let download_finished_at = Instant::now();
-    let mut final_bytes_per_second = if let Some(sampled_at) = last_speed_sample_at {
-        let elapsed = download_finished_at.saturating_duration_since(sampled_at);
-        let delta_bytes = downloaded_bytes.saturating_sub(last_speed_sample_bytes);
-        calculate_bytes_per_second(delta_bytes, elapsed)
-    } else {
-        None
-    };
+    rate.record(downloaded_bytes);
+    let mut final_bytes_per_second = rate.bytes_per_second();
 
     if final_bytes_per_second.is_some() {
         emitted_non_zero_speed = true;
@@ -2980,6 +5089,7 @@ async fn download_saved_media_with_progress(
             progress: download_progress_percent(downloaded_bytes, total_bytes),
             downloaded_bytes,
             bytes_per_second: final_bytes_per_second,
+            eta_secs: total_bytes.map(|_| 0),
             total_bytes,
             destination_path: None,
             message: None,
@@ -2988,61 +5098,133 @@ async fn download_saved_media_with_progress(
 
     staged_file.flush().await.map_err(|e| TelegramError {
         message: format!("Failed to flush staged download file: {}", e),
+        code: error_code::IO.to_string(),
     })?;
 
     Ok((downloaded_bytes, total_bytes))
 }
 
+pub async fn tg_get_backfill_status_impl(db: Database) -> Result<serde_json::Value, TelegramError> {
+    let (chat_id, _, _) = current_owner_context().await?;
+
+    let total_indexed = db
+        .count_all_indexed_messages(chat_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to count indexed messages: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    let oldest_message_id =
+        db.get_oldest_indexed_message_id(chat_id)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to read oldest indexed message: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+
+    let is_complete = db
+        .get_setting(&backfill_complete_key(chat_id))
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read backfill state: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+        .unwrap_or_default()
+        == "1";
+
+    let cursor = db
+        .get_setting(&backfill_cursor_key(chat_id))
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read backfill cursor: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+        .and_then(|value| value.parse::<i32>().ok())
+        .filter(|value| *value > 0);
+
+    Ok(json!({
+        "total_indexed": total_indexed,
+        "oldest_message_id": if oldest_message_id > 0 { Some(oldest_message_id) } else { None },
+        "is_complete": is_complete,
+        "cursor": cursor
+    }))
+}
+
+pub fn tg_cancel_indexing_impl() -> Result<bool, TelegramError> {
+    INDEXING_CANCEL_REQUESTED.store(true, Ordering::Release);
+    Ok(true)
+}
+
 pub fn tg_cancel_saved_file_download_impl(source_path: String) -> Result<bool, TelegramError> {
     request_download_cancel(&source_path);
     Ok(true)
 }
 
-pub fn tg_cancel_saved_file_upload_impl(file_name: String) -> Result<bool, TelegramError> {
-    request_upload_cancel(&file_name);
+pub fn tg_cancel_saved_file_upload_impl(
+    upload_id: Option<String>,
+    file_name: Option<String>,
+) -> Result<bool, TelegramError> {
+    if upload_id.is_none() && file_name.is_none() {
+        return Err(TelegramError {
+            message: "An upload id or file name is required".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
+    if let Some(file_name) = &file_name {
+        request_upload_cancel(file_name);
+    }
+
+    if let Ok(guard) = ACTIVE_UPLOADS.lock() {
+        for (id, upload) in guard.iter() {
+            let matches_upload = upload_id.as_deref() == Some(id.as_str())
+                || file_name.as_deref() == Some(upload.file_name.as_str());
+
+            if matches_upload {
+                request_upload_cancel(&upload.file_name);
+                upload.cancel_signal.notify_one();
+            }
+        }
+    }
+
     Ok(true)
 }
 
+fn audio_metadata_from_message(message: &Message) -> Option<AudioMetadata> {
+    let Some(Media::Document(document)) = message.media() else {
+        return None;
+    };
+
+    Some(AudioMetadata {
+        duration_secs: document.duration().map(|secs| secs.round() as i64),
+        title: document
+            .audio_title()
+            .filter(|value| !value.trim().is_empty()),
+        performer: document
+            .performer()
+            .filter(|value| !value.trim().is_empty()),
+    })
+}
+
 pub async fn tg_prepare_saved_media_preview_impl(
     app: AppHandle,
     db: Database,
     source_path: String,
-) -> Result<String, TelegramError> {
+) -> Result<MediaPreview, TelegramError> {
     let message_id =
         parse_message_id_from_virtual_path(&source_path).ok_or_else(|| TelegramError {
             message: "Only Saved Message files can be previewed".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         })?;
 
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
+
     let client = {
         let state_guard = AUTH_STATE.lock().await;
         let state = state_guard.as_ref().ok_or_else(|| TelegramError {
             message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
         state.client.clone()
     };
 
-    let me = run_telegram_request("tg_prepare_saved_media_preview_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-
-    let chat_id = me.raw.id();
-    let owner_id = chat_id.to_string();
-    let input_peer = match &me.raw {
-        tl::enums::User::User(user) => tl::enums::InputPeer::User(tl::types::InputPeerUser {
-            user_id: user.id,
-            access_hash: user.access_hash.unwrap_or(0),
-        }),
-        _ => {
-            return Err(TelegramError {
-                message: "Invalid user type".to_string(),
-            })
-        }
-    };
-
     let mut messages = run_telegram_request(
         "tg_prepare_saved_media_preview_impl.get_messages_by_id",
         || async {
@@ -3054,10 +5236,12 @@ pub async fn tg_prepare_saved_media_preview_impl(
     .await
     .map_err(|e| TelegramError {
         message: format!("Failed to fetch message for preview: {}", e),
+        code: request_error_code(&e),
     })?;
 
     let message = messages.pop().flatten().ok_or_else(|| TelegramError {
         message: "Message not found for preview".to_string(),
+        code: error_code::NOT_FOUND.to_string(),
     })?;
 
     let categorized = categorize_message(&message, chat_id);
@@ -3065,6 +5249,7 @@ pub async fn tg_prepare_saved_media_preview_impl(
         .get_telegram_saved_file_name_by_message_id(&owner_id, message_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to read saved file metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let fallback_name = categorized
@@ -3095,6 +5280,7 @@ pub async fn tg_prepare_saved_media_preview_impl(
                 cache_dir.display(),
                 e
             ),
+            code: error_code::IO.to_string(),
         })?;
 
     let cache_file_path = build_preview_cache_path(&cache_dir, message_id, &target_file_name);
@@ -3109,6 +5295,36 @@ pub async fn tg_prepare_saved_media_preview_impl(
                     .is_some_and(|mime| mime.starts_with("image/"))
         })
         .unwrap_or(false);
+    let is_audio_preview = categorized
+        .as_ref()
+        .map(|item| {
+            item.category.eq_ignore_ascii_case("Audios")
+                || item
+                    .mime_type
+                    .as_deref()
+                    .is_some_and(|mime| mime.starts_with("audio/"))
+        })
+        .unwrap_or(false);
+
+    // Read from the message itself so the player knows the length before the download ends
+    let audio = if is_audio_preview {
+        audio_metadata_from_message(&message)
+    } else {
+        None
+    };
+    if let Some(audio) = &audio {
+        db.update_telegram_saved_item_audio_metadata(
+            &owner_id,
+            message_id,
+            audio.duration_secs,
+            audio.title.as_deref(),
+            audio.performer.as_deref(),
+        )
+        .map_err(|e| TelegramError {
+            message: format!("Failed to persist audio metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+    }
 
     if let Ok(metadata) = fs::metadata(&cache_file_path) {
         if metadata.is_file() && metadata.len() > 0 {
@@ -3119,6 +5335,7 @@ pub async fn tg_prepare_saved_media_preview_impl(
                             "Failed to persist cached image preview in telegram_messages: {}",
                             e.message
                         ),
+                        code: error_code::DATABASE.to_string(),
                     })?;
 
                 db.update_telegram_saved_item_thumbnail(
@@ -3131,15 +5348,21 @@ pub async fn tg_prepare_saved_media_preview_impl(
                         "Failed to persist cached image preview in telegram_saved_items: {}",
                         e.message
                     ),
+                    code: error_code::DATABASE.to_string(),
                 })?;
             }
 
-            return Ok(cache_file_path_string);
+            return Ok(MediaPreview {
+                path: cache_file_path_string,
+                audio,
+            });
         }
     }
 
-    let has_media = match run_telegram_request(
+    let has_media = match run_telegram_request_with_policy(
         "tg_prepare_saved_media_preview_impl.download_media",
+        Duration::from_secs(TELEGRAM_TRANSFER_TIMEOUT_SECS),
+        RequestRetryPolicy::transfer(),
         || async { message.download_media(&cache_file_path).await },
     )
     .await
@@ -3151,6 +5374,7 @@ pub async fn tg_prepare_saved_media_preview_impl(
             }
             return Err(TelegramError {
                 message: format!("Failed to prepare media preview: {}", error),
+                code: request_error_code(&error),
             });
         }
     };
@@ -3162,6 +5386,7 @@ pub async fn tg_prepare_saved_media_preview_impl(
 
         return Err(TelegramError {
             message: "Selected item does not contain previewable media".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
         });
     }
 
@@ -3172,6 +5397,7 @@ pub async fn tg_prepare_saved_media_preview_impl(
                     "Failed to persist image preview in telegram_messages: {}",
                     e.message
                 ),
+                code: error_code::DATABASE.to_string(),
             })?;
 
         db.update_telegram_saved_item_thumbnail(&owner_id, message_id, &cache_file_path_string)
@@ -3180,56 +5406,237 @@ pub async fn tg_prepare_saved_media_preview_impl(
                     "Failed to persist image preview in telegram_saved_items: {}",
                     e.message
                 ),
+                code: error_code::DATABASE.to_string(),
             })?;
     }
 
-    Ok(cache_file_path_string)
+    Ok(MediaPreview {
+        path: cache_file_path_string,
+        audio,
+    })
+}
+
+// Decode a byte prefix as UTF-8, dropping a character cut off by the size limit
+fn decode_text_preview(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(error) if error.error_len().is_none() => {
+            String::from_utf8_lossy(&bytes[..error.valid_up_to()]).into_owned()
+        }
+        Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+pub async fn tg_prepare_text_preview_impl(
+    app: AppHandle,
+    db: Database,
+    source_path: String,
+    max_bytes: Option<u64>,
+) -> Result<serde_json::Value, TelegramError> {
+    let message_id =
+        parse_message_id_from_virtual_path(&source_path).ok_or_else(|| TelegramError {
+            message: "Only Saved Message files can be previewed".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        })?;
+
+    let limit = max_bytes
+        .unwrap_or(DEFAULT_TEXT_PREVIEW_BYTES)
+        .clamp(1, MAX_TEXT_PREVIEW_BYTES);
+
+    let (chat_id, _, _) = current_owner_context().await?;
+
+    let indexed_message =
+        db.get_telegram_message(chat_id, message_id)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to read cached message: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+
+    if let Some(indexed_message) = &indexed_message {
+        if !indexed_message.category.eq_ignore_ascii_case("Notes") {
+            return Err(TelegramError {
+                message: "Only text items can be previewed as text".to_string(),
+                code: error_code::INVALID_INPUT.to_string(),
+            });
+        }
+
+        // Plain notes live in the index; only attached text files need a download
+        if indexed_message.filename.is_none() {
+            let text = indexed_message.text.as_deref().unwrap_or_default();
+            let truncated = text.len() as u64 > limit;
+            let content = if truncated {
+                decode_text_preview(&text.as_bytes()[..limit as usize])
+            } else {
+                text.to_string()
+            };
+
+            return Ok(json!({
+                "content": content,
+                "truncated": truncated
+            }));
+        }
+    }
+
+    let cached_path = tg_prepare_saved_media_preview_impl(app, db, source_path)
+        .await?
+        .path;
+
+    let file = fs::File::open(&cached_path).map_err(|e| TelegramError {
+        message: format!("Failed to open text preview {}: {}", cached_path, e),
+        code: error_code::IO.to_string(),
+    })?;
+    let file_size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+
+    let mut bytes = Vec::new();
+    file.take(limit)
+        .read_to_end(&mut bytes)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read text preview {}: {}", cached_path, e),
+            code: error_code::IO.to_string(),
+        })?;
+
+    Ok(json!({
+        "content": decode_text_preview(&bytes),
+        "truncated": file_size > bytes.len() as u64
+    }))
+}
+
+// Download a message's media into `staged_path`. An expired file reference is
+// refreshed once and the download retried with the refetched message.
+#[allow(clippy::too_many_arguments)]
+async fn download_saved_message_media(
+    app: &AppHandle,
+    db: &Database,
+    client: &grammers_client::Client,
+    chat_id: i64,
+    input_peer: &tl::enums::InputPeer,
+    message: &Message,
+    staged_path: &Path,
+    source_path: &str,
+    file_name: &str,
+    total_bytes_hint: Option<u64>,
+) -> Result<(u64, Option<u64>), TelegramError> {
+    let download_result = download_saved_media_with_progress(
+        client,
+        message,
+        staged_path,
+        source_path,
+        file_name,
+        total_bytes_hint,
+        app,
+    )
+    .await;
+
+    let Err(error) = &download_result else {
+        return download_result;
+    };
+    if !is_file_reference_expired(&error.message) {
+        return download_result;
+    }
+
+    let message_id = message.id();
+    match refresh_message_file_reference(db, client, chat_id, input_peer, message_id).await {
+        Ok(refreshed_message) => {
+            download_saved_media_with_progress(
+                client,
+                &refreshed_message,
+                staged_path,
+                source_path,
+                file_name,
+                total_bytes_hint,
+                app,
+            )
+            .await
+        }
+        Err(refresh_error) => {
+            log::warn!(
+                "download_saved_message_media: Failed to refresh file reference for message {}: {}",
+                message_id,
+                refresh_error.message
+            );
+            download_result
+        }
+    }
+}
+
+// Indexed sizes are often estimates (or 0 for photos); keep the real one
+fn record_downloaded_file_size(
+    db: &Database,
+    chat_id: i64,
+    owner_id: &str,
+    message_id: i32,
+    destination_path: &Path,
+    downloaded_bytes: u64,
+) {
+    let actual_size = fs::metadata(destination_path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(downloaded_bytes) as i64;
+    if let Err(e) = db.update_telegram_message_size(chat_id, message_id, actual_size) {
+        log::warn!(
+            "record_downloaded_file_size: Failed to update telegram_messages size for message {}: {}",
+            message_id,
+            e.message
+        );
+    }
+    if let Err(e) = db.set_telegram_saved_item_file_size(owner_id, message_id, actual_size) {
+        log::warn!(
+            "record_downloaded_file_size: Failed to update telegram_saved_items size for message {}: {}",
+            message_id,
+            e.message
+        );
+    }
 }
 
 pub async fn tg_download_saved_file_impl(
     app: AppHandle,
     db: Database,
     source_path: String,
+    destination_path: Option<String>,
+    overwrite: bool,
 ) -> Result<Option<String>, TelegramError> {
     clear_download_cancel(&source_path);
 
+    let requested_destination = destination_path
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from);
+
+    // Checked before downloading so the UI can ask to overwrite without wasting a transfer
+    if let Some(destination) = &requested_destination {
+        if destination.is_dir() {
+            return Err(TelegramError {
+                message: format!("Destination is a directory: {}", destination.display()),
+                code: error_code::INVALID_INPUT.to_string(),
+            });
+        }
+
+        if destination.exists() && !overwrite {
+            return Err(TelegramError {
+                message: format!("Destination file already exists: {}", destination.display()),
+                code: error_code::DESTINATION_EXISTS.to_string(),
+            });
+        }
+    }
+
     let message_id =
         parse_message_id_from_virtual_path(&source_path).ok_or_else(|| TelegramError {
             message: "Only Saved Message files can be downloaded".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
         })?;
 
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
+
     let client = {
         let state_guard = AUTH_STATE.lock().await;
         let state = state_guard.as_ref().ok_or_else(|| TelegramError {
             message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
         state.client.clone()
     };
 
-    let me = run_telegram_request("tg_download_saved_file_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-
-    let chat_id = me.raw.id();
-    let owner_id = chat_id.to_string();
-    let input_peer = match &me.raw {
-        tl::enums::User::User(user) => tl::enums::InputPeer::User(tl::types::InputPeerUser {
-            user_id: user.id,
-            access_hash: user.access_hash.unwrap_or(0),
-        }),
-        _ => {
-            return Err(TelegramError {
-                message: "Invalid user type".to_string(),
-            })
-        }
-    };
-
     let mut messages =
-        run_telegram_request("tg_download_saved_file_impl.get_messages_by_id", || async {
+        run_telegram_transfer_request("tg_download_saved_file_impl.get_messages_by_id", || async {
             client
                 .get_messages_by_id(input_peer.clone(), &[message_id])
                 .await
@@ -3237,10 +5644,12 @@ pub async fn tg_download_saved_file_impl(
         .await
         .map_err(|e| TelegramError {
             message: format!("Failed to fetch message for download: {}", e),
+            code: request_error_code(&e),
         })?;
 
     let message = messages.pop().flatten().ok_or_else(|| TelegramError {
         message: "Message not found for download".to_string(),
+        code: error_code::NOT_FOUND.to_string(),
     })?;
 
     let categorized = categorize_message(&message, chat_id);
@@ -3248,6 +5657,7 @@ pub async fn tg_download_saved_file_impl(
         .get_telegram_saved_file_name_by_message_id(&owner_id, message_id)
         .map_err(|e| TelegramError {
             message: format!("Failed to read saved file metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     let fallback_name = categorized
@@ -3273,57 +5683,67 @@ pub async fn tg_download_saved_file_impl(
         .filter(|value| *value > 0)
         .map(|value| value as u64);
 
-    let default_downloads_dir = get_device_downloads_dir()?;
-
-    emit_download_progress(
-        &app,
-        DownloadProgressPayload {
-            source_path: source_path.clone(),
-            file_name: target_file_name.clone(),
-            stage: "selecting".to_string(),
-            progress: 0.0,
-            downloaded_bytes: 0,
-            bytes_per_second: None,
-            total_bytes: total_bytes_hint,
-            destination_path: None,
-            message: Some("Choose where to save the file".to_string()),
-        },
-    );
-
-    let selected_destination = app
-        .dialog()
-        .file()
-        .set_title("Save Download")
-        .set_directory(&default_downloads_dir)
-        .set_file_name(target_file_name.clone())
-        .blocking_save_file();
-
-    let Some(selected_destination) = selected_destination else {
-        clear_download_cancel(&source_path);
+    // The save dialog already asked before picking an existing file
+    let overwrite = overwrite || requested_destination.is_none();
+    let destination_file_path = match requested_destination {
+        Some(destination) => destination,
+        None => {
+            let default_downloads_dir = get_default_download_dir(&db)?;
 
-        emit_download_progress(
-            &app,
-            DownloadProgressPayload {
-                source_path: source_path,
-                file_name: target_file_name,
-                stage: "cancelled".to_string(),
-                progress: 0.0,
-                downloaded_bytes: 0,
-                bytes_per_second: None,
-                total_bytes: total_bytes_hint,
-                destination_path: None,
-                message: Some("Download cancelled".to_string()),
-            },
-        );
+            emit_download_progress(
+                &app,
+                DownloadProgressPayload {
+                    source_path: source_path.clone(),
+                    file_name: target_file_name.clone(),
+                    stage: "selecting".to_string(),
+                    progress: 0.0,
+                    downloaded_bytes: 0,
+                    bytes_per_second: None,
+                    eta_secs: None,
+                    total_bytes: total_bytes_hint,
+                    destination_path: None,
+                    message: Some("Choose where to save the file".to_string()),
+                },
+            );
 
-        return Ok(None);
-    };
+            let selected_destination = app
+                .dialog()
+                .file()
+                .set_title("Save Download")
+                .set_directory(&default_downloads_dir)
+                .set_file_name(target_file_name.clone())
+                .blocking_save_file();
 
-    let destination_file_path = selected_destination
-        .into_path()
-        .map_err(|_| TelegramError {
-            message: "Selected destination is not a local filesystem path".to_string(),
-        })?;
+            let Some(selected_destination) = selected_destination else {
+                clear_download_cancel(&source_path);
+
+                emit_download_progress(
+                    &app,
+                    DownloadProgressPayload {
+                        source_path: source_path,
+                        file_name: target_file_name,
+                        stage: "cancelled".to_string(),
+                        progress: 0.0,
+                        downloaded_bytes: 0,
+                        bytes_per_second: None,
+                        eta_secs: None,
+                        total_bytes: total_bytes_hint,
+                        destination_path: None,
+                        message: Some("Download cancelled".to_string()),
+                    },
+                );
+
+                return Ok(None);
+            };
+
+            selected_destination
+                .into_path()
+                .map_err(|_| TelegramError {
+                    message: "Selected destination is not a local filesystem path".to_string(),
+                    code: error_code::INVALID_INPUT.to_string(),
+                })?
+        }
+    };
 
     if let Some(parent_dir) = destination_file_path.parent() {
         fs::create_dir_all(parent_dir).map_err(|e| TelegramError {
@@ -3332,20 +5752,30 @@ pub async fn tg_download_saved_file_impl(
                 parent_dir.display(),
                 e
             ),
+            code: error_code::IO.to_string(),
         })?;
     }
 
     let staging_dir = get_download_staging_dir()?;
+    if let Some(total_bytes) = total_bytes_hint {
+        ensure_free_space(&staging_dir, total_bytes)?;
+        if let Some(parent_dir) = destination_file_path.parent() {
+            ensure_free_space(parent_dir, total_bytes)?;
+        }
+    }
     let staged_file_path = build_unique_file_path(&staging_dir, &target_file_name);
 
-    let download_result = download_saved_media_with_progress(
+    let download_result = download_saved_message_media(
+        &app,
+        &db,
         &client,
+        chat_id,
+        &input_peer,
         &message,
         &staged_file_path,
         &source_path,
         &target_file_name,
         total_bytes_hint,
-        &app,
     )
     .await;
 
@@ -3372,6 +5802,7 @@ pub async fn tg_download_saved_file_impl(
                     progress: 0.0,
                     downloaded_bytes: 0,
                     bytes_per_second: None,
+                    eta_secs: None,
                     total_bytes: total_bytes_hint,
                     destination_path: None,
                     message: Some(error.message.clone()),
@@ -3391,6 +5822,7 @@ pub async fn tg_download_saved_file_impl(
 
         let error = TelegramError {
             message: "Selected item does not contain downloadable media".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
         };
 
         emit_download_progress(
@@ -3402,6 +5834,7 @@ pub async fn tg_download_saved_file_impl(
                 progress: 0.0,
                 downloaded_bytes: 0,
                 bytes_per_second: None,
+                eta_secs: None,
                 total_bytes,
                 destination_path: None,
                 message: Some(error.message.clone()),
@@ -3423,123 +5856,1049 @@ pub async fn tg_download_saved_file_impl(
             progress: moving_progress,
             downloaded_bytes,
             bytes_per_second: None,
+            eta_secs: None,
             total_bytes,
             destination_path: Some(destination_file_path.to_string_lossy().replace('\\', "/")),
             message: Some("Moving file to your selected location".to_string()),
         },
     );
 
-    if let Err(error) = move_staged_download(&staged_file_path, &destination_file_path) {
-        clear_download_cancel(&source_path);
+    if let Err(error) = move_staged_download(&staged_file_path, &destination_file_path, overwrite) {
+        clear_download_cancel(&source_path);
+        if staged_file_path.exists() {
+            let _ = fs::remove_file(&staged_file_path);
+        }
+
+        emit_download_progress(
+            &app,
+            DownloadProgressPayload {
+                source_path: source_path.clone(),
+                file_name: target_file_name.clone(),
+                stage: "failed".to_string(),
+                progress: moving_progress,
+                downloaded_bytes,
+                bytes_per_second: None,
+                eta_secs: None,
+                total_bytes,
+                destination_path: Some(destination_file_path.to_string_lossy().replace('\\', "/")),
+                message: Some(error.message.clone()),
+            },
+        );
+
+        return Err(error);
+    }
+
+    let destination_path_string = destination_file_path.to_string_lossy().replace('\\', "/");
+
+    record_downloaded_file_size(
+        &db,
+        chat_id,
+        &owner_id,
+        message_id,
+        &destination_file_path,
+        downloaded_bytes,
+    );
+
+    clear_download_cancel(&source_path);
+
+    emit_download_progress(
+        &app,
+        DownloadProgressPayload {
+            source_path,
+            file_name: target_file_name,
+            stage: "completed".to_string(),
+            progress: 100.0,
+            downloaded_bytes,
+            bytes_per_second: None,
+            eta_secs: None,
+            total_bytes,
+            destination_path: Some(destination_path_string.clone()),
+            message: Some("Download complete".to_string()),
+        },
+    );
+
+    Ok(Some(destination_path_string))
+}
+
+pub async fn tg_download_saved_folder_impl(
+    app: AppHandle,
+    db: Database,
+    source_path: String,
+    destination_dir: String,
+) -> Result<serde_json::Value, TelegramError> {
+    clear_download_cancel(&source_path);
+
+    let source_saved_path = virtual_to_saved_path(&source_path).ok_or_else(|| TelegramError {
+        message: "Only Saved Message folders can be downloaded".to_string(),
+        code: error_code::INVALID_INPUT.to_string(),
+    })?;
+
+    let (source_parent_path, folder_name) = split_saved_parent_and_name(&source_saved_path)
+        .ok_or_else(|| TelegramError {
+            message: "Invalid source folder path".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        })?;
+
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
+
+    let client = {
+        let state_guard = AUTH_STATE.lock().await;
+        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+        state.client.clone()
+    };
+
+    if !db
+        .telegram_saved_folder_exists(&owner_id, &source_parent_path, &folder_name)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to check source folder: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+    {
+        return Err(TelegramError {
+            message: "Source folder was not found in local index".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
+        });
+    }
+
+    let message_ids = db
+        .get_telegram_saved_message_ids_by_folder_tree(&owner_id, &source_saved_path)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to collect folder message ids: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    let destination_root = build_unique_file_path(Path::new(&destination_dir), &folder_name);
+    fs::create_dir_all(&destination_root).map_err(|e| TelegramError {
+        message: format!(
+            "Failed to create destination folder {}: {}",
+            destination_root.display(),
+            e
+        ),
+        code: error_code::IO.to_string(),
+    })?;
+
+    let staging_dir = get_download_staging_dir()?;
+    let total = message_ids.len();
+    let mut completed = 0usize;
+    let mut failed = 0usize;
+    let mut cancelled = false;
+
+    emit_folder_download_progress(
+        &app,
+        FolderDownloadProgressPayload {
+            source_path: source_path.clone(),
+            completed,
+            total,
+            failed,
+            current_file_name: None,
+        },
+    );
+
+    'batches: for chunk in message_ids.chunks(TELEGRAM_DELETE_BATCH_SIZE) {
+        let messages = match run_telegram_transfer_request(
+            "tg_download_saved_folder_impl.get_messages_by_id",
+            || async { client.get_messages_by_id(input_peer.clone(), chunk).await },
+        )
+        .await
+        {
+            Ok(messages) => messages,
+            // Count the chunk as failed and go on, so the files already written are reported
+            Err(error) => {
+                failed += chunk.len();
+                log::warn!(
+                    "tg_download_saved_folder_impl: Failed to fetch {} folder messages: {}",
+                    chunk.len(),
+                    error
+                );
+                emit_folder_download_progress(
+                    &app,
+                    FolderDownloadProgressPayload {
+                        source_path: source_path.clone(),
+                        completed,
+                        total,
+                        failed,
+                        current_file_name: None,
+                    },
+                );
+                continue;
+            }
+        };
+
+        for (message_id, message) in chunk.iter().copied().zip(messages) {
+            if is_download_cancel_requested(&source_path) {
+                cancelled = true;
+                break 'batches;
+            }
+
+            let result = download_folder_entry(
+                &app,
+                &db,
+                &client,
+                &owner_id,
+                chat_id,
+                &input_peer,
+                message_id,
+                message,
+                &source_saved_path,
+                &destination_root,
+                &staging_dir,
+            )
+            .await;
+
+            let current_file_name = match result {
+                Ok(file_name) => {
+                    completed += 1;
+                    Some(file_name)
+                }
+                Err(error) => {
+                    failed += 1;
+                    log::warn!(
+                        "tg_download_saved_folder_impl: Failed to download message {}: {}",
+                        message_id,
+                        error.message
+                    );
+                    None
+                }
+            };
+
+            emit_folder_download_progress(
+                &app,
+                FolderDownloadProgressPayload {
+                    source_path: source_path.clone(),
+                    completed,
+                    total,
+                    failed,
+                    current_file_name,
+                },
+            );
+        }
+    }
+
+    clear_download_cancel(&source_path);
+
+    Ok(json!({
+        "destination_path": destination_root.to_string_lossy().replace('\\', "/"),
+        "total": total,
+        "downloaded_count": completed,
+        "failed_count": failed,
+        "cancelled": cancelled
+    }))
+}
+
+// Download one file of a folder download into the directory matching its
+// saved-folder location below the downloaded folder. Returns the file name used.
+#[allow(clippy::too_many_arguments)]
+async fn download_folder_entry(
+    app: &AppHandle,
+    db: &Database,
+    client: &grammers_client::Client,
+    owner_id: &str,
+    chat_id: i64,
+    input_peer: &tl::enums::InputPeer,
+    message_id: i32,
+    message: Option<Message>,
+    source_saved_path: &str,
+    destination_root: &Path,
+    staging_dir: &Path,
+) -> Result<String, TelegramError> {
+    let message = message.ok_or_else(|| TelegramError {
+        message: "Message no longer exists in Saved Messages".to_string(),
+        code: error_code::NOT_FOUND.to_string(),
+    })?;
+
+    let (file_path, _) = db
+        .get_telegram_saved_file_path_and_recycle_origin_by_message_id(owner_id, message_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read saved file path: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+        .ok_or_else(|| TelegramError {
+            message: "Saved file was not found in local index".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
+        })?;
+
+    let mut target_dir = destination_root.to_path_buf();
+    for segment in file_path
+        .strip_prefix(source_saved_path)
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.trim().is_empty())
+    {
+        // Never let a stored folder name step outside the destination
+        if segment == "." || segment == ".." {
+            target_dir.push("_");
+        } else {
+            target_dir.push(sanitize_file_name(segment));
+        }
+    }
+
+    fs::create_dir_all(&target_dir).map_err(|e| TelegramError {
+        message: format!(
+            "Failed to create destination folder {}: {}",
+            target_dir.display(),
+            e
+        ),
+        code: error_code::IO.to_string(),
+    })?;
+
+    let categorized = categorize_message(&message, chat_id);
+    let file_name = db
+        .get_telegram_saved_file_name_by_message_id(owner_id, message_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to read saved file metadata: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+        .filter(|value| !value.trim().is_empty())
+        .or_else(|| {
+            categorized
+                .as_ref()
+                .and_then(|item| item.filename.clone())
+                .filter(|value| !value.trim().is_empty())
+        })
+        .unwrap_or_else(|| {
+            let ext = categorized
+                .as_ref()
+                .and_then(|item| item.extension.clone())
+                .filter(|value| !value.trim().is_empty())
+                .unwrap_or_else(|| "bin".to_string());
+            format!("message_{}.{}", message_id, ext)
+        });
+
+    let total_bytes_hint = categorized
+        .as_ref()
+        .and_then(|item| item.size)
+        .filter(|value| *value > 0)
+        .map(|value| value as u64);
+
+    let file_source_path = format!("tg://msg/{}", message_id);
+    let staged_file_path = build_unique_file_path(staging_dir, &file_name);
+
+    let download_result = download_saved_message_media(
+        app,
+        db,
+        client,
+        chat_id,
+        input_peer,
+        &message,
+        &staged_file_path,
+        &file_source_path,
+        &file_name,
+        total_bytes_hint,
+    )
+    .await;
+
+    let downloaded_bytes = match download_result {
+        Ok((downloaded_bytes, _)) => downloaded_bytes,
+        Err(error) => {
+            if staged_file_path.exists() {
+                let _ = fs::remove_file(&staged_file_path);
+            }
+            return Err(error);
+        }
+    };
+
+    if downloaded_bytes == 0 {
+        if staged_file_path.exists() {
+            let _ = fs::remove_file(&staged_file_path);
+        }
+        return Err(TelegramError {
+            message: "Selected item does not contain downloadable media".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
+        });
+    }
+
+    let destination_file_path = build_unique_file_path(&target_dir, &file_name);
+    if let Err(error) = move_staged_download(&staged_file_path, &destination_file_path, false) {
+        if staged_file_path.exists() {
+            let _ = fs::remove_file(&staged_file_path);
+        }
+        return Err(error);
+    }
+    record_downloaded_file_size(
+        db,
+        chat_id,
+        owner_id,
+        message_id,
+        &destination_file_path,
+        downloaded_bytes,
+    );
+
+    Ok(file_name)
+}
+
+// How often the pending upload queue checks whether the device is back online
+const PENDING_UPLOAD_POLL_INTERVAL_SECS: u64 = 30;
+// Failed uploads are retried with a doubling delay until this many attempts were made
+const PENDING_UPLOAD_MAX_ATTEMPTS: i64 = 5;
+
+// Telegram only rejects an oversized file after every part was sent, so check
+// before anything is copied, queued or uploaded
+async fn ensure_upload_within_limit(
+    file_name: &str,
+    file_size: u64,
+    online: bool,
+) -> Result<(), TelegramError> {
+    let max_bytes = upload_size_limit(online).await;
+    if file_size <= max_bytes {
+        return Ok(());
+    }
+
+    Err(TelegramError {
+        message: format!(
+            "{} is too large to upload: {} bytes ({:.1} MB), the limit for this account is {} bytes ({} MB)",
+            file_name,
+            file_size,
+            file_size as f64 / (1024.0 * 1024.0),
+            max_bytes,
+            max_bytes / (1024 * 1024)
+        ),
+        code: error_code::FILE_TOO_LARGE.to_string(),
+    })
+}
+
+pub async fn tg_upload_file_to_saved_messages_impl(
+    app: AppHandle,
+    db: Database,
+    file_name: String,
+    file_bytes: Vec<u8>,
+    file_path: Option<String>,
+    on_conflict: Option<String>,
+) -> Result<TelegramMessage, TelegramError> {
+    let conflict_mode = parse_upload_conflict_mode(on_conflict.as_deref())?;
+    if file_bytes.is_empty() {
+        return Err(TelegramError {
+            message: "Cannot upload an empty file".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
+    let connectivity = ensure_basic_connectivity().await;
+    ensure_upload_within_limit(&file_name, file_bytes.len() as u64, connectivity.is_ok()).await?;
+
+    if let Err(error) = connectivity {
+        log::info!(
+            "tg_upload_file_to_saved_messages_impl: Offline ({}), queueing {}",
+            error.message,
+            file_name
+        );
+        return enqueue_pending_upload(
+            &db,
+            &file_name,
+            &file_bytes,
+            file_path.as_deref(),
+            conflict_mode,
+        )
+        .await;
+    }
+
+    upload_file_to_saved_messages(
+        app,
+        db,
+        file_name,
+        UploadSource::Bytes(file_bytes),
+        file_path,
+        conflict_mode,
+    )
+    .await
+}
+
+pub async fn tg_upload_local_file_impl(
+    app: AppHandle,
+    db: Database,
+    local_path: String,
+    file_path: Option<String>,
+    on_conflict: Option<String>,
+) -> Result<TelegramMessage, TelegramError> {
+    let conflict_mode = parse_upload_conflict_mode(on_conflict.as_deref())?;
+    let local_path = PathBuf::from(crate::fs::normalize_local_path(&local_path));
+    let metadata = fs::metadata(&local_path).map_err(|e| TelegramError {
+        message: format!("Failed to read {}: {}", local_path.display(), e),
+        code: if e.kind() == std::io::ErrorKind::NotFound {
+            error_code::NOT_FOUND.to_string()
+        } else {
+            error_code::IO.to_string()
+        },
+    })?;
+
+    if !metadata.is_file() {
+        return Err(TelegramError {
+            message: format!("Not a file: {}", local_path.display()),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
+    if metadata.len() == 0 {
+        return Err(TelegramError {
+            message: "Cannot upload an empty file".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
+    let file_name = local_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| TelegramError {
+            message: format!("Cannot derive a file name from {}", local_path.display()),
+            code: error_code::INVALID_INPUT.to_string(),
+        })?;
+
+    let connectivity = ensure_basic_connectivity().await;
+    ensure_upload_within_limit(&file_name, metadata.len(), connectivity.is_ok()).await?;
+
+    if let Err(error) = connectivity {
+        log::info!(
+            "tg_upload_local_file_impl: Offline ({}), queueing {}",
+            error.message,
+            file_name
+        );
+        // Queued uploads keep their own copy, so later edits to the file don't change them
+        let file_bytes = fs::read(&local_path).map_err(|e| TelegramError {
+            message: format!("Failed to read {}: {}", local_path.display(), e),
+            code: error_code::IO.to_string(),
+        })?;
+        return enqueue_pending_upload(
+            &db,
+            &file_name,
+            &file_bytes,
+            file_path.as_deref(),
+            conflict_mode,
+        )
+        .await;
+    }
+
+    upload_file_to_saved_messages(
+        app,
+        db,
+        file_name,
+        UploadSource::LocalFile(local_path),
+        file_path,
+        conflict_mode,
+    )
+    .await
+}
+
+// Owner of the current session without talking to Telegram, which is unreachable here
+async fn offline_owner_id(db: &Database) -> Result<String, TelegramError> {
+    // The stored session knows the owner even before the client has connected
+    if let Some(user_id) = db
+        .get_session()
+        .ok()
+        .flatten()
+        .and_then(|session| session.user_id)
+    {
+        return Ok(user_id.to_string());
+    }
+
+    let state_guard = AUTH_STATE.lock().await;
+    state_guard
+        .as_ref()
+        .and_then(|state| state.owner_context.as_ref())
+        .map(|(chat_id, _)| chat_id.to_string())
+        .ok_or_else(|| TelegramError {
+            message: "Cannot queue the upload before the account has been resolved".to_string(),
+            code: error_code::OFFLINE.to_string(),
+        })
+}
+
+async fn enqueue_pending_upload(
+    db: &Database,
+    file_name: &str,
+    file_bytes: &[u8],
+    file_path: Option<&str>,
+    conflict_mode: Option<UploadConflictMode>,
+) -> Result<TelegramMessage, TelegramError> {
+    let owner_id = offline_owner_id(db).await?;
+
+    if let Some(target_path) = file_path {
+        if resolve_saved_path(target_path).is_none() {
+            return Err(TelegramError {
+                message: "Destination path must stay within Home".to_string(),
+                code: error_code::INVALID_INPUT.to_string(),
+            });
+        }
+
+        if !saved_folder_hierarchy_exists(db, &owner_id, target_path)? {
+            return Err(TelegramError {
+                message: format!(
+                    "Destination folder does not exist: {}",
+                    normalize_saved_path(target_path)
+                ),
+                code: error_code::NOT_FOUND.to_string(),
+            });
+        }
+    }
+
+    let (upload_file_name, upload_extension) = build_upload_file_name(file_name);
+
+    // Reported now so the UI can ask; a free "(n)" name is picked when the upload is sent
+    if conflict_mode == Some(UploadConflictMode::Error) {
+        resolve_upload_conflict_name(
+            db,
+            &owner_id,
+            &upload_target_folder(file_path, upload_extension.as_deref()),
+            file_name,
+            upload_extension.as_deref(),
+            UploadConflictMode::Error,
+        )?;
+    }
+
+    let pending_dir = get_skybox_data_dir()?.join(".pending-uploads");
+    fs::create_dir_all(&pending_dir).map_err(|e| TelegramError {
+        message: format!(
+            "Failed to create pending upload directory {}: {}",
+            pending_dir.display(),
+            e
+        ),
+        code: error_code::IO.to_string(),
+    })?;
+
+    let local_path = pending_dir.join(format!("{}_{}", Uuid::new_v4().simple(), upload_file_name));
+    fs::write(&local_path, file_bytes).map_err(|e| TelegramError {
+        message: format!(
+            "Failed to store pending upload {}: {}",
+            local_path.display(),
+            e
+        ),
+        code: error_code::IO.to_string(),
+    })?;
+
+    let pending_id = db
+        .add_pending_upload(
+            &owner_id,
+            file_name,
+            &local_path.to_string_lossy(),
+            file_path,
+            match conflict_mode {
+                Some(UploadConflictMode::Rename) => Some("rename"),
+                Some(UploadConflictMode::Error) => Some("error"),
+                None => None,
+            },
+        )
+        .map_err(|e| {
+            let _ = fs::remove_file(&local_path);
+            TelegramError {
+                message: format!("Failed to queue upload: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            }
+        })?;
+
+    log::info!(
+        "enqueue_pending_upload: Queued {} as pending upload {}",
+        file_name,
+        pending_id
+    );
+
+    let classification = classify_extension(upload_extension.as_deref());
+    Ok(TelegramMessage {
+        message_id: 0,
+        chat_id: owner_id.parse().unwrap_or(0),
+        category: classification.category.to_string(),
+        filename: Some(upload_file_name),
+        extension: upload_extension,
+        mime_type: None,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        size: Some(file_bytes.len() as i64),
+        text: None,
+        thumbnail: None,
+        file_reference: format!("pending:{}", pending_id),
+        deduped: false,
+        queued: true,
+    })
+}
+
+/// Background task that sends queued uploads whenever the device is online.
+pub async fn run_pending_upload_queue(app: AppHandle) {
+    let db = app.state::<Database>().inner().clone();
+    if let Err(e) = db.reset_uploading_pending_uploads() {
+        log::warn!("run_pending_upload_queue: {}", e.message);
+    }
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(PENDING_UPLOAD_POLL_INTERVAL_SECS)).await;
+        drain_pending_uploads(&app, &db).await;
+    }
+}
+
+async fn drain_pending_uploads(app: &AppHandle, db: &Database) {
+    let owner_id = {
+        let state_guard = AUTH_STATE.lock().await;
+        match state_guard
+            .as_ref()
+            .and_then(|state| state.owner_context.as_ref())
+        {
+            Some((chat_id, _)) => chat_id.to_string(),
+            None => return,
+        }
+    };
+
+    let pending_uploads = match db.get_pending_uploads(&owner_id) {
+        Ok(uploads) => uploads,
+        Err(e) => {
+            log::warn!("drain_pending_uploads: Failed to read queue: {}", e.message);
+            return;
+        }
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    for pending_upload in pending_uploads.into_iter().filter(|upload| {
+        upload.status == "pending"
+            || (upload.status == "failed"
+                && upload.attempts < PENDING_UPLOAD_MAX_ATTEMPTS
+                && upload.next_retry_at.is_some_and(|retry_at| retry_at <= now))
+    }) {
+        if ensure_basic_connectivity().await.is_err() {
+            return;
+        }
+
+        let file_bytes = match fs::read(&pending_upload.local_path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                // Retrying won't bring the file back
+                let message = format!("Queued file is no longer readable: {}", e);
+                let _ = db.record_pending_upload_failure(pending_upload.id, &message, None);
+                continue;
+            }
+        };
+
+        let _ = db.update_pending_upload_status(pending_upload.id, "uploading", None);
+        let result = upload_file_to_saved_messages(
+            app.clone(),
+            db.clone(),
+            pending_upload.file_name.clone(),
+            UploadSource::Bytes(file_bytes),
+            pending_upload.target_path.clone(),
+            parse_upload_conflict_mode(pending_upload.on_conflict.as_deref())
+                .ok()
+                .flatten(),
+        )
+        .await;
+
+        match result {
+            Ok(message) => {
+                log::info!(
+                    "drain_pending_uploads: Sent pending upload {} as message {}",
+                    pending_upload.id,
+                    message.message_id
+                );
+                let _ = db.remove_pending_upload(pending_upload.id);
+                let _ = fs::remove_file(&pending_upload.local_path);
+                let _ = app.emit("pending-upload-completed", &message);
+            }
+            // Connection trouble: leave it queued for the next round
+            Err(error)
+                if error.code == error_code::OFFLINE
+                    || error.code == error_code::TIMEOUT
+                    || error.code == error_code::FLOOD_WAIT =>
+            {
+                let _ = db.update_pending_upload_status(
+                    pending_upload.id,
+                    "pending",
+                    Some(&error.message),
+                );
+                return;
+            }
+            Err(error) => {
+                log::warn!(
+                    "drain_pending_uploads: Pending upload {} failed: {}",
+                    pending_upload.id,
+                    error.message
+                );
+                let _ = db.record_pending_upload_failure(
+                    pending_upload.id,
+                    &error.message,
+                    pending_upload_next_retry_at(pending_upload.attempts + 1, now),
+                );
+            }
+        }
+    }
+}
+
+// 1, 2, 4, ... poll intervals after the latest failure, None once the attempt budget is spent
+fn pending_upload_next_retry_at(attempts: i64, now: i64) -> Option<i64> {
+    if attempts >= PENDING_UPLOAD_MAX_ATTEMPTS {
+        return None;
+    }
+
+    let delay = PENDING_UPLOAD_POLL_INTERVAL_SECS as i64 * (1_i64 << (attempts - 1).clamp(0, 16));
+    Some(now + delay)
+}
+
+pub async fn tg_list_pending_uploads_impl(
+    db: Database,
+) -> Result<Vec<PendingUpload>, TelegramError> {
+    let owner_id = offline_owner_id(&db).await?;
+
+    db.get_pending_uploads(&owner_id)
+        .map_err(|e| TelegramError {
+            message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })
+}
+
+pub async fn tg_cancel_pending_upload_impl(db: Database, id: i64) -> Result<(), TelegramError> {
+    let owner_id = offline_owner_id(&db).await?;
 
-        emit_download_progress(
-            &app,
-            DownloadProgressPayload {
-                source_path: source_path.clone(),
-                file_name: target_file_name.clone(),
-                stage: "failed".to_string(),
-                progress: moving_progress,
-                downloaded_bytes,
-                bytes_per_second: None,
-                total_bytes,
-                destination_path: Some(destination_file_path.to_string_lossy().replace('\\', "/")),
-                message: Some(error.message.clone()),
-            },
-        );
+    let pending_upload = db
+        .get_pending_uploads(&owner_id)
+        .map_err(|e| TelegramError {
+            message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+        .into_iter()
+        .find(|upload| upload.id == id)
+        .ok_or_else(|| TelegramError {
+            message: "Pending upload not found".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
+        })?;
 
-        return Err(error);
+    if pending_upload.status == "uploading" {
+        return Err(TelegramError {
+            message: "Upload is already in progress; cancel it with tg_cancel_saved_file_upload"
+                .to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
     }
 
-    let destination_path_string = destination_file_path.to_string_lossy().replace('\\', "/");
+    db.remove_pending_upload(id).map_err(|e| TelegramError {
+        message: format!("Failed to remove pending upload: {}", e.message),
+        code: error_code::DATABASE.to_string(),
+    })?;
 
-    clear_download_cancel(&source_path);
+    if let Err(e) = fs::remove_file(&pending_upload.local_path) {
+        log::warn!(
+            "tg_cancel_pending_upload_impl: Failed to delete {}: {}",
+            pending_upload.local_path,
+            e
+        );
+    }
 
-    emit_download_progress(
-        &app,
-        DownloadProgressPayload {
-            source_path,
-            file_name: target_file_name,
-            stage: "completed".to_string(),
-            progress: 100.0,
-            downloaded_bytes,
-            bytes_per_second: None,
-            total_bytes,
-            destination_path: Some(destination_path_string.clone()),
-            message: Some("Download complete".to_string()),
-        },
-    );
+    Ok(())
+}
 
-    Ok(Some(destination_path_string))
+pub async fn tg_retry_pending_upload_impl(db: Database, id: i64) -> Result<(), TelegramError> {
+    let owner_id = offline_owner_id(&db).await?;
+
+    let pending_upload = db
+        .get_pending_uploads(&owner_id)
+        .map_err(|e| TelegramError {
+            message: format!("Database error: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?
+        .into_iter()
+        .find(|upload| upload.id == id)
+        .ok_or_else(|| TelegramError {
+            message: "Pending upload not found".to_string(),
+            code: error_code::NOT_FOUND.to_string(),
+        })?;
+
+    let requeued = db
+        .retry_pending_upload(pending_upload.id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to requeue pending upload: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    if !requeued {
+        return Err(TelegramError {
+            message: "Only failed uploads can be retried".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
+    Ok(())
 }
 
-pub async fn tg_upload_file_to_saved_messages_impl(
+async fn upload_file_to_saved_messages(
     app: AppHandle,
     db: Database,
     file_name: String,
-    file_bytes: Vec<u8>,
+    source: UploadSource,
     file_path: Option<String>,
+    conflict_mode: Option<UploadConflictMode>,
 ) -> Result<TelegramMessage, TelegramError> {
-    if file_bytes.is_empty() {
-        return Err(TelegramError {
-            message: "Cannot upload an empty file".to_string(),
-        });
-    }
-
     let _upload_cancel_cleanup_guard = UploadCancelCleanupGuard::new(&file_name);
+    let active_upload = ActiveUploadGuard::new(&file_name);
+    let upload_id = active_upload.upload_id.clone();
 
-    let (upload_file_name, upload_extension) = build_upload_file_name(&file_name);
-    let upload_media_kind = upload_media_kind_for_extension(upload_extension.as_deref());
+    let (mut upload_file_name, upload_extension) = build_upload_file_name(&file_name);
+    let mut upload_media_kind = upload_media_kind_for_extension(upload_extension.as_deref());
     let upload_mime_type = mime_type_from_extension(upload_extension.as_deref());
 
+    let mut image_dimensions = None;
+    if matches!(upload_media_kind, UploadMediaKind::Photo) {
+        let probed = match &source {
+            UploadSource::Bytes(file_bytes) => {
+                probe_image_dimensions(std::io::Cursor::new(file_bytes.as_slice()))
+            }
+            UploadSource::LocalFile(path) => fs::File::open(path)
+                .map_err(|e| e.to_string())
+                .and_then(|file| probe_image_dimensions(std::io::BufReader::new(file))),
+        };
+        match probed {
+            Ok(dimensions) => image_dimensions = dimensions,
+            Err(e) => {
+                // Telegram rejects photos it cannot decode, so send those as plain files
+                log::warn!(
+                    "tg_upload_file_to_saved_messages_impl: Could not read image header of {}, uploading as document: {}",
+                    file_name,
+                    e
+                );
+                upload_media_kind = UploadMediaKind::Document;
+            }
+        }
+    }
+
+    let (chat_id, owner_id, input_peer) = current_owner_context().await?;
+
+    db.ensure_telegram_saved_folders(&owner_id)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    // A mistyped destination would leave the item in a folder no listing ever shows
+    if let Some(target_path) = file_path.as_deref() {
+        if resolve_saved_path(target_path).is_none() {
+            return Err(TelegramError {
+                message: "Destination path must stay within Home".to_string(),
+                code: error_code::INVALID_INPUT.to_string(),
+            });
+        }
+
+        if !saved_folder_hierarchy_exists(&db, &owner_id, target_path)? {
+            return Err(TelegramError {
+                message: format!(
+                    "Destination folder does not exist: {}",
+                    normalize_saved_path(target_path)
+                ),
+                code: error_code::NOT_FOUND.to_string(),
+            });
+        }
+    }
+
     let client = {
         let state_guard = AUTH_STATE.lock().await;
         let state = state_guard.as_ref().ok_or_else(|| TelegramError {
             message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
         state.client.clone()
     };
 
-    let me = run_telegram_request("tg_upload_file_to_saved_messages_impl.get_me", || async {
-        client.get_me().await
-    })
-    .await
-    .map_err(|e| TelegramError {
-        message: format!("Failed to get user info: {}", e),
-    })?;
-
-    let chat_id = me.raw.id();
-    let owner_id = chat_id.to_string();
-    let input_peer = match &me.raw {
-        tl::enums::User::User(user) => tl::enums::InputPeer::User(tl::types::InputPeerUser {
-            user_id: user.id,
-            access_hash: user.access_hash.unwrap_or(0),
-        }),
-        _ => {
-            return Err(TelegramError {
-                message: "Invalid user type".to_string(),
-            })
+    let local_file_error = |path: &Path, error: std::io::Error| TelegramError {
+        message: format!("Failed to read {}: {}", path.display(), error),
+        code: error_code::IO.to_string(),
+    };
+    let (total_upload_bytes_usize, content_hash) = match &source {
+        UploadSource::Bytes(file_bytes) => (
+            file_bytes.len(),
+            format!("{:x}", Sha256::digest(file_bytes)),
+        ),
+        UploadSource::LocalFile(path) => {
+            let metadata = fs::metadata(path).map_err(|e| local_file_error(path, e))?;
+            // Hashing a large file would otherwise stall this runtime worker
+            let hash_path = path.clone();
+            let content_hash = tokio::task::spawn_blocking(move || hash_local_file(&hash_path))
+                .await
+                .map_err(|e| TelegramError {
+                    message: format!("Hashing {} failed: {}", path.display(), e),
+                    code: error_code::INTERNAL.to_string(),
+                })?
+                .map_err(|e| local_file_error(path, e))?;
+            (metadata.len() as usize, content_hash)
         }
     };
-
-    let total_upload_bytes_usize = file_bytes.len();
     let total_upload_bytes = total_upload_bytes_usize as u64;
-    let temp_path = build_temp_upload_path(&upload_file_name);
-    fs::write(&temp_path, &file_bytes).map_err(|e| TelegramError {
-        message: format!(
-            "Failed to prepare temporary upload file {}: {}",
-            temp_path.display(),
-            e
-        ),
-    })?;
+    let upload_part_size = upload_part_size_bytes(&db, total_upload_bytes_usize);
+
+    // Identical content already uploaded by this user is reused instead of sent again
+    let existing_message_id = db
+        .find_telegram_saved_message_id_by_content_hash(&owner_id, &content_hash)
+        .map_err(|e| TelegramError {
+            message: format!("Failed to check for duplicate upload: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+
+    if let Some(existing_message_id) = existing_message_id {
+        let existing_message = db
+            .get_telegram_message(chat_id, existing_message_id)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to read existing upload: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+
+        if let Some(mut existing_message) = existing_message {
+            log::info!(
+                "tg_upload_file_to_saved_messages_impl: {} matches message {}, skipping upload",
+                file_name,
+                existing_message_id
+            );
+
+            emit_upload_progress(
+                &app,
+                UploadProgressPayload {
+                    upload_id: upload_id.clone(),
+                    file_name: file_name.clone(),
+                    stage: "completed".to_string(),
+                    progress: 100.0,
+                    uploaded_bytes: total_upload_bytes,
+                    bytes_per_second: None,
+                    eta_secs: None,
+                    total_bytes: Some(total_upload_bytes),
+                    message: Some("File already in Saved Messages".to_string()),
+                },
+            );
+
+            existing_message.deduped = true;
+            return Ok(existing_message);
+        }
+    }
+
+    // With a conflict mode the item keeps its original name instead of a unique one
+    if let Some(mode) = conflict_mode {
+        upload_file_name = resolve_upload_conflict_name(
+            &db,
+            &owner_id,
+            &upload_target_folder(file_path.as_deref(), upload_extension.as_deref()),
+            &file_name,
+            upload_extension.as_deref(),
+            mode,
+        )?;
+    }
+
+    // Local files are streamed from where they are; IPC bytes go through a temporary file
+    let (temp_upload_file, temp_path) = match source {
+        UploadSource::Bytes(file_bytes) => {
+            let temp_upload_file = TempUploadFile {
+                path: build_temp_upload_path(&upload_file_name),
+            };
+            let temp_path = temp_upload_file.path.clone();
+            fs::write(&temp_path, &file_bytes).map_err(|e| TelegramError {
+                message: format!(
+                    "Failed to prepare temporary upload file {}: {}",
+                    temp_path.display(),
+                    e
+                ),
+                code: error_code::IO.to_string(),
+            })?;
+            (Some(temp_upload_file), temp_path)
+        }
+        UploadSource::LocalFile(path) => (None, path),
+    };
 
     emit_upload_progress(
         &app,
         UploadProgressPayload {
+            upload_id: upload_id.clone(),
             file_name: file_name.clone(),
             stage: "uploading".to_string(),
             progress: 0.0,
             uploaded_bytes: 0,
             bytes_per_second: None,
+            eta_secs: None,
             total_bytes: Some(total_upload_bytes),
             message: Some("Uploading file".to_string()),
         },
@@ -3550,25 +6909,30 @@ pub async fn tg_upload_file_to_saved_messages_impl(
         let upload_file_name_for_stream = upload_file_name.clone();
         let ui_file_name_for_stream = file_name.clone();
         let app_for_stream = app.clone();
+        let upload_id_for_stream = upload_id.clone();
 
-        let uploaded_file = run_telegram_request(
+        let upload_request = run_telegram_request_with_timeout(
             "tg_upload_file_to_saved_messages_impl.upload_stream",
+            Duration::from_secs(TELEGRAM_TRANSFER_TIMEOUT_SECS),
             || {
                 let temp_path_for_stream = temp_path_for_stream.clone();
                 let upload_file_name_for_stream = upload_file_name_for_stream.clone();
                 let ui_file_name_for_stream = ui_file_name_for_stream.clone();
                 let app_for_stream = app_for_stream.clone();
+                let upload_id_for_stream = upload_id_for_stream.clone();
                 let client_for_stream = client.clone();
 
                 async move {
                     emit_upload_progress(
                         &app_for_stream,
                         UploadProgressPayload {
+                            upload_id: upload_id_for_stream.clone(),
                             file_name: ui_file_name_for_stream.clone(),
                             stage: "uploading".to_string(),
                             progress: 0.0,
                             uploaded_bytes: 0,
                             bytes_per_second: None,
+                            eta_secs: None,
                             total_bytes: Some(total_upload_bytes),
                             message: None,
                         },
@@ -3579,55 +6943,89 @@ pub async fn tg_upload_file_to_saved_messages_impl(
                             .await
                             .map_err(|error| TelegramError {
                                 message: format!(
-                                    "Failed to open temporary upload file {}: {}",
+                                    "Failed to open upload file {}: {}",
                                     temp_path_for_stream.display(),
                                     error
                                 ),
+                                code: error_code::IO.to_string(),
                             })?;
 
                     let mut progress_reader = UploadProgressReader::new(
                         upload_file,
                         app_for_stream,
+                        upload_id_for_stream,
                         ui_file_name_for_stream,
                         total_upload_bytes,
                     );
 
-                    client_for_stream
-                        .upload_stream(
+                    let upload_result = if upload_part_size == MAX_UPLOAD_PART_SIZE {
+                        client_for_stream
+                            .upload_stream(
+                                &mut progress_reader,
+                                total_upload_bytes_usize,
+                                upload_file_name_for_stream,
+                            )
+                            .await
+                    } else {
+                        upload_stream_with_part_size(
+                            &client_for_stream,
                             &mut progress_reader,
                             total_upload_bytes_usize,
                             upload_file_name_for_stream,
+                            upload_part_size,
                         )
                         .await
-                        .map_err(|error| TelegramError {
-                            message: {
-                                let error_message = error.to_string();
-                                if error_message.contains(UPLOAD_CANCELLED_MARKER) {
-                                    UPLOAD_CANCELLED_MARKER.to_string()
-                                } else {
-                                    format!("Failed to upload file to Telegram: {}", error_message)
-                                }
-                            },
-                        })
+                    };
+
+                    upload_result.map_err(|error| {
+                        let error_message = error.to_string();
+                        if error_message.contains(UPLOAD_CANCELLED_MARKER) {
+                            TelegramError {
+                                message: UPLOAD_CANCELLED_MARKER.to_string(),
+                                code: error_code::CANCELLED.to_string(),
+                            }
+                        } else {
+                            TelegramError {
+                                message: format!(
+                                    "Failed to upload file to Telegram: {}",
+                                    error_message
+                                ),
+                                code: request_error_code(&error),
+                            }
+                        }
+                    })
                 }
             },
-        )
-        .await?;
+        );
+
+        // Cancelling drops the in-flight upload instead of waiting for the next read
+        let uploaded_file = tokio::select! {
+            result = upload_request => result?,
+            _ = active_upload.cancel_signal.notified() => {
+                return Err(TelegramError {
+                    message: UPLOAD_CANCELLED_MARKER.to_string(),
+                    code: error_code::CANCELLED.to_string(),
+                });
+            }
+        };
 
         if is_upload_cancel_requested(&file_name) {
             return Err(TelegramError {
                 message: UPLOAD_CANCELLED_MARKER.to_string(),
+                code: error_code::CANCELLED.to_string(),
             });
         }
 
         emit_upload_progress(
             &app,
             UploadProgressPayload {
+                upload_id: upload_id.clone(),
                 file_name: file_name.clone(),
                 stage: "sending".to_string(),
                 progress: 100.0,
                 uploaded_bytes: total_upload_bytes,
                 bytes_per_second: None,
+                eta_secs: None,
                 total_bytes: Some(total_upload_bytes),
                 message: Some("Sending message".to_string()),
             },
@@ -3666,28 +7064,25 @@ pub async fn tg_upload_file_to_saved_messages_impl(
         .await
         .map_err(|error| TelegramError {
             message: format!("Failed to send uploaded file: {}", error),
+            code: request_error_code(&error),
         })
     }
     .await;
 
-    if let Err(cleanup_error) = fs::remove_file(&temp_path) {
-        log::warn!(
-            "Failed to delete temporary upload file {}: {}",
-            temp_path.display(),
-            cleanup_error
-        );
-    }
+    drop(temp_upload_file);
 
     let sent_message = match upload_and_send_result {
         Ok(message) => {
             emit_upload_progress(
                 &app,
                 UploadProgressPayload {
+                    upload_id: upload_id.clone(),
                     file_name: file_name.clone(),
                     stage: "completed".to_string(),
                     progress: 100.0,
                     uploaded_bytes: total_upload_bytes,
                     bytes_per_second: None,
+                    eta_secs: None,
                     total_bytes: Some(total_upload_bytes),
                     message: Some("Upload complete".to_string()),
                 },
@@ -3703,11 +7098,13 @@ pub async fn tg_upload_file_to_saved_messages_impl(
             emit_upload_progress(
                 &app,
                 UploadProgressPayload {
+                    upload_id: upload_id.clone(),
                     file_name: file_name.clone(),
                     stage: "failed".to_string(),
                     progress: 0.0,
                     uploaded_bytes: 0,
                     bytes_per_second: None,
+                    eta_secs: None,
                     total_bytes: Some(total_upload_bytes),
                     message: Some(error.message.clone()),
                 },
@@ -3732,7 +7129,7 @@ pub async fn tg_upload_file_to_saved_messages_impl(
             extension,
             mime_type: None,
             timestamp: sent_message.date().to_rfc3339(),
-            size: Some(file_bytes.len() as i64),
+            size: Some(total_upload_bytes as i64),
             text: if sent_message.text().is_empty() {
                 None
             } else {
@@ -3740,6 +7137,8 @@ pub async fn tg_upload_file_to_saved_messages_impl(
             },
             thumbnail: None,
             file_reference: format!("upload:{}:{}", chat_id, sent_message.id()),
+            deduped: false,
+            queued: false,
         }
     };
 
@@ -3754,11 +7153,7 @@ pub async fn tg_upload_file_to_saved_messages_impl(
     db.save_telegram_message(&telegram_message)
         .map_err(|e| TelegramError {
             message: format!("Failed to save uploaded message metadata: {}", e.message),
-        })?;
-
-    db.ensure_telegram_saved_folders(&owner_id)
-        .map_err(|e| TelegramError {
-            message: format!("Failed to ensure default folders: {}", e.message),
+            code: error_code::DATABASE.to_string(),
         })?;
 
     upsert_saved_item_from_message(
@@ -3769,6 +7164,33 @@ pub async fn tg_upload_file_to_saved_messages_impl(
         Some(&upload_file_name),
     )?;
 
+    if let Err(e) = db.update_telegram_saved_item_content_hash(
+        &owner_id,
+        telegram_message.message_id,
+        &content_hash,
+    ) {
+        log::warn!(
+            "tg_upload_file_to_saved_messages_impl: Failed to store content hash for message {}: {}",
+            telegram_message.message_id,
+            e.message
+        );
+    }
+
+    if let Some((width, height)) = image_dimensions {
+        if let Err(e) = db.update_telegram_saved_item_dimensions(
+            &owner_id,
+            telegram_message.message_id,
+            width as i64,
+            height as i64,
+        ) {
+            log::warn!(
+                "tg_upload_file_to_saved_messages_impl: Failed to store image dimensions for message {}: {}",
+                telegram_message.message_id,
+                e.message
+            );
+        }
+    }
+
     Ok(telegram_message)
 }
 fn estimate_photo_message_size(photo: &tl::types::Photo) -> Option<i64> {
@@ -3801,6 +7223,7 @@ fn estimate_photo_message_size(photo: &tl::types::Photo) -> Option<i64> {
 }
 
 fn categorize_message(message: &Message, chat_id: i64) -> Option<TelegramMessage> {
+    use base64::Engine;
     let media = message.media();
 
     let (category, filename, extension, mime_type, size, thumbnail, file_ref) = match media {
@@ -3824,7 +7247,7 @@ fn categorize_message(message: &Message, chat_id: i64) -> Option<TelegramMessage
                 Some("image/jpeg".to_string()),
                 size,
                 None,
-                json!({"type": "photo", "id": id, "access_hash": access_hash, "file_reference": base64_encode(&file_ref_bytes)}).to_string()
+                json!({"type": "photo", "id": id, "access_hash": access_hash, "file_reference": base64::engine::general_purpose::STANDARD.encode(&file_ref_bytes)}).to_string()
             )
         }
         Some(Media::Document(doc)) => {
@@ -3852,7 +7275,7 @@ fn categorize_message(message: &Message, chat_id: i64) -> Option<TelegramMessage
                 mime,
                 sz,
                 None,
-                json!({"type": "document", "id": id, "access_hash": access_hash, "file_reference": base64_encode(&file_ref_bytes)}).to_string()
+                json!({"type": "document", "id": id, "access_hash": access_hash, "file_reference": base64::engine::general_purpose::STANDARD.encode(&file_ref_bytes)}).to_string()
             )
         }
         _ => {
@@ -3890,10 +7313,280 @@ fn categorize_message(message: &Message, chat_id: i64) -> Option<TelegramMessage
         },
         thumbnail,
         file_reference: file_ref,
+        deduped: false,
+        queued: false,
     })
 }
 
-fn base64_encode(bytes: &[u8]) -> String {
-    use base64::Engine;
-    base64::engine::general_purpose::STANDARD.encode(bytes)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_standard_base64_image_data_urls() {
+        assert_eq!(
+            decode_data_url_image_bytes("data:image/jpeg;base64,aGVsbG8="),
+            Some(b"hello".to_vec())
+        );
+        assert_eq!(
+            decode_data_url_image_bytes("data:image/jpeg;base64,aGVsbG8"),
+            Some(b"hello".to_vec())
+        );
+        assert_eq!(
+            decode_data_url_image_bytes("data:image/png;base64,+/8="),
+            Some(vec![0xfb, 0xff])
+        );
+    }
+
+    #[test]
+    fn decodes_url_safe_base64_image_data_urls() {
+        assert_eq!(
+            decode_data_url_image_bytes("data:image/png;base64,-_8="),
+            Some(vec![0xfb, 0xff])
+        );
+        assert_eq!(
+            decode_data_url_image_bytes("data:image/png;base64,-_8"),
+            Some(vec![0xfb, 0xff])
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_or_empty_payloads() {
+        assert_eq!(
+            decode_data_url_image_bytes("data:image/jpeg;base64,aGVsb"),
+            None
+        );
+        assert_eq!(decode_data_url_image_bytes("data:image/jpeg;base64,"), None);
+    }
+
+    #[test]
+    fn rejects_non_image_and_non_base64_data_urls() {
+        assert_eq!(
+            decode_data_url_image_bytes("data:text/plain;base64,aGVsbG8="),
+            None
+        );
+        assert_eq!(
+            decode_data_url_image_bytes("data:image/svg+xml,<svg/>"),
+            None
+        );
+        assert_eq!(decode_data_url_image_bytes("aGVsbG8="), None);
+    }
+
+    #[test]
+    fn resolves_dot_segments_inside_home() {
+        assert_eq!(resolve_saved_path("/Home").as_deref(), Some("/Home"));
+        assert_eq!(resolve_saved_path("").as_deref(), Some("/Home"));
+        assert_eq!(
+            resolve_saved_path("/Home/Images/./trip/..").as_deref(),
+            Some("/Home/Images")
+        );
+        assert_eq!(
+            resolve_saved_path("Home\\Documents//work/").as_deref(),
+            Some("/Home/Documents/work")
+        );
+    }
+
+    #[test]
+    fn rejects_paths_that_climb_above_home() {
+        assert_eq!(resolve_saved_path(".."), None);
+        assert_eq!(resolve_saved_path("/Home/.."), None);
+        assert_eq!(resolve_saved_path("/Home/Images/../../Recycle Bin"), None);
+        assert_eq!(resolve_saved_path("/Home/a/..\\..\\etc"), None);
+        assert_eq!(normalize_saved_path("/Home/../etc"), "/Home");
+    }
+
+    #[test]
+    fn keeps_absolute_paths_under_home() {
+        assert_eq!(
+            resolve_saved_path("/etc/passwd").as_deref(),
+            Some("/Home/etc/passwd")
+        );
+        assert_eq!(
+            resolve_saved_path("C:\\Windows\\System32").as_deref(),
+            Some("/Home/C:/Windows/System32")
+        );
+        assert_eq!(
+            resolve_saved_path("/HomeX/Images").as_deref(),
+            Some("/Home/HomeX/Images")
+        );
+    }
+
+    fn fetched_document(message_id: i32, thumbnail: Option<&str>) -> TelegramMessage {
+        TelegramMessage {
+            message_id,
+            chat_id: 42,
+            category: "Documents".to_string(),
+            filename: Some("report.pdf".to_string()),
+            extension: Some("pdf".to_string()),
+            mime_type: Some("application/pdf".to_string()),
+            timestamp: "2026-01-01T00:00:00+00:00".to_string(),
+            size: Some(10),
+            text: None,
+            thumbnail: thumbnail.map(str::to_string),
+            file_reference: String::new(),
+            deduped: false,
+            queued: false,
+        }
+    }
+
+    #[test]
+    fn reindexing_an_unchanged_message_skips_the_write() {
+        let db = crate::db::TempDatabase::new("reindex-unchanged");
+        assert!(
+            save_indexed_message(&db, "42", &fetched_document(7, Some("/cache/7.jpg"))).unwrap()
+        );
+
+        // The refetched message carries Telegram's thumbnail instead of the cached path
+        let mut skipped_unchanged = 0;
+        if !save_indexed_message(
+            &db,
+            "42",
+            &fetched_document(7, Some("data:image/jpeg;base64,AA")),
+        )
+        .unwrap()
+        {
+            skipped_unchanged += 1;
+        }
+        assert_eq!(skipped_unchanged, 1);
+
+        let message = db.get_telegram_message(42, 7).unwrap().unwrap();
+        assert_eq!(message.thumbnail.as_deref(), Some("/cache/7.jpg"));
+        let item = db
+            .get_telegram_saved_file_by_message_id("42", 7)
+            .unwrap()
+            .unwrap();
+        assert_eq!(item.thumbnail.as_deref(), Some("/cache/7.jpg"));
+    }
+
+    #[test]
+    fn reindexing_keeps_a_user_rename() {
+        let db = crate::db::TempDatabase::new("reindex-renamed");
+        save_indexed_message(&db, "42", &fetched_document(7, None)).unwrap();
+        db.rename_telegram_saved_file_with_type_by_message_id(
+            "42",
+            42,
+            7,
+            "notes.txt",
+            "txt",
+            "text",
+            "Notes",
+            "2026-01-02T00:00:00+00:00",
+        )
+        .unwrap();
+
+        assert!(!save_indexed_message(&db, "42", &fetched_document(7, None)).unwrap());
+
+        // A caption edit is still picked up without bringing back the original name
+        let mut edited = fetched_document(7, None);
+        edited.text = Some("Q3".to_string());
+        assert!(save_indexed_message(&db, "42", &edited).unwrap());
+
+        let message = db.get_telegram_message(42, 7).unwrap().unwrap();
+        assert_eq!(message.filename.as_deref(), Some("notes.txt"));
+        assert_eq!(message.text.as_deref(), Some("Q3"));
+        let item = db
+            .get_telegram_saved_file_by_message_id("42", 7)
+            .unwrap()
+            .unwrap();
+        assert_eq!(item.file_name, "notes.txt");
+    }
+
+    #[test]
+    fn rename_to_new_extension_reclassifies_item_and_cached_message() {
+        let db = crate::db::TempDatabase::new("reclassify");
+        let owner_id = "42";
+        let chat_id = 42;
+
+        db.save_telegram_message(&TelegramMessage {
+            message_id: 7,
+            chat_id,
+            category: "Images".to_string(),
+            filename: Some("scan.jpg".to_string()),
+            extension: Some("jpg".to_string()),
+            mime_type: Some("image/jpeg".to_string()),
+            timestamp: "2026-01-01T00:00:00+00:00".to_string(),
+            size: Some(3),
+            text: None,
+            thumbnail: None,
+            file_reference: String::new(),
+            deduped: false,
+            queued: false,
+        })
+        .unwrap();
+        db.upsert_telegram_saved_item(&TelegramSavedItem {
+            chat_id,
+            message_id: 7,
+            thumbnail: None,
+            file_type: "image".to_string(),
+            file_unique_id: "msg_42_7".to_string(),
+            file_size: 3,
+            file_name: "scan.jpg".to_string(),
+            file_caption: None,
+            file_path: "/Home/Images".to_string(),
+            recycle_origin_path: None,
+            modified_date: "2026-01-01T00:00:00+00:00".to_string(),
+            owner_id: owner_id.to_string(),
+            pinned: false,
+            image_width: None,
+            image_height: None,
+            indexed_at: None,
+            audio_duration_secs: None,
+            audio_title: None,
+            audio_performer: None,
+        })
+        .unwrap();
+
+        let extension = extension_from_name("scan.pdf").unwrap();
+        let classification = classify_extension(Some(extension.as_str()));
+        db.rename_telegram_saved_file_with_type_by_message_id(
+            owner_id,
+            chat_id,
+            7,
+            "scan.pdf",
+            &extension,
+            classification.file_type,
+            classification.category,
+            "2026-01-02T00:00:00+00:00",
+        )
+        .unwrap();
+
+        let item = db
+            .get_telegram_saved_file_by_message_id(owner_id, 7)
+            .unwrap()
+            .unwrap();
+        assert_eq!(item.file_name, "scan.pdf");
+        assert_eq!(item.file_type, "document");
+
+        let message = db.get_telegram_message(chat_id, 7).unwrap().unwrap();
+        assert_eq!(message.filename.as_deref(), Some("scan.pdf"));
+        assert_eq!(message.extension.as_deref(), Some("pdf"));
+        assert_eq!(message.category, "Documents");
+    }
+
+    #[test]
+    fn rejects_reserved_folder_names_at_home() {
+        for name in ["recycle bin", "Images", "  RECYCLE BIN  "] {
+            let error = validate_new_folder_name(SAVED_ROOT_PATH, name).unwrap_err();
+            assert_eq!(error.code, error_code::INVALID_INPUT);
+        }
+
+        // Default names are only reserved directly under Home
+        assert_eq!(
+            validate_new_folder_name("/Home/Projects", "Images").unwrap(),
+            "Images"
+        );
+    }
+
+    #[test]
+    fn rejects_folder_names_with_separators() {
+        for name in ["a/b", "a\\b", "/"] {
+            let error = validate_new_folder_name(SAVED_ROOT_PATH, name).unwrap_err();
+            assert_eq!(error.code, error_code::INVALID_INPUT);
+        }
+
+        assert_eq!(
+            validate_new_folder_name(SAVED_ROOT_PATH, "  Trip photos ").unwrap(),
+            "Trip photos"
+        );
+    }
 }
@@ -1,5 +1,7 @@
+use connection::{active_connection, ConnectionHealth};
+use grammers_client::grammers_tl_types as tl;
 use grammers_client::types::{LoginToken, PasswordToken};
-use grammers_client::{Client, SignInError};
+use grammers_client::{Client, InvocationError, SignInError};
 use grammers_mtsender::SenderPoolHandle;
 #[allow(deprecated)]
 use grammers_session::storages::TlSession;
@@ -10,9 +12,9 @@ use serde::{Deserialize, Serialize};
 use std::env;
 use std::future::Future;
 use std::sync::atomic::AtomicU64;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::{Duration, Instant};
-use tauri::State;
+use tauri::{Emitter, State};
 use tokio::sync::{mpsc::UnboundedReceiver, Mutex};
 use tokio::task::JoinHandle;
 
@@ -31,6 +33,9 @@ pub static AUTH_FLOW_ID: AtomicU64 = AtomicU64::new(0);
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TelegramError {
     pub message: String,
+    /// Stable identifier from `error_code` for the frontend to branch on; `message` is for display.
+    #[serde(default)]
+    pub code: String,
 }
 
 impl std::fmt::Display for TelegramError {
@@ -41,6 +46,65 @@ impl std::fmt::Display for TelegramError {
 
 impl std::error::Error for TelegramError {}
 
+pub(crate) mod error_code {
+    pub const FLOOD_WAIT: &str = "FLOOD_WAIT";
+    pub const TIMEOUT: &str = "TIMEOUT";
+    pub const OFFLINE: &str = "OFFLINE";
+    pub const NOT_AUTHORIZED: &str = "NOT_AUTHORIZED";
+    pub const SESSION_EXPIRED: &str = "SESSION_EXPIRED";
+    pub const CORRUPT_SESSION: &str = "CORRUPT_SESSION";
+    pub const AUTH_FLOW_EXPIRED: &str = "AUTH_FLOW_EXPIRED";
+    pub const INVALID_CODE: &str = "INVALID_CODE";
+    pub const INVALID_PASSWORD: &str = "INVALID_PASSWORD";
+    pub const PASSWORD_REQUIRED: &str = "PASSWORD_REQUIRED";
+    pub const SIGN_UP_REQUIRED: &str = "SIGN_UP_REQUIRED";
+    pub const INVALID_INPUT: &str = "INVALID_INPUT";
+    pub const NOT_FOUND: &str = "NOT_FOUND";
+    pub const DESTINATION_EXISTS: &str = "DESTINATION_EXISTS";
+    pub const FILE_TOO_LARGE: &str = "FILE_TOO_LARGE";
+    pub const INSUFFICIENT_SPACE: &str = "INSUFFICIENT_SPACE";
+    pub const CANCELLED: &str = "CANCELLED";
+    pub const DATABASE: &str = "DATABASE";
+    pub const IO: &str = "IO";
+    pub const REQUEST_FAILED: &str = "REQUEST_FAILED";
+    pub const INTERNAL: &str = "INTERNAL";
+}
+
+/// Map a failed Telegram request to an `error_code`, based on the RPC error name or transport failure.
+pub(crate) fn request_error_code(error: &dyn std::fmt::Display) -> String {
+    let message = error.to_string();
+    let upper = message.to_uppercase();
+
+    let code = if parse_flood_wait_seconds(&message).is_some() {
+        error_code::FLOOD_WAIT
+    } else if message == REQUEST_TIMED_OUT_MESSAGE || upper.contains("TIMED OUT") {
+        error_code::TIMEOUT
+    } else if upper.contains("SESSION_PASSWORD_NEEDED") {
+        error_code::PASSWORD_REQUIRED
+    } else if upper.contains("PHONE_CODE_INVALID") || upper.contains("PHONE_CODE_EXPIRED") {
+        error_code::INVALID_CODE
+    } else if upper.contains("PASSWORD_HASH_INVALID") {
+        error_code::INVALID_PASSWORD
+    } else if upper.contains("AUTH_KEY_UNREGISTERED")
+        || upper.contains("AUTH_KEY_INVALID")
+        || upper.contains("SESSION_REVOKED")
+        || upper.contains("SESSION_EXPIRED")
+        || upper.contains("USER_DEACTIVATED")
+    {
+        error_code::NOT_AUTHORIZED
+    } else if upper.contains("I/O")
+        || upper.contains("CONNECTION")
+        || upper.contains("NETWORK")
+        || upper.contains("DROPPED")
+    {
+        error_code::OFFLINE
+    } else {
+        error_code::REQUEST_FAILED
+    };
+
+    code.to_string()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TelegramAuthData {
     pub phone_number: String,
@@ -54,7 +118,7 @@ pub struct TelegramAuthResult {
     pub requires_password: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserInfo {
     pub id: i64,
     pub username: Option<String>,
@@ -63,11 +127,138 @@ pub struct UserInfo {
     pub profile_photo: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct AccountLimits {
+    pub is_premium: bool,
+    /// Largest file the account may upload, in bytes
+    pub max_upload_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectionStatus {
+    pub authorized: bool,
+    pub online: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TelegramDiagnostics {
+    pub dc_id: Option<i32>,
+    pub authorized: bool,
+    pub is_migrating: bool,
+    /// RFC 3339 time the last Telegram request was sent
+    pub last_request_at: Option<String>,
+    pub flood_wait_active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileClassification {
+    pub category: String,
+    pub file_type: String,
+}
+
+/// Newly indexed messages per category
+#[derive(Debug, Default, Serialize)]
+pub struct IndexedCategoryCounts {
+    pub images: usize,
+    pub videos: usize,
+    pub audios: usize,
+    pub documents: usize,
+    pub notes: usize,
+}
+
+impl IndexedCategoryCounts {
+    pub(crate) fn add(&mut self, category: &str) {
+        match category {
+            "Images" => self.images += 1,
+            "Videos" => self.videos += 1,
+            "Audios" => self.audios += 1,
+            "Notes" => self.notes += 1,
+            _ => self.documents += 1,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexSummary {
+    pub total_new_messages: usize,
+    #[serde(flatten)]
+    pub categories: IndexedCategoryCounts,
+    pub skipped_unchanged: usize,
+    pub started_from_empty_db: bool,
+    pub repaired_image_sizes: usize,
+    pub cancelled: bool,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackfillSummary {
+    pub fetched_count: usize,
+    pub indexed_count: usize,
+    #[serde(flatten)]
+    pub categories: IndexedCategoryCounts,
+    pub has_more: bool,
+    pub is_complete: bool,
+    pub next_offset_id: Option<i32>,
+    pub cancelled: bool,
+}
+
+/// Track info read from a document's audio attribute
+#[derive(Debug, Serialize)]
+pub struct AudioMetadata {
+    pub duration_secs: Option<i64>,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MediaPreview {
+    /// Local file the preview was downloaded to
+    pub path: String,
+    /// Set for audio files
+    pub audio: Option<AudioMetadata>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedSavedFolder {
+    #[serde(flatten)]
+    pub item: crate::db::TelegramSavedItem,
+    /// `tg://saved/...` path to navigate into the new folder
+    pub virtual_path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchAllResults {
+    pub local: Vec<String>,
+    pub saved: Vec<crate::db::TelegramSavedItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActiveSession {
+    // Serialized as a string: the i64 hash does not fit in a JS number
+    pub hash: String,
+    pub device_model: String,
+    pub platform: String,
+    pub system_version: String,
+    pub app_name: String,
+    pub app_version: String,
+    pub ip: String,
+    pub country: String,
+    pub region: String,
+    pub date_created: i64,
+    pub date_active: i64,
+    pub is_current: bool,
+    pub is_official_app: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QrLoginData {
     pub qr_url: String,
+    // Only populated when the caller asks for a pre-rendered PNG
+    pub qr_png_data_url: Option<String>,
     pub expires_at_unix: i64,
     pub flow_id: u64,
+    // Set when the session was already authorized, so there is no QR code to scan
+    pub login_result: Option<QrPollResult>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -127,13 +318,241 @@ pub(crate) struct AuthState {
     pub is_migrating: bool,
     // Current DC ID for this session
     pub current_dc_id: Option<i32>,
+    // Logged-in user id and Saved Messages peer, filled by the first get_me
+    pub owner_context: Option<(i64, tl::enums::InputPeer)>,
+    // Profile fields of the logged-in user, served by tg_get_me
+    pub user_info: Option<UserInfo>,
+    // Online/offline tracking for the sender pool, updated by request retries
+    pub connection: Arc<ConnectionHealth>,
+    // Premium status and upload limit, filled by the first tg_get_account_limits
+    pub account_limits: Option<AccountLimits>,
 }
 
 pub(crate) static AUTH_STATE: Lazy<Mutex<Option<AuthState>>> = Lazy::new(|| Mutex::const_new(None));
+
+pub(crate) fn input_peer_for_user(
+    user: &grammers_client::types::User,
+) -> Result<tl::enums::InputPeer, TelegramError> {
+    match &user.raw {
+        tl::enums::User::User(user) => Ok(tl::enums::InputPeer::User(tl::types::InputPeerUser {
+            user_id: user.id,
+            access_hash: user.access_hash.unwrap_or(0),
+        })),
+        _ => Err(TelegramError {
+            message: "Invalid user type".to_string(),
+            code: error_code::INTERNAL.to_string(),
+        }),
+    }
+}
+
+/// Resolve `(chat_id, owner_id, input_peer)` for the logged-in user.
+///
+/// Only the first call per session hits `get_me`; the result is cached in
+/// `AuthState` and dropped together with it on logout or account switch.
+pub(crate) async fn current_owner_context(
+) -> Result<(i64, String, tl::enums::InputPeer), TelegramError> {
+    let (client, session) = {
+        let state_guard = AUTH_STATE.lock().await;
+        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+
+        if let Some((chat_id, input_peer)) = &state.owner_context {
+            return Ok((*chat_id, chat_id.to_string(), input_peer.clone()));
+        }
+
+        (state.client.clone(), Arc::clone(&state.session))
+    };
+
+    let me = run_telegram_request("current_owner_context.get_me", || async {
+        client.get_me().await
+    })
+    .await
+    .map_err(|e| TelegramError {
+        message: format!("Failed to get user info: {}", e),
+        code: request_error_code(&e),
+    })?;
+
+    let chat_id = me.raw.id();
+    let input_peer = input_peer_for_user(&me)?;
+
+    {
+        let mut state_guard = AUTH_STATE.lock().await;
+        // Only cache onto the session we asked about; it may have been replaced meanwhile
+        if let Some(state) = state_guard
+            .as_mut()
+            .filter(|state| Arc::ptr_eq(&state.session, &session))
+        {
+            state.owner_context = Some((chat_id, input_peer.clone()));
+        }
+    }
+
+    Ok((chat_id, chat_id.to_string(), input_peer))
+}
 static TELEGRAM_LAST_REQUEST_AT: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+// End of the flood wait a request is currently sleeping through, for diagnostics
+static TELEGRAM_FLOOD_WAIT_UNTIL: Lazy<StdMutex<Option<Instant>>> =
+    Lazy::new(|| StdMutex::new(None));
 
 const TELEGRAM_REQUEST_DELAY_MS: u64 = 350;
 const TELEGRAM_FLOOD_WAIT_RETRY_LIMIT: usize = 3;
+const TELEGRAM_REQUEST_DELAY_SETTING_KEY: &str = "tg_request_delay_ms";
+const TELEGRAM_FLOOD_WAIT_RETRY_LIMIT_SETTING_KEY: &str = "tg_flood_wait_retry_limit";
+const TELEGRAM_TRANSFER_RETRY_LIMIT: usize = 2;
+const TELEGRAM_TRANSFER_RETRY_LIMIT_SETTING_KEY: &str = "tg_transfer_retry_limit";
+const TELEGRAM_TRANSFER_RETRY_BACKOFF_MS: u64 = 500;
+const TELEGRAM_REQUEST_TIMEOUT_SECS: u64 = 30;
+// Budget for transfers that stream whole files through a single request
+pub(crate) const TELEGRAM_TRANSFER_TIMEOUT_SECS: u64 = 30 * 60;
+
+const REQUEST_TIMED_OUT_MESSAGE: &str = "Request timed out";
+
+/// Error types that can represent a Telegram request which exceeded its timeout.
+pub(crate) trait RequestTimeoutError {
+    fn request_timed_out() -> Self;
+}
+
+impl RequestTimeoutError for TelegramError {
+    fn request_timed_out() -> Self {
+        TelegramError {
+            message: REQUEST_TIMED_OUT_MESSAGE.to_string(),
+            code: error_code::TIMEOUT.to_string(),
+        }
+    }
+}
+
+impl RequestTimeoutError for std::io::Error {
+    fn request_timed_out() -> Self {
+        std::io::Error::new(std::io::ErrorKind::TimedOut, REQUEST_TIMED_OUT_MESSAGE)
+    }
+}
+
+impl RequestTimeoutError for InvocationError {
+    fn request_timed_out() -> Self {
+        InvocationError::Io(std::io::Error::request_timed_out())
+    }
+}
+
+impl RequestTimeoutError for SignInError {
+    fn request_timed_out() -> Self {
+        SignInError::Other(InvocationError::request_timed_out())
+    }
+}
+
+// Overrides loaded from the settings table at startup; unset cells fall back to the constants
+static TELEGRAM_REQUEST_DELAY_MS_CELL: OnceCell<u64> = OnceCell::new();
+static TELEGRAM_FLOOD_WAIT_RETRY_LIMIT_CELL: OnceCell<usize> = OnceCell::new();
+static TELEGRAM_TRANSFER_RETRY_LIMIT_CELL: OnceCell<usize> = OnceCell::new();
+
+// Lets request helpers emit events without every caller passing an AppHandle
+static EVENT_APP_HANDLE: OnceCell<tauri::AppHandle> = OnceCell::new();
+
+pub fn register_event_app_handle(app: tauri::AppHandle) {
+    let _ = EVENT_APP_HANDLE.set(app);
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FloodWaitPayload {
+    operation: String,
+    seconds: u64,
+}
+
+fn emit_flood_wait(operation_name: &str, wait_seconds: u64) {
+    let Some(app) = EVENT_APP_HANDLE.get() else {
+        return;
+    };
+
+    let payload = FloodWaitPayload {
+        operation: operation_name.to_string(),
+        seconds: wait_seconds,
+    };
+    if let Err(error) = app.emit("telegram-flood-wait", payload) {
+        log::warn!("Failed to emit flood wait event: {}", error);
+    }
+}
+
+fn read_request_tuning_setting(db: &Database, key: &str) -> Option<i64> {
+    match db.get_setting_i64(key) {
+        Ok(Some(value)) if value >= 0 => Some(value),
+        Ok(Some(value)) => {
+            log::warn!("Ignoring negative value {} for setting {}", value, key);
+            None
+        }
+        Ok(None) => None,
+        Err(e) => {
+            log::warn!("Ignoring setting {}: {}", key, e.message);
+            None
+        }
+    }
+}
+
+pub fn load_request_tuning_settings(db: &Database) {
+    if let Some(delay_ms) = read_request_tuning_setting(db, TELEGRAM_REQUEST_DELAY_SETTING_KEY) {
+        let _ = TELEGRAM_REQUEST_DELAY_MS_CELL.set(delay_ms as u64);
+        log::info!("Telegram request delay set to {}ms", delay_ms);
+    }
+
+    if let Some(retry_limit) =
+        read_request_tuning_setting(db, TELEGRAM_FLOOD_WAIT_RETRY_LIMIT_SETTING_KEY)
+    {
+        let _ = TELEGRAM_FLOOD_WAIT_RETRY_LIMIT_CELL.set(retry_limit as usize);
+        log::info!("Telegram flood wait retry limit set to {}", retry_limit);
+    }
+
+    if let Some(retry_limit) =
+        read_request_tuning_setting(db, TELEGRAM_TRANSFER_RETRY_LIMIT_SETTING_KEY)
+    {
+        let _ = TELEGRAM_TRANSFER_RETRY_LIMIT_CELL.set(retry_limit as usize);
+        log::info!("Telegram transfer retry limit set to {}", retry_limit);
+    }
+}
+
+fn telegram_request_delay_ms() -> u64 {
+    *TELEGRAM_REQUEST_DELAY_MS_CELL.get_or_init(|| TELEGRAM_REQUEST_DELAY_MS)
+}
+
+fn telegram_flood_wait_retry_limit() -> usize {
+    *TELEGRAM_FLOOD_WAIT_RETRY_LIMIT_CELL.get_or_init(|| TELEGRAM_FLOOD_WAIT_RETRY_LIMIT)
+}
+
+fn telegram_transfer_retry_limit() -> usize {
+    *TELEGRAM_TRANSFER_RETRY_LIMIT_CELL.get_or_init(|| TELEGRAM_TRANSFER_RETRY_LIMIT)
+}
+
+/// Which failed requests `run_telegram_request_with_policy` tries again.
+///
+/// Only flood waits and dropped connections are retried; other errors, such as
+/// an invalid login code, are returned right away.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RequestRetryPolicy {
+    /// Flood waits to sleep through before giving up
+    pub flood_wait_retries: usize,
+    /// Retries after a dropped or reset connection, each on a fresh connection
+    pub connection_retries: usize,
+    /// Pause before the first connection retry; doubled for each further one
+    pub connection_backoff: Duration,
+}
+
+impl RequestRetryPolicy {
+    /// Default for every request: one immediate retry on a dropped connection
+    pub(crate) fn standard() -> Self {
+        RequestRetryPolicy {
+            flood_wait_retries: telegram_flood_wait_retry_limit(),
+            connection_retries: 1,
+            connection_backoff: Duration::ZERO,
+        }
+    }
+
+    /// Downloads and thumbnail fetches, which are safe to repeat
+    pub(crate) fn transfer() -> Self {
+        RequestRetryPolicy {
+            connection_retries: telegram_transfer_retry_limit(),
+            connection_backoff: Duration::from_millis(TELEGRAM_TRANSFER_RETRY_BACKOFF_MS),
+            ..Self::standard()
+        }
+    }
+}
 
 pub(crate) fn parse_flood_wait_seconds(message: &str) -> Option<u64> {
     let upper = message.to_uppercase();
@@ -170,7 +589,7 @@ pub(crate) fn parse_flood_wait_seconds(message: &str) -> Option<u64> {
 async fn wait_for_telegram_request_slot() {
     let mut last_request_at = TELEGRAM_LAST_REQUEST_AT.lock().await;
     if let Some(previous) = *last_request_at {
-        let min_delay = Duration::from_millis(TELEGRAM_REQUEST_DELAY_MS);
+        let min_delay = Duration::from_millis(telegram_request_delay_ms());
         let elapsed = previous.elapsed();
         if elapsed < min_delay {
             tokio::time::sleep(min_delay - elapsed).await;
@@ -180,29 +599,149 @@ async fn wait_for_telegram_request_slot() {
     *last_request_at = Some(Instant::now());
 }
 
+pub(crate) async fn last_telegram_request_at() -> Option<chrono::DateTime<chrono::Utc>> {
+    let last_request_at = *TELEGRAM_LAST_REQUEST_AT.lock().await;
+    last_request_at.and_then(|instant| {
+        chrono::Duration::from_std(instant.elapsed())
+            .ok()
+            .map(|elapsed| chrono::Utc::now() - elapsed)
+    })
+}
+
+pub(crate) fn is_request_flood_wait_active() -> bool {
+    TELEGRAM_FLOOD_WAIT_UNTIL
+        .lock()
+        .map(|until| until.is_some_and(|until| Instant::now() < until))
+        .unwrap_or(false)
+}
+
 pub(crate) async fn run_telegram_request<T, E, F, Fut>(
     operation_name: &str,
+    request_fn: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Display + RequestTimeoutError,
+{
+    run_telegram_request_with_timeout(
+        operation_name,
+        Duration::from_secs(TELEGRAM_REQUEST_TIMEOUT_SECS),
+        request_fn,
+    )
+    .await
+}
+
+// run_telegram_request with `RequestRetryPolicy::transfer()`
+pub(crate) async fn run_telegram_transfer_request<T, E, F, Fut>(
+    operation_name: &str,
+    request_fn: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Display + RequestTimeoutError,
+{
+    run_telegram_request_with_policy(
+        operation_name,
+        Duration::from_secs(TELEGRAM_REQUEST_TIMEOUT_SECS),
+        RequestRetryPolicy::transfer(),
+        request_fn,
+    )
+    .await
+}
+
+// Like run_telegram_request, but each attempt is bounded by `request_timeout`
+// instead of the default. Flood-wait sleeps do not count against it.
+pub(crate) async fn run_telegram_request_with_timeout<T, E, F, Fut>(
+    operation_name: &str,
+    request_timeout: Duration,
+    request_fn: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Display + RequestTimeoutError,
+{
+    run_telegram_request_with_policy(
+        operation_name,
+        request_timeout,
+        RequestRetryPolicy::standard(),
+        request_fn,
+    )
+    .await
+}
+
+pub(crate) async fn run_telegram_request_with_policy<T, E, F, Fut>(
+    operation_name: &str,
+    request_timeout: Duration,
+    policy: RequestRetryPolicy,
     mut request_fn: F,
 ) -> Result<T, E>
 where
     F: FnMut() -> Fut,
     Fut: Future<Output = Result<T, E>>,
-    E: std::fmt::Display,
+    E: std::fmt::Display + RequestTimeoutError,
 {
+    let flood_wait_retry_limit = policy.flood_wait_retries;
     let mut flood_wait_retries = 0usize;
+    let mut connection_retries = 0usize;
 
     loop {
         wait_for_telegram_request_slot().await;
 
-        match request_fn().await {
-            Ok(result) => return Ok(result),
+        let Ok(outcome) = tokio::time::timeout(request_timeout, request_fn()).await else {
+            log::warn!(
+                "{} timed out after {}s",
+                operation_name,
+                request_timeout.as_secs()
+            );
+            // A socket left hanging (e.g. by laptop sleep) only shows up as a timeout;
+            // drop it so the next request dials a fresh connection
+            if let Some(connection) = active_connection() {
+                connection.set_online(false);
+                connection.drop_home_connection();
+            }
+            return Err(E::request_timed_out());
+        };
+
+        match outcome {
+            Ok(result) => {
+                if let Some(connection) = active_connection() {
+                    connection.set_online(true);
+                }
+                return Ok(result);
+            }
             Err(error) => {
                 let error_message = error.to_string();
                 let Some(wait_seconds) = parse_flood_wait_seconds(&error_message) else {
+                    if request_error_code(&error) == error_code::OFFLINE {
+                        if let Some(connection) = active_connection() {
+                            connection.set_online(false);
+                            if connection_retries < policy.connection_retries
+                                && connection.drop_home_connection()
+                            {
+                                let backoff = policy
+                                    .connection_backoff
+                                    .saturating_mul(1 << connection_retries.min(16));
+                                connection_retries += 1;
+                                log::warn!(
+                                    "{} lost its connection, reconnecting and retrying ({}/{})",
+                                    operation_name,
+                                    connection_retries,
+                                    policy.connection_retries
+                                );
+                                if !backoff.is_zero() {
+                                    tokio::time::sleep(backoff).await;
+                                }
+                                continue;
+                            }
+                        }
+                    }
                     return Err(error);
                 };
 
-                if flood_wait_retries >= TELEGRAM_FLOOD_WAIT_RETRY_LIMIT {
+                if flood_wait_retries >= flood_wait_retry_limit {
                     log::warn!(
                         "{} hit Telegram flood wait ({}s) and retries were exhausted",
                         operation_name,
@@ -217,9 +756,14 @@ where
                     operation_name,
                     wait_seconds,
                     flood_wait_retries,
-                    TELEGRAM_FLOOD_WAIT_RETRY_LIMIT
+                    flood_wait_retry_limit
                 );
 
+                emit_flood_wait(operation_name, wait_seconds.max(1));
+                if let Ok(mut flood_wait_until) = TELEGRAM_FLOOD_WAIT_UNTIL.lock() {
+                    *flood_wait_until =
+                        Some(Instant::now() + Duration::from_secs(wait_seconds.max(1)));
+                }
                 tokio::time::sleep(Duration::from_secs(wait_seconds.max(1))).await;
             }
         }
@@ -284,6 +828,7 @@ pub fn get_api_hash() -> &'static str {
 }
 // ===== Modules =====
 
+mod connection;
 mod health;
 mod login;
 pub mod messages;
@@ -299,22 +844,38 @@ use login::{
     tg_request_auth_code_impl, tg_sign_in_with_code_impl, tg_sign_in_with_password_impl,
 };
 
-use health::tg_ping_impl;
-use session::{tg_logout_impl, tg_restore_session_impl};
+use connection::{tg_connection_status_impl, tg_reconnect_impl};
+use health::{tg_get_diagnostics_impl, tg_ping_impl};
+use session::{
+    tg_get_account_limits_impl, tg_get_me_impl, tg_is_authorized_impl,
+    tg_list_active_sessions_impl, tg_logout_impl, tg_restore_session_impl,
+    tg_terminate_session_impl,
+};
 
 use photo::tg_get_my_profile_photo_impl;
 use sync::initialize_sync_task;
+pub(crate) use sync::watch_live_sync_setting;
 
+pub(crate) use messages::migrate_legacy_thumbnail_cache;
+pub use messages::run_pending_upload_queue;
 use messages::{
-    tg_backfill_saved_messages_batch_impl, tg_cancel_saved_file_download_impl,
-    tg_cancel_saved_file_upload_impl, tg_create_saved_folder_impl,
-    tg_delete_saved_item_permanently_impl, tg_download_saved_file_impl,
-    tg_edit_saved_note_message_impl, tg_get_indexed_saved_messages_impl,
-    tg_get_message_thumbnail_impl, tg_index_saved_messages_impl, tg_list_saved_items_impl,
+    classify_file_impl, clear_thumbnail_cache_impl, get_thumbnail_cache_size_impl, search_all_impl,
+    tg_backfill_saved_messages_batch_impl, tg_cancel_indexing_impl, tg_cancel_pending_upload_impl,
+    tg_cancel_saved_file_download_impl, tg_cancel_saved_file_upload_impl,
+    tg_create_saved_folder_impl, tg_delete_saved_item_permanently_impl,
+    tg_download_saved_file_impl, tg_download_saved_folder_impl, tg_edit_saved_note_message_impl,
+    tg_fix_missing_extensions_impl, tg_forget_saved_item_impl, tg_get_backfill_status_impl,
+    tg_get_folder_counts_impl, tg_get_folder_preview_thumbnail_impl,
+    tg_get_indexed_saved_messages_impl, tg_get_message_thumbnail_data_url_impl,
+    tg_get_message_thumbnail_impl, tg_get_saved_item_impl, tg_index_saved_messages_impl,
+    tg_list_pending_uploads_impl, tg_list_recent_saved_items_impl, tg_list_saved_items_impl,
     tg_list_saved_items_page_impl, tg_move_saved_item_impl, tg_move_saved_item_to_recycle_bin_impl,
-    tg_prefetch_message_thumbnails_impl, tg_prepare_saved_media_preview_impl,
-    tg_rebuild_saved_items_index_impl, tg_rename_saved_item_impl, tg_restore_saved_item_impl,
-    tg_send_saved_note_message_impl, tg_upload_file_to_saved_messages_impl,
+    tg_prefetch_folder_thumbnails_impl, tg_prefetch_message_thumbnails_impl,
+    tg_prepare_saved_media_preview_impl, tg_prepare_text_preview_impl,
+    tg_rebuild_saved_items_index_impl, tg_rename_saved_item_impl,
+    tg_restore_all_recycle_items_impl, tg_restore_saved_item_impl, tg_retry_pending_upload_impl,
+    tg_send_saved_note_message_impl, tg_set_saved_item_pinned_impl,
+    tg_upload_file_to_saved_messages_impl, tg_upload_local_file_impl,
 };
 
 // ===== Tauri Commands =====
@@ -340,13 +901,19 @@ pub async fn tg_sign_in_with_password(
 }
 
 #[tauri::command]
-pub async fn tg_generate_qr_code(app: tauri::AppHandle) -> Result<QrLoginData, TelegramError> {
-    tg_generate_qr_code_impl(app).await
+pub async fn tg_generate_qr_code(
+    app: tauri::AppHandle,
+    include_png: Option<bool>,
+) -> Result<QrLoginData, TelegramError> {
+    tg_generate_qr_code_impl(app, include_png.unwrap_or(false)).await
 }
 
 #[tauri::command]
-pub async fn tg_poll_qr_login(app: tauri::AppHandle) -> Result<QrPollResult, TelegramError> {
-    tg_poll_qr_login_impl(app).await
+pub async fn tg_poll_qr_login(
+    app: tauri::AppHandle,
+    db: State<'_, crate::db::Database>,
+) -> Result<QrPollResult, TelegramError> {
+    tg_poll_qr_login_impl(app, db.inner().clone()).await
 }
 
 #[tauri::command]
@@ -358,9 +925,10 @@ pub async fn tg_cancel_qr_login() -> Result<bool, TelegramError> {
 #[tauri::command]
 pub async fn tg_restore_session(
     db: State<'_, crate::db::Database>,
-    session_data: String,
+    session_data: Option<String>,
+    user_id: Option<i64>,
 ) -> Result<TelegramAuthResult, TelegramError> {
-    tg_restore_session_impl(db, session_data).await
+    tg_restore_session_impl(db, session_data, user_id).await
 }
 
 #[tauri::command]
@@ -369,8 +937,51 @@ pub async fn tg_ping() -> Result<bool, TelegramError> {
 }
 
 #[tauri::command]
-pub async fn tg_logout() -> Result<bool, TelegramError> {
-    tg_logout_impl().await
+pub async fn tg_get_diagnostics() -> Result<TelegramDiagnostics, TelegramError> {
+    tg_get_diagnostics_impl().await
+}
+
+#[tauri::command]
+pub async fn tg_connection_status() -> Result<ConnectionStatus, TelegramError> {
+    tg_connection_status_impl().await
+}
+
+#[tauri::command]
+pub async fn tg_reconnect() -> Result<bool, TelegramError> {
+    tg_reconnect_impl().await
+}
+
+#[tauri::command]
+pub async fn tg_logout(
+    db: State<'_, Database>,
+    wipe_local_data: Option<bool>,
+) -> Result<bool, TelegramError> {
+    tg_logout_impl(db.inner().clone(), wipe_local_data.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn tg_list_active_sessions() -> Result<Vec<ActiveSession>, TelegramError> {
+    tg_list_active_sessions_impl().await
+}
+
+#[tauri::command]
+pub async fn tg_terminate_session(hash: String) -> Result<bool, TelegramError> {
+    tg_terminate_session_impl(hash).await
+}
+
+#[tauri::command]
+pub async fn tg_is_authorized(db: State<'_, Database>) -> Result<bool, TelegramError> {
+    tg_is_authorized_impl(db.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn tg_get_me(db: State<'_, Database>) -> Result<UserInfo, TelegramError> {
+    tg_get_me_impl(db.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn tg_get_account_limits() -> Result<AccountLimits, TelegramError> {
+    tg_get_account_limits_impl().await
 }
 
 #[tauri::command]
@@ -380,11 +991,18 @@ pub async fn tg_get_my_profile_photo(
     tg_get_my_profile_photo_impl(db).await
 }
 
+#[tauri::command]
+pub async fn classify_file(name_or_extension: String) -> Result<FileClassification, TelegramError> {
+    classify_file_impl(name_or_extension)
+}
+
 #[tauri::command]
 pub async fn tg_index_saved_messages(
+    app: tauri::AppHandle,
     db: State<'_, crate::db::Database>,
-) -> Result<serde_json::Value, TelegramError> {
-    tg_index_saved_messages_impl(db.inner().clone()).await
+    max_messages: Option<usize>,
+) -> Result<IndexSummary, TelegramError> {
+    tg_index_saved_messages_impl(app, db.inner().clone(), max_messages).await
 }
 
 #[tauri::command]
@@ -395,6 +1013,14 @@ pub async fn tg_get_indexed_saved_messages(
     tg_get_indexed_saved_messages_impl(db.inner().clone(), category).await
 }
 
+#[tauri::command]
+pub async fn tg_get_saved_item(
+    db: State<'_, crate::db::Database>,
+    source_path: String,
+) -> Result<Option<crate::db::TelegramSavedItem>, TelegramError> {
+    tg_get_saved_item_impl(db.inner().clone(), source_path).await
+}
+
 #[tauri::command]
 pub async fn tg_list_saved_items(
     db: State<'_, crate::db::Database>,
@@ -403,6 +1029,14 @@ pub async fn tg_list_saved_items(
     tg_list_saved_items_impl(db.inner().clone(), file_path).await
 }
 
+#[tauri::command]
+pub async fn tg_list_recent_saved_items(
+    db: State<'_, crate::db::Database>,
+    limit: Option<i64>,
+) -> Result<Vec<crate::db::TelegramSavedItem>, TelegramError> {
+    tg_list_recent_saved_items_impl(db.inner().clone(), limit).await
+}
+
 #[tauri::command]
 pub async fn tg_list_saved_items_page(
     db: State<'_, crate::db::Database>,
@@ -417,10 +1051,22 @@ pub async fn tg_list_saved_items_page(
 pub async fn tg_backfill_saved_messages_batch(
     db: State<'_, crate::db::Database>,
     batch_size: Option<i32>,
-) -> Result<serde_json::Value, TelegramError> {
+) -> Result<BackfillSummary, TelegramError> {
     tg_backfill_saved_messages_batch_impl(db.inner().clone(), batch_size).await
 }
 
+#[tauri::command]
+pub async fn tg_get_backfill_status(
+    db: State<'_, crate::db::Database>,
+) -> Result<serde_json::Value, TelegramError> {
+    tg_get_backfill_status_impl(db.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn tg_cancel_indexing() -> Result<bool, TelegramError> {
+    tg_cancel_indexing_impl()
+}
+
 #[tauri::command]
 pub async fn tg_rebuild_saved_items_index(
     db: State<'_, crate::db::Database>,
@@ -428,12 +1074,19 @@ pub async fn tg_rebuild_saved_items_index(
     tg_rebuild_saved_items_index_impl(db.inner().clone()).await
 }
 
+#[tauri::command]
+pub async fn tg_fix_missing_extensions(
+    db: State<'_, crate::db::Database>,
+) -> Result<serde_json::Value, TelegramError> {
+    tg_fix_missing_extensions_impl(db.inner().clone()).await
+}
+
 #[tauri::command]
 pub async fn tg_create_saved_folder(
     db: State<'_, crate::db::Database>,
     parent_path: String,
     folder_name: String,
-) -> Result<crate::db::TelegramSavedItem, TelegramError> {
+) -> Result<CreatedSavedFolder, TelegramError> {
     tg_create_saved_folder_impl(db.inner().clone(), parent_path, folder_name).await
 }
 
@@ -462,12 +1115,53 @@ pub async fn tg_restore_saved_item(
     tg_restore_saved_item_impl(db.inner().clone(), source_path).await
 }
 
+#[tauri::command]
+pub async fn tg_restore_all_recycle_items(
+    db: State<'_, crate::db::Database>,
+) -> Result<serde_json::Value, TelegramError> {
+    tg_restore_all_recycle_items_impl(db.inner().clone()).await
+}
+
 #[tauri::command]
 pub async fn tg_delete_saved_item_permanently(
     db: State<'_, crate::db::Database>,
     source_path: String,
+    dry_run: Option<bool>,
+) -> Result<serde_json::Value, TelegramError> {
+    tg_delete_saved_item_permanently_impl(db.inner().clone(), source_path, dry_run.unwrap_or(false))
+        .await
+}
+
+#[tauri::command]
+pub async fn tg_forget_saved_item(
+    db: State<'_, crate::db::Database>,
+    source_path: String,
+) -> Result<(), TelegramError> {
+    tg_forget_saved_item_impl(db.inner().clone(), source_path).await
+}
+
+#[tauri::command]
+pub async fn tg_pin_saved_item(
+    db: State<'_, crate::db::Database>,
+    source_path: String,
+) -> Result<(), TelegramError> {
+    tg_set_saved_item_pinned_impl(db.inner().clone(), source_path, true).await
+}
+
+#[tauri::command]
+pub async fn tg_unpin_saved_item(
+    db: State<'_, crate::db::Database>,
+    source_path: String,
 ) -> Result<(), TelegramError> {
-    tg_delete_saved_item_permanently_impl(db.inner().clone(), source_path).await
+    tg_set_saved_item_pinned_impl(db.inner().clone(), source_path, false).await
+}
+
+#[tauri::command]
+pub async fn search_all(
+    db: State<'_, crate::db::Database>,
+    query: String,
+) -> Result<SearchAllResults, TelegramError> {
+    search_all_impl(db.inner().clone(), query).await
 }
 
 #[tauri::command]
@@ -475,7 +1169,7 @@ pub async fn tg_rename_saved_item(
     db: State<'_, crate::db::Database>,
     source_path: String,
     new_name: String,
-) -> Result<(), TelegramError> {
+) -> Result<serde_json::Value, TelegramError> {
     tg_rename_saved_item_impl(db.inner().clone(), source_path, new_name).await
 }
 
@@ -483,8 +1177,9 @@ pub async fn tg_rename_saved_item(
 pub async fn tg_send_saved_note_message(
     db: State<'_, crate::db::Database>,
     text: String,
+    file_path: Option<String>,
 ) -> Result<crate::db::TelegramMessage, TelegramError> {
-    tg_send_saved_note_message_impl(db.inner().clone(), text).await
+    tg_send_saved_note_message_impl(db.inner().clone(), text, file_path).await
 }
 
 #[tauri::command]
@@ -492,7 +1187,7 @@ pub async fn tg_edit_saved_note_message(
     db: State<'_, crate::db::Database>,
     source_path: String,
     text: String,
-) -> Result<(), TelegramError> {
+) -> Result<crate::db::TelegramMessage, TelegramError> {
     tg_edit_saved_note_message_impl(db.inner().clone(), source_path, text).await
 }
 
@@ -504,6 +1199,24 @@ pub async fn tg_get_message_thumbnail(
     tg_get_message_thumbnail_impl(db.inner().clone(), message_id).await
 }
 
+#[tauri::command]
+pub async fn tg_get_message_thumbnail_data_url(
+    db: State<'_, crate::db::Database>,
+    message_id: i32,
+) -> Result<Option<String>, TelegramError> {
+    tg_get_message_thumbnail_data_url_impl(db.inner().clone(), message_id).await
+}
+
+#[tauri::command]
+pub async fn get_thumbnail_cache_size() -> Result<u64, TelegramError> {
+    get_thumbnail_cache_size_impl().await
+}
+
+#[tauri::command]
+pub async fn clear_thumbnail_cache() -> Result<u64, TelegramError> {
+    clear_thumbnail_cache_impl().await
+}
+
 #[tauri::command]
 pub async fn tg_prefetch_message_thumbnails(
     db: State<'_, crate::db::Database>,
@@ -512,13 +1225,56 @@ pub async fn tg_prefetch_message_thumbnails(
     tg_prefetch_message_thumbnails_impl(db.inner().clone(), message_ids).await
 }
 
+#[tauri::command]
+pub async fn tg_get_folder_preview_thumbnail(
+    db: State<'_, crate::db::Database>,
+    file_path: String,
+) -> Result<Option<String>, TelegramError> {
+    tg_get_folder_preview_thumbnail_impl(db.inner().clone(), file_path).await
+}
+
+#[tauri::command]
+pub async fn tg_get_folder_counts(
+    db: State<'_, crate::db::Database>,
+    file_paths: Vec<String>,
+) -> Result<std::collections::HashMap<String, i64>, TelegramError> {
+    tg_get_folder_counts_impl(db.inner().clone(), file_paths).await
+}
+
+#[tauri::command]
+pub async fn tg_prefetch_folder_thumbnails(
+    db: State<'_, crate::db::Database>,
+    file_path: String,
+) -> Result<serde_json::Value, TelegramError> {
+    tg_prefetch_folder_thumbnails_impl(db.inner().clone(), file_path).await
+}
+
 #[tauri::command]
 pub async fn tg_download_saved_file(
     app: tauri::AppHandle,
     db: State<'_, crate::db::Database>,
     source_path: String,
+    destination_path: Option<String>,
+    overwrite: Option<bool>,
 ) -> Result<Option<String>, TelegramError> {
-    tg_download_saved_file_impl(app, db.inner().clone(), source_path).await
+    tg_download_saved_file_impl(
+        app,
+        db.inner().clone(),
+        source_path,
+        destination_path,
+        overwrite.unwrap_or(false),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn tg_download_saved_folder(
+    app: tauri::AppHandle,
+    db: State<'_, crate::db::Database>,
+    source_path: String,
+    destination_dir: String,
+) -> Result<serde_json::Value, TelegramError> {
+    tg_download_saved_folder_impl(app, db.inner().clone(), source_path, destination_dir).await
 }
 
 #[tauri::command]
@@ -527,8 +1283,11 @@ pub async fn tg_cancel_saved_file_download(source_path: String) -> Result<bool,
 }
 
 #[tauri::command]
-pub async fn tg_cancel_saved_file_upload(file_name: String) -> Result<bool, TelegramError> {
-    tg_cancel_saved_file_upload_impl(file_name)
+pub async fn tg_cancel_saved_file_upload(
+    upload_id: Option<String>,
+    file_name: Option<String>,
+) -> Result<bool, TelegramError> {
+    tg_cancel_saved_file_upload_impl(upload_id, file_name)
 }
 
 #[tauri::command]
@@ -536,10 +1295,20 @@ pub async fn tg_prepare_saved_media_preview(
     app: tauri::AppHandle,
     db: State<'_, crate::db::Database>,
     source_path: String,
-) -> Result<String, TelegramError> {
+) -> Result<MediaPreview, TelegramError> {
     tg_prepare_saved_media_preview_impl(app, db.inner().clone(), source_path).await
 }
 
+#[tauri::command]
+pub async fn tg_prepare_text_preview(
+    app: tauri::AppHandle,
+    db: State<'_, crate::db::Database>,
+    source_path: String,
+    max_bytes: Option<u64>,
+) -> Result<serde_json::Value, TelegramError> {
+    tg_prepare_text_preview_impl(app, db.inner().clone(), source_path, max_bytes).await
+}
+
 #[tauri::command]
 pub async fn tg_upload_file_to_saved_messages(
     app: tauri::AppHandle,
@@ -547,9 +1316,51 @@ pub async fn tg_upload_file_to_saved_messages(
     file_name: String,
     file_bytes: Vec<u8>,
     file_path: Option<String>,
+    on_conflict: Option<String>,
 ) -> Result<crate::db::TelegramMessage, TelegramError> {
-    tg_upload_file_to_saved_messages_impl(app, db.inner().clone(), file_name, file_bytes, file_path)
-        .await
+    tg_upload_file_to_saved_messages_impl(
+        app,
+        db.inner().clone(),
+        file_name,
+        file_bytes,
+        file_path,
+        on_conflict,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn tg_upload_local_file(
+    app: tauri::AppHandle,
+    db: State<'_, crate::db::Database>,
+    local_path: String,
+    file_path: Option<String>,
+    on_conflict: Option<String>,
+) -> Result<crate::db::TelegramMessage, TelegramError> {
+    tg_upload_local_file_impl(app, db.inner().clone(), local_path, file_path, on_conflict).await
+}
+
+#[tauri::command]
+pub async fn tg_list_pending_uploads(
+    db: State<'_, crate::db::Database>,
+) -> Result<Vec<crate::db::PendingUpload>, TelegramError> {
+    tg_list_pending_uploads_impl(db.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn tg_cancel_pending_upload(
+    db: State<'_, crate::db::Database>,
+    id: i64,
+) -> Result<(), TelegramError> {
+    tg_cancel_pending_upload_impl(db.inner().clone(), id).await
+}
+
+#[tauri::command]
+pub async fn tg_retry_pending_upload(
+    db: State<'_, crate::db::Database>,
+    id: i64,
+) -> Result<(), TelegramError> {
+    tg_retry_pending_upload_impl(db.inner().clone(), id).await
 }
 
 // ===== Utility Functions =====
@@ -566,6 +1377,26 @@ pub async fn tg_start_real_time_sync(app: tauri::AppHandle) -> Result<(), Telegr
 
 // ===== Utility Functions =====
 
+// Stop a client taken out of AUTH_STATE: its sender pool first, then the client itself
+pub(crate) fn stop_auth_state(state: AuthState) {
+    state.pool_handle.quit();
+    state.pool_task.abort();
+    state.client.disconnect();
+}
+
+// Install the state of a new login flow, stopping the client of the flow it replaces
+pub(crate) async fn replace_auth_state(state: AuthState, caller: &str) {
+    let previous = AUTH_STATE.lock().await.replace(state);
+    if let Some(previous) = previous {
+        log::info!(
+            "{}: Stopping client of previous flow (flow_id={})",
+            caller,
+            previous.flow_id
+        );
+        stop_auth_state(previous);
+    }
+}
+
 // Function to disconnect the Telegram client gracefully when the app closes
 pub async fn disconnect_client() {
     log::info!("Disconnecting Telegram client in background...");
@@ -591,16 +1422,9 @@ pub async fn disconnect_client() {
         Some(state) => {
             log::info!("Found active Telegram client, initiating disconnect sequence...");
 
-            // Stop the sender pool first (non-blocking)
-            state.pool_handle.quit();
-            state.pool_task.abort();
+            stop_auth_state(state);
 
-            log::info!("Pool stopped, disconnecting client...");
-
-            // Disconnect the client gracefully
-            state.client.disconnect();
-
-            log::info!("Client disconnect initiated");
+            log::info!("Pool stopped, client disconnect initiated");
 
             // Give a small delay to ensure cleanup completes
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
@@ -612,3 +1436,112 @@ pub async fn disconnect_client() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::task::AbortHandle;
+
+    // Both tests swap the process-wide AUTH_STATE
+    static AUTH_STATE_TEST_LOCK: Mutex<()> = Mutex::const_new(());
+
+    // A client that never signs in; the pool only connects once a request is sent
+    fn idle_auth_state(flow_id: u64) -> (AuthState, AbortHandle) {
+        let _ = API_ID_CELL.set(1);
+
+        #[allow(deprecated)]
+        let session = Arc::new(TlSession::new());
+        let built = utils::build_client(Arc::clone(&session));
+        let pool_task = built.pool_task.abort_handle();
+        let connection = ConnectionHealth::register(&built.pool_handle, &session);
+
+        let state = AuthState {
+            client: built.client,
+            session,
+            pool_handle: built.pool_handle,
+            pool_task: built.pool_task,
+            updates: built.updates,
+            login_token: None,
+            password_token: None,
+            phone_number: None,
+            flow_id,
+            qr_state: None,
+            is_migrating: false,
+            current_dc_id: None,
+            owner_context: None,
+            user_info: None,
+            connection,
+            account_limits: None,
+        };
+        (state, pool_task)
+    }
+
+    #[tokio::test]
+    async fn repeated_auth_flows_stop_the_previous_pool() {
+        let _auth_state_lock = AUTH_STATE_TEST_LOCK.lock().await;
+        let mut pool_tasks = Vec::new();
+        for flow_id in 1..=5 {
+            let (state, pool_task) = idle_auth_state(flow_id);
+            replace_auth_state(state, "repeated_auth_flows_stop_the_previous_pool").await;
+            pool_tasks.push(pool_task);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let (current, previous) = pool_tasks.split_last().unwrap();
+        assert!(previous.iter().all(AbortHandle::is_finished));
+        assert!(!current.is_finished());
+        assert_eq!(
+            AUTH_STATE.lock().await.as_ref().map(|state| state.flow_id),
+            Some(5)
+        );
+
+        if let Some(state) = AUTH_STATE.lock().await.take() {
+            stop_auth_state(state);
+        }
+    }
+
+    #[tokio::test]
+    async fn listing_proceeds_while_an_index_holds_the_client() {
+        let _auth_state_lock = AUTH_STATE_TEST_LOCK.lock().await;
+        let (mut state, _) = idle_auth_state(1);
+        state.owner_context = Some((42, tl::enums::InputPeer::PeerSelf));
+        replace_auth_state(state, "listing_proceeds_while_an_index_holds_the_client").await;
+
+        let db = crate::db::TempDatabase::new("listing-while-indexing");
+
+        // A mock index loop: clone the client out of the guard, then keep working with it
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let indexer = tokio::spawn(async {
+            let client = {
+                let state_guard = AUTH_STATE.lock().await;
+                state_guard.as_ref().unwrap().client.clone()
+            };
+            let _ = started_tx.send(());
+            loop {
+                let _ = &client;
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+        started_rx.await.unwrap();
+
+        let owner = tokio::time::timeout(Duration::from_millis(250), current_owner_context())
+            .await
+            .expect("owner lookup is not blocked by the index")
+            .unwrap();
+        assert_eq!(owner.1, "42");
+
+        let items = tokio::time::timeout(
+            Duration::from_millis(250),
+            messages::tg_list_saved_items_impl((*db).clone(), "/Home".to_string()),
+        )
+        .await
+        .expect("listing is not blocked by the index")
+        .unwrap();
+        assert!(items.iter().any(|item| item.file_name == "Images"));
+
+        indexer.abort();
+        if let Some(state) = AUTH_STATE.lock().await.take() {
+            stop_auth_state(state);
+        }
+    }
+}
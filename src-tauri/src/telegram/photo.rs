@@ -1,4 +1,4 @@
-use super::{run_telegram_request, TelegramError, AUTH_STATE};
+use super::{error_code, request_error_code, run_telegram_request, TelegramError, AUTH_STATE};
 use crate::db::Database;
 use base64::Engine;
 use grammers_client::grammers_tl_types as tl;
@@ -28,6 +28,7 @@ pub async fn tg_get_my_profile_photo_impl(
         let guard = AUTH_STATE.lock().await;
         let state = guard.as_ref().ok_or_else(|| TelegramError {
             message: "Not authorized. Please log in first".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
         state.client.clone()
     };
@@ -46,6 +47,7 @@ pub async fn tg_get_my_profile_photo_impl(
             );
             return Err(TelegramError {
                 message: format!("Failed to get user info: {}", e),
+                code: request_error_code(&e),
             });
         }
     };
@@ -226,5 +228,11 @@ pub async fn tg_get_my_profile_photo_impl(
         ),
     }
 
+    if let Some(state) = AUTH_STATE.lock().await.as_mut() {
+        if let Some(user_info) = state.user_info.as_mut() {
+            user_info.profile_photo = Some(data_url.clone());
+        }
+    }
+
     Ok(Some(data_url))
 }
@@ -1,7 +1,15 @@
+use super::connection::ConnectionHealth;
+use super::messages::clear_local_media_caches;
 use super::utils::{build_client, decode_session};
-use super::{run_telegram_request, AUTH_STATE};
-use super::{AuthState, TelegramAuthResult, TelegramError, UserInfo};
+#[allow(deprecated)]
+use super::TlSession;
+use super::{
+    error_code, input_peer_for_user, request_error_code, run_telegram_request, AUTH_STATE,
+};
+use super::{AccountLimits, ActiveSession, AuthState, TelegramAuthResult, TelegramError, UserInfo};
 use crate::db::Database;
+use grammers_client::grammers_tl_types as tl;
+use grammers_session::Session as _;
 use log;
 use std::sync::Arc;
 use tauri::State;
@@ -17,18 +25,93 @@ pub(crate) async fn ensure_basic_connectivity() -> Result<(), TelegramError> {
         Ok(Ok(_stream)) => Ok(()),
         Ok(Err(e)) => Err(TelegramError {
             message: format!("Basic connectivity check failed: {e}"),
+            code: error_code::OFFLINE.to_string(),
         }),
         Err(_) => Err(TelegramError {
             message: "Basic connectivity check timed out".to_string(),
+            code: error_code::TIMEOUT.to_string(),
         }),
     }
 }
 
+// Reject a stored session that cannot work before a client is started on it:
+// it must be base64 of a TL session, as `encode_session` writes it, and hold an
+// auth key for its home DC.
+#[allow(deprecated)]
+fn validate_session_data(session_data: &str) -> Result<TlSession, TelegramError> {
+    let corrupt = |reason: String| TelegramError {
+        message: format!("Stored session is corrupt ({reason}), please log in again"),
+        code: error_code::CORRUPT_SESSION.to_string(),
+    };
+
+    let session = decode_session(session_data.trim()).map_err(|e| corrupt(e.message))?;
+
+    let home_dc_id = session.home_dc_id();
+    if session
+        .dc_option(home_dc_id)
+        .and_then(|dc_option| dc_option.auth_key)
+        .is_none()
+    {
+        return Err(corrupt(format!("no auth key for home DC {home_dc_id}")));
+    }
+
+    Ok(session)
+}
+
 pub async fn tg_restore_session_impl(
     db: State<'_, Database>,
-    session_data: String,
+    session_data: Option<String>,
+    user_id: Option<i64>,
 ) -> Result<TelegramAuthResult, TelegramError> {
-    log::info!("tg_restore_session_impl: Starting session restore");
+    log::info!(
+        "tg_restore_session_impl: Starting session restore (user_id={:?})",
+        user_id
+    );
+
+    // Restoring a specific account makes it the active one and uses its stored session
+    if let Some(user_id) = user_id {
+        db.switch_active_session(user_id)
+            .map_err(|e| TelegramError {
+                message: format!("Failed to switch account: {}", e.message),
+                code: error_code::DATABASE.to_string(),
+            })?;
+    }
+
+    // Only a session read from the database may be cleared below; a bad
+    // `session_data` argument says nothing about the stored one
+    let (session_data, loaded_from_db) = match session_data.filter(|data| !data.trim().is_empty()) {
+        Some(data) if user_id.is_none() => (data, false),
+        _ => (
+            db.get_session()
+                .map_err(|e| TelegramError {
+                    message: format!("Failed to load stored session: {}", e.message),
+                    code: error_code::DATABASE.to_string(),
+                })?
+                .and_then(|s| s.session_data)
+                .ok_or_else(|| TelegramError {
+                    message: "No stored session to restore".to_string(),
+                    code: error_code::NOT_AUTHORIZED.to_string(),
+                })?,
+            true,
+        ),
+    };
+
+    let loaded = match validate_session_data(&session_data) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            log::warn!("tg_restore_session_impl: {}", e.message);
+            if !loaded_from_db {
+                return Err(e);
+            }
+            if let Err(db_err) = db.clear_session() {
+                log::error!(
+                    "tg_restore_session_impl: Failed to clear corrupt session: {}",
+                    db_err.message
+                );
+            }
+            return Err(e);
+        }
+    };
 
     // Fast path: if we appear offline, avoid spinning up the Telegram client
     // at all. This prevents native stack overflows when the runtime repeatedly
@@ -41,10 +124,10 @@ pub async fn tg_restore_session_impl(
         return Err(TelegramError {
             message: "Network appears offline or unreachable. Please check your connection and try again."
                 .to_string(),
+            code: error_code::OFFLINE.to_string(),
         });
     }
 
-    let loaded = decode_session(&session_data)?;
     let session = Arc::new(loaded);
 
     log::info!("tg_restore_session_impl: Session decoded successfully");
@@ -74,24 +157,41 @@ pub async fn tg_restore_session_impl(
             built.pool_handle.quit();
             built.pool_task.abort();
 
-            // Check if it's an Auth Key error (401)
-            let msg = e.to_string();
-            if msg.contains("AUTH_KEY_UNREGISTERED") || msg.contains("401") {
-                log::warn!("tg_restore_session_impl: Session is invalid (Auth Key Unregistered). Clearing database.");
-                if let Err(db_err) = db.clear_session() {
-                    log::error!(
-                        "tg_restore_session_impl: Failed to clear invalid session: {}",
-                        db_err.message
+            // Only a rejected authorization invalidates the stored session; network
+            // failures leave it in place for the next attempt
+            let code = request_error_code(&e);
+            if code == error_code::NOT_AUTHORIZED || e.to_string().contains("401") {
+                if loaded_from_db {
+                    log::warn!(
+                        "tg_restore_session_impl: Session was revoked or expired. Clearing database."
                     );
+                    if let Err(db_err) = db.clear_session() {
+                        log::error!(
+                            "tg_restore_session_impl: Failed to clear invalid session: {}",
+                            db_err.message
+                        );
+                    }
                 }
+
+                return Err(TelegramError {
+                    message: format!("Session has expired, please log in again: {e}"),
+                    code: error_code::SESSION_EXPIRED.to_string(),
+                });
             }
 
             return Err(TelegramError {
-                message: format!("Session is not valid: {e}"),
+                message: format!("Could not verify session: {e}"),
+                code,
             });
         }
     };
 
+    let owner_context = input_peer_for_user(&me)
+        .ok()
+        .map(|input_peer| (me.raw.id(), input_peer));
+
+    let connection = ConnectionHealth::register(&built.pool_handle, &session);
+
     // Store restored state ONCE (including pool fields)
     let mut guard = AUTH_STATE.lock().await;
     *guard = Some(AuthState {
@@ -107,6 +207,10 @@ pub async fn tg_restore_session_impl(
         qr_state: None,
         is_migrating: false,
         current_dc_id: None, // Will be determined when needed
+        owner_context,
+        user_info: None,
+        account_limits: None,
+        connection,
     });
 
     log::info!(
@@ -128,6 +232,18 @@ pub async fn tg_restore_session_impl(
         profile_photo: cached_photo,
     };
 
+    if let Some(state) = guard.as_mut() {
+        state.user_info = Some(user_info.clone());
+    }
+    drop(guard);
+
+    if let Err(e) = db.set_active_session_user_id(user_info.id) {
+        log::warn!(
+            "tg_restore_session_impl: Failed to key session by user id: {}",
+            e.message
+        );
+    }
+
     // Cache user info in database
     match db.update_session_user_info(
         user_info.first_name.as_deref(),
@@ -149,7 +265,159 @@ pub async fn tg_restore_session_impl(
     })
 }
 
-pub async fn tg_logout_impl() -> Result<bool, TelegramError> {
+// Local-only check: a stored session counts because tg_restore_session can revive it
+pub async fn tg_is_authorized_impl(db: Database) -> Result<bool, TelegramError> {
+    if AUTH_STATE.lock().await.is_some() {
+        return Ok(true);
+    }
+
+    let session = db.get_session().map_err(|e| TelegramError {
+        message: format!("Failed to read stored session: {}", e.message),
+        code: error_code::DATABASE.to_string(),
+    })?;
+
+    Ok(session.is_some())
+}
+
+pub async fn tg_get_me_impl(db: Database) -> Result<UserInfo, TelegramError> {
+    let (client, session) = {
+        let guard = AUTH_STATE.lock().await;
+        let state = guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+
+        if let Some(user_info) = &state.user_info {
+            return Ok(user_info.clone());
+        }
+
+        (state.client.clone(), Arc::clone(&state.session))
+    };
+
+    let me = run_telegram_request("tg_get_me_impl.get_me", || async { client.get_me().await })
+        .await
+        .map_err(|e| TelegramError {
+            message: format!("Failed to get user info: {}", e),
+            code: request_error_code(&e),
+        })?;
+
+    let cached_photo = match db.get_session() {
+        Ok(Some(s)) => s.profile_photo,
+        _ => None,
+    };
+
+    let user_info = UserInfo {
+        id: me.raw.id(),
+        username: me.username().map(|s| s.to_string()),
+        first_name: me.first_name().map(|s| s.to_string()),
+        last_name: me.last_name().map(|s| s.to_string()),
+        profile_photo: cached_photo,
+    };
+
+    {
+        let mut guard = AUTH_STATE.lock().await;
+        // Skip caching if the session was replaced while get_me was in flight
+        if let Some(state) = guard
+            .as_mut()
+            .filter(|state| Arc::ptr_eq(&state.session, &session))
+        {
+            state.user_info = Some(user_info.clone());
+            if state.owner_context.is_none() {
+                state.owner_context = input_peer_for_user(&me)
+                    .ok()
+                    .map(|input_peer| (user_info.id, input_peer));
+            }
+        }
+    }
+
+    if let Err(e) = db.update_session_user_info(
+        user_info.first_name.as_deref(),
+        user_info.last_name.as_deref(),
+        user_info.username.as_deref(),
+    ) {
+        log::warn!(
+            "tg_get_me_impl: Failed to update user info cache: {}",
+            e.message
+        );
+    }
+
+    Ok(user_info)
+}
+
+// Telegram takes up to 4000 parts of 512 KB per file, or 8000 with Premium
+const MAX_UPLOAD_BYTES: u64 = 4000 * 512 * 1024;
+const PREMIUM_MAX_UPLOAD_BYTES: u64 = 8000 * 512 * 1024;
+
+pub async fn tg_get_account_limits_impl() -> Result<AccountLimits, TelegramError> {
+    let (client, session) = {
+        let guard = AUTH_STATE.lock().await;
+        let state = guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+
+        if let Some(limits) = state.account_limits {
+            return Ok(limits);
+        }
+
+        (state.client.clone(), Arc::clone(&state.session))
+    };
+
+    let me = run_telegram_request("tg_get_account_limits_impl.get_me", || async {
+        client.get_me().await
+    })
+    .await
+    .map_err(|e| TelegramError {
+        message: format!("Failed to get account info: {}", e),
+        code: request_error_code(&e),
+    })?;
+
+    let is_premium = matches!(&me.raw, tl::enums::User::User(user) if user.premium);
+    let limits = AccountLimits {
+        is_premium,
+        max_upload_bytes: if is_premium {
+            PREMIUM_MAX_UPLOAD_BYTES
+        } else {
+            MAX_UPLOAD_BYTES
+        },
+    };
+
+    {
+        let mut guard = AUTH_STATE.lock().await;
+        // Skip caching if the session was replaced while get_me was in flight
+        if let Some(state) = guard
+            .as_mut()
+            .filter(|state| Arc::ptr_eq(&state.session, &session))
+        {
+            state.account_limits = Some(limits);
+        }
+    }
+
+    Ok(limits)
+}
+
+/// Upload limit of the current account. Offline, or when the lookup fails, the
+/// cached limit is used if there is one, otherwise the non-Premium limit.
+pub(crate) async fn upload_size_limit(online: bool) -> u64 {
+    if online {
+        match tg_get_account_limits_impl().await {
+            Ok(limits) => return limits.max_upload_bytes,
+            Err(e) => log::warn!(
+                "upload_size_limit: Could not look up account limits: {}",
+                e.message
+            ),
+        }
+    }
+
+    AUTH_STATE
+        .lock()
+        .await
+        .as_ref()
+        .and_then(|state| state.account_limits)
+        .map_or(MAX_UPLOAD_BYTES, |limits| limits.max_upload_bytes)
+}
+
+pub async fn tg_logout_impl(db: Database, wipe_local_data: bool) -> Result<bool, TelegramError> {
     log::info!("tg_logout_impl: Initiating logout");
 
     // Take the current state out so we can drop/stop it cleanly
@@ -164,6 +432,115 @@ pub async fn tg_logout_impl() -> Result<bool, TelegramError> {
         log::info!("tg_logout_impl: Client pool stopped");
     }
 
+    if wipe_local_data {
+        clear_local_media_caches()?;
+
+        db.clear_session().map_err(|e| TelegramError {
+            message: format!("Failed to clear stored session: {}", e.message),
+            code: error_code::DATABASE.to_string(),
+        })?;
+        log::info!("tg_logout_impl: Local caches and stored session cleared");
+    }
+
     log::info!("tg_logout_impl: Logout completed");
     Ok(true)
 }
+
+pub async fn tg_list_active_sessions_impl() -> Result<Vec<ActiveSession>, TelegramError> {
+    let client = {
+        let state_guard = AUTH_STATE.lock().await;
+        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+        state.client.clone()
+    };
+
+    let tl::enums::account::Authorizations::Authorizations(result) = run_telegram_request(
+        "tg_list_active_sessions_impl.get_authorizations",
+        || async {
+            client
+                .invoke(&tl::functions::account::GetAuthorizations {})
+                .await
+        },
+    )
+    .await
+    .map_err(|e| TelegramError {
+        message: format!("Failed to list active sessions: {}", e),
+        code: request_error_code(&e),
+    })?;
+
+    let sessions = result
+        .authorizations
+        .into_iter()
+        .map(|authorization| {
+            let tl::enums::Authorization::Authorization(auth) = authorization;
+            ActiveSession {
+                hash: auth.hash.to_string(),
+                device_model: auth.device_model,
+                platform: auth.platform,
+                system_version: auth.system_version,
+                app_name: auth.app_name,
+                app_version: auth.app_version,
+                ip: auth.ip,
+                country: auth.country,
+                region: auth.region,
+                date_created: auth.date_created as i64,
+                date_active: auth.date_active as i64,
+                is_current: auth.current,
+                is_official_app: auth.official_app,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    log::info!(
+        "tg_list_active_sessions_impl: Found {} active sessions",
+        sessions.len()
+    );
+
+    Ok(sessions)
+}
+
+pub async fn tg_terminate_session_impl(hash: String) -> Result<bool, TelegramError> {
+    let hash = hash.trim().parse::<i64>().map_err(|_| TelegramError {
+        message: format!("Invalid session hash: {}", hash),
+        code: error_code::INVALID_INPUT.to_string(),
+    })?;
+
+    // Telegram reports the current session with hash 0; use tg_logout for that
+    if hash == 0 {
+        return Err(TelegramError {
+            message: "Cannot terminate the current session".to_string(),
+            code: error_code::INVALID_INPUT.to_string(),
+        });
+    }
+
+    let client = {
+        let state_guard = AUTH_STATE.lock().await;
+        let state = state_guard.as_ref().ok_or_else(|| TelegramError {
+            message: "Not authorized".to_string(),
+            code: error_code::NOT_AUTHORIZED.to_string(),
+        })?;
+        state.client.clone()
+    };
+
+    let terminated =
+        run_telegram_request("tg_terminate_session_impl.reset_authorization", || async {
+            client
+                .invoke(&tl::functions::account::ResetAuthorization { hash })
+                .await
+        })
+        .await
+        .map_err(|e| TelegramError {
+            message: format!("Failed to terminate session: {}", e),
+            code: request_error_code(&e),
+        })?;
+
+    log::info!(
+        "tg_terminate_session_impl: hash={} terminated={}",
+        hash,
+        terminated
+    );
+
+    Ok(terminated)
+}
@@ -1,19 +1,60 @@
-use super::{TelegramError, AUTH_STATE};
+use super::messages::{is_indexing_in_progress, sync_new_saved_messages};
+use super::{error_code, TelegramError, AUTH_STATE};
+use crate::db::{Database, SettingChanged, SETTING_CHANGED_EVENT};
 use grammers_client::client::updates::UpdatesLike;
+use grammers_client::grammers_tl_types as tl;
 use log;
 use serde_json::json;
-use tauri::{AppHandle, Emitter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Emitter, Listener, Manager};
 use tokio::time::{interval, Duration};
 
+const LIVE_SYNC_SETTING_KEY: &str = "live_sync";
+
+// Mirrors the `live_sync` setting; kept current from `setting-changed` events
+static LIVE_SYNC_ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Read `live_sync` once, then follow changes so toggling it needs no restart
+pub(crate) fn watch_live_sync_setting(app: &AppHandle, db: &Database) {
+    let enabled = db.get_setting(LIVE_SYNC_SETTING_KEY).ok().flatten();
+    LIVE_SYNC_ENABLED.store(is_live_sync_value(enabled.as_deref()), Ordering::Relaxed);
+
+    app.listen(SETTING_CHANGED_EVENT, |event| {
+        match serde_json::from_str::<SettingChanged>(event.payload()) {
+            Ok(change) if change.key == LIVE_SYNC_SETTING_KEY => {
+                let enabled = is_live_sync_value(Some(&change.value));
+                log::info!("Live sync {}", if enabled { "enabled" } else { "disabled" });
+                LIVE_SYNC_ENABLED.store(enabled, Ordering::Relaxed);
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Ignoring malformed setting change event: {}", e),
+        }
+    });
+}
+
 // Background sync task that processes Telegram updates
 pub async fn start_real_time_sync(app: AppHandle) {
     log::info!("Starting real-time Telegram sync background task");
 
     let mut interval = interval(Duration::from_secs(2)); // Check for updates every 2 seconds
 
+    // Set when a Saved Messages update arrives; cleared once the local index caught up
+    let mut saved_sync_pending = false;
+
     loop {
         interval.tick().await;
 
+        // Indexing or backfill may be writing the same rows; wait until they finish
+        if saved_sync_pending && !is_indexing_in_progress() {
+            saved_sync_pending = false;
+            let db = app.state::<Database>().inner().clone();
+            match sync_new_saved_messages(&app, &db).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Live sync indexed {} new saved messages", count),
+                Err(e) => log::warn!("Live sync of saved messages failed: {}", e),
+            }
+        }
+
         // Check if we have an active session
         let updates_stream = {
             let guard = AUTH_STATE.lock().await;
@@ -31,6 +72,13 @@ pub async fn start_real_time_sync(app: AppHandle) {
 
             match timeout_result {
                 Ok(Some(update)) => {
+                    if !saved_sync_pending
+                        && is_live_sync_enabled()
+                        && has_new_saved_message(&update).await
+                    {
+                        saved_sync_pending = true;
+                    }
+
                     if let Err(e) = process_update(&app, update).await {
                         log::warn!("Failed to process Telegram update: {}", e);
                     }
@@ -53,6 +101,53 @@ pub async fn start_real_time_sync(app: AppHandle) {
     log::info!("Real-time sync task stopped");
 }
 
+fn is_live_sync_value(value: Option<&str>) -> bool {
+    matches!(value, Some("1") | Some("true"))
+}
+
+fn is_live_sync_enabled() -> bool {
+    LIVE_SYNC_ENABLED.load(Ordering::Relaxed)
+}
+
+// Saved Messages is the chat with our own user id
+async fn has_new_saved_message(update: &UpdatesLike) -> bool {
+    let self_id = {
+        let guard = AUTH_STATE.lock().await;
+        match guard
+            .as_ref()
+            .and_then(|state| state.owner_context.as_ref())
+        {
+            Some((self_id, _)) => *self_id,
+            None => return false,
+        }
+    };
+
+    let is_saved_message = |update: &tl::enums::Update| match update {
+        tl::enums::Update::NewMessage(new_message) => matches!(
+            &new_message.message,
+            tl::enums::Message::Message(message)
+                if matches!(&message.peer_id, tl::enums::Peer::User(peer) if peer.user_id == self_id)
+        ),
+        _ => false,
+    };
+
+    match update {
+        UpdatesLike::Updates(tl::enums::Updates::UpdateShortMessage(message)) => {
+            message.user_id == self_id
+        }
+        UpdatesLike::Updates(tl::enums::Updates::UpdateShort(short)) => {
+            is_saved_message(&short.update)
+        }
+        UpdatesLike::Updates(tl::enums::Updates::Combined(combined)) => {
+            combined.updates.iter().any(is_saved_message)
+        }
+        UpdatesLike::Updates(tl::enums::Updates::Updates(updates)) => {
+            updates.updates.iter().any(is_saved_message)
+        }
+        _ => false,
+    }
+}
+
 // Process individual Telegram updates
 async fn process_update(app: &AppHandle, update: UpdatesLike) -> Result<(), TelegramError> {
     // Convert the update to a string and check if it contains NewMessage
@@ -71,6 +166,7 @@ async fn process_update(app: &AppHandle, update: UpdatesLike) -> Result<(), Tele
     app.emit("tg-update-received", update_json)
         .map_err(|e| TelegramError {
             message: format!("Failed to emit update event: {}", e),
+            code: error_code::INTERNAL.to_string(),
         })?;
 
     Ok(())
@@ -1,4 +1,4 @@
-use super::TelegramError;
+use super::{error_code, TelegramError};
 #[allow(deprecated)]
 use super::{get_api_id, Client, TlSession};
 use grammers_client::client::updates::UpdatesLike;
@@ -23,10 +23,12 @@ pub fn decode_session(session_data: &str) -> Result<TlSession, TelegramError> {
         .decode(session_data)
         .map_err(|e| TelegramError {
             message: format!("Failed to decode session data: {e}"),
+            code: error_code::NOT_AUTHORIZED.to_string(),
         })?;
 
     TlSession::load(&bytes).map_err(|e| TelegramError {
         message: format!("Failed to load TlSession: {e}"),
+        code: error_code::NOT_AUTHORIZED.to_string(),
     })
 }
 
@@ -1,21 +1,71 @@
 use log;
+use tauri::Manager;
+
+/// Base name of the log file in the app log directory (`skybox.log`).
+pub const LOG_FILE_NAME: &str = "skybox";
+/// The log file is rotated once it reaches this size.
+pub const LOG_MAX_FILE_SIZE: u128 = 5 * 1024 * 1024;
+/// Number of rotated log files kept next to the current one.
+pub const LOG_ROTATED_FILES_KEPT: usize = 3;
+
+// Upper bound for one get_recent_logs call
+const MAX_RECENT_LOG_LINES: usize = 5000;
+
+// Render `{ flow_id: 3 }` as ` flow_id=3`, matching the backend's `key={}` log style
+fn format_context(context: Option<serde_json::Value>) -> String {
+    match context {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::Object(fields)) => fields
+            .into_iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(text) => format!(" {}={}", key, text),
+                other => format!(" {}={}", key, other),
+            })
+            .collect(),
+        Some(other) => format!(" context={}", other),
+    }
+}
+
+#[tauri::command]
+pub fn log_debug(message: String, context: Option<serde_json::Value>) {
+    log::debug!("[React] {}{}", message, format_context(context));
+}
 
 #[tauri::command]
-pub fn log_debug(message: String) {
-    log::debug!("[React] {}", message);
+pub fn log_info(message: String, context: Option<serde_json::Value>) {
+    log::info!("[React] {}{}", message, format_context(context));
 }
 
 #[tauri::command]
-pub fn log_info(message: String) {
-    log::info!("[React] {}", message);
+pub fn log_warn(message: String, context: Option<serde_json::Value>) {
+    log::warn!("[React] {}{}", message, format_context(context));
 }
 
 #[tauri::command]
-pub fn log_warn(message: String) {
-    log::warn!("[React] {}", message);
+pub fn log_error(message: String, context: Option<serde_json::Value>) {
+    log::error!("[React] {}{}", message, format_context(context));
 }
 
 #[tauri::command]
-pub fn log_error(message: String) {
-    log::error!("[React] {}", message);
+pub fn get_recent_logs(app: tauri::AppHandle, lines: usize) -> Result<Vec<String>, String> {
+    let log_path = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve log directory: {}", e))?
+        .join(format!("{}.log", LOG_FILE_NAME));
+
+    // Rotation keeps the file below LOG_MAX_FILE_SIZE, so reading it whole is cheap
+    let content = match std::fs::read(&log_path) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", log_path.display(), e)),
+    };
+
+    let all_lines: Vec<&str> = content.lines().collect();
+    let count = lines.min(MAX_RECENT_LOG_LINES).min(all_lines.len());
+
+    Ok(all_lines[all_lines.len() - count..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect())
 }
@@ -13,7 +13,7 @@ async function log(
   if (!isTauri()) return;
 
   try {
-    await invoke(cmd, { message });
+    await invoke(cmd, { message, context });
   } catch {
     // Silent error handling for logging function
   }
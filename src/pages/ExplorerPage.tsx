@@ -43,14 +43,17 @@ import { resolveThumbnailSrc } from "@/lib/thumbnail-src";
 
 interface FsError {
   message: string;
+  kind?: string;
 }
 
 interface DbError {
   message: string;
+  kind?: string;
 }
 
 interface TelegramError {
   message: string;
+  code?: string;
 }
 
 // Define the FileEntry type to match the Rust struct
@@ -116,6 +119,8 @@ interface TelegramMessage {
   text?: string;
   thumbnail?: string;
   file_reference: string;
+  deduped?: boolean;
+  queued?: boolean;
 }
 
 interface TelegramSavedItem {
@@ -130,6 +135,13 @@ interface TelegramSavedItem {
   file_path: string;
   modified_date: string;
   owner_id: string;
+  pinned?: boolean;
+  image_width?: number | null;
+  image_height?: number | null;
+  indexed_at?: string | null;
+  audio_duration_secs?: number | null;
+  audio_title?: string | null;
+  audio_performer?: string | null;
 }
 
 interface TelegramSavedItemsPage {
@@ -138,8 +150,22 @@ interface TelegramSavedItemsPage {
   next_offset: number;
 }
 
+interface TelegramMediaPreview {
+  path: string;
+  audio?: {
+    duration_secs?: number | null;
+    title?: string | null;
+    performer?: string | null;
+  } | null;
+}
+
 interface TelegramIndexSavedMessagesResult {
   total_new_messages: number;
+  images: number;
+  videos: number;
+  audios: number;
+  documents: number;
+  notes: number;
   started_from_empty_db?: boolean;
 }
 
@@ -166,17 +192,20 @@ interface DownloadProgressPayload {
   progress: number;
   downloadedBytes: number;
   bytesPerSecond?: number | null;
+  etaSecs?: number | null;
   totalBytes?: number | null;
   destinationPath?: string | null;
   message?: string | null;
 }
 
 interface UploadProgressPayload {
+  uploadId: string;
   fileName: string;
   stage: UploadStage;
   progress: number;
   uploadedBytes: number;
   bytesPerSecond?: number | null;
+  etaSecs?: number | null;
   totalBytes?: number | null;
   message?: string | null;
 }
@@ -510,7 +539,8 @@ const savedItemToFileItem = (item: TelegramSavedItem): FileItem => {
       joinPath(item.file_path, resolvedName),
     );
     return {
-      name: resolvedName,
+      // Default folders carry a localized caption; the path keeps file_name
+      name: item.file_caption?.trim() || resolvedName,
       path: folderPath,
       isDirectory: true,
       modifiedAt: item.modified_date,
@@ -685,6 +715,7 @@ export default function ExplorerPage() {
   const uploadProgressEnterFrameRef = useRef<number | null>(null);
   const uploadCancelRequestedRef = useRef(false);
   const currentUploadQueueIndexRef = useRef<number | null>(null);
+  const activeUploadIdRef = useRef<string | null>(null);
   const uploadSpeedSampleRef = useRef({
     fileName: null as string | null,
     latestBytes: 0,
@@ -1877,8 +1908,11 @@ export default function ExplorerPage() {
           await loadSavedItemsPage(path, 0, false);
         }
       } else {
-        const result: FileEntry[] = await invoke("fs_list_dir", { path });
-        const convertedFiles = result.map(convertFileEntryToFileItem);
+        const result: { entries: FileEntry[]; errors: string[] } = await invoke(
+          "read_directory",
+          { path },
+        );
+        const convertedFiles = result.entries.map(convertFileEntryToFileItem);
         setFiles(convertedFiles);
         setCurrentPath(path);
         setSavedItemsOffset(0);
@@ -2376,7 +2410,7 @@ export default function ExplorerPage() {
         }
 
         if (isSavedPreviewableFile(file)) {
-          const previewPath: string = await invoke(
+          const { path: previewPath }: TelegramMediaPreview = await invoke(
             "tg_prepare_saved_media_preview",
             {
               sourcePath: file.path,
@@ -2463,10 +2497,10 @@ export default function ExplorerPage() {
     setMediaViewerSrc(null);
     setMediaViewerError(null);
 
-    invoke<string>("tg_prepare_saved_media_preview", {
+    invoke<TelegramMediaPreview>("tg_prepare_saved_media_preview", {
       sourcePath: currentMediaViewerFile.path,
     })
-      .then((localPath) => {
+      .then(({ path: localPath }) => {
         if (cancelled) {
           return;
         }
@@ -3879,6 +3913,7 @@ export default function ExplorerPage() {
           }
 
           trackUploadSpeedSample(payload);
+          activeUploadIdRef.current = payload.uploadId;
 
           const progressFraction =
             Math.max(0, Math.min(100, payload.progress)) / 100;
@@ -4000,7 +4035,10 @@ export default function ExplorerPage() {
           );
 
           uploadedCount += 1;
-          uploadedMessages.push(uploadedMessage);
+          // Offline uploads are queued and sent by the backend once back online
+          if (!uploadedMessage.queued) {
+            uploadedMessages.push(uploadedMessage);
+          }
           setUploadQueueItems((prev) =>
             prev.map((item, itemIndex) =>
               itemIndex === index
@@ -4008,7 +4046,9 @@ export default function ExplorerPage() {
                     ...item,
                     status: "completed",
                     progress: 100,
-                    message: undefined,
+                    message: uploadedMessage.queued
+                      ? "Queued until online"
+                      : undefined,
                   }
                 : item,
             ),
@@ -4172,6 +4212,7 @@ export default function ExplorerPage() {
     if (activeUploadFileName) {
       try {
         await invoke("tg_cancel_saved_file_upload", {
+          uploadId: activeUploadIdRef.current,
           fileName: activeUploadFileName,
         });
       } catch (error) {
@@ -19,10 +19,12 @@ import appStartIcon from "@/assets/images/icon.png";
 
 interface DbError {
   message: string;
+  kind?: string;
 }
 
 interface TelegramError {
   message: string;
+  code?: string;
 }
 
 interface Session {
@@ -47,8 +49,10 @@ interface TelegramAuthResult {
 
 interface QrLoginData {
   qr_url: string;
+  qr_png_data_url: string | null;
   expires_at_unix: number;
   flow_id: number;
+  login_result?: QrPollResult | null;
 }
 
 interface QrPollResult {
@@ -386,6 +390,17 @@ export default function LoginPage() {
       logger.info("generateQrCode: Calling tg_generate_qr_code");
       const result: QrLoginData = await invoke("tg_generate_qr_code");
 
+      // The session was already authorized, so there is nothing to scan
+      if (result.login_result?.status === "Success") {
+        const userInfo = result.login_result.user_info;
+        toast({
+          title: "Login Successful!",
+          description: `Welcome ${userInfo?.first_name || userInfo?.username || "user"}!`,
+        });
+        navigate("/explorer");
+        return;
+      }
+
       if (!result.qr_url) {
         throw new Error("No QR URL returned from backend");
       }